@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE backtest_metrics DROP COLUMN drawdown_percent;
+ALTER TABLE backtest_metrics DROP COLUMN max_drawdown_duration;
+ALTER TABLE backtest_metrics DROP COLUMN max_drawdown_percent;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE backtest_metrics ADD COLUMN max_drawdown_percent REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN max_drawdown_duration INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN drawdown_percent REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+-- Add down migration script here
+ALTER TABLE backtest_metrics DROP COLUMN ulcer_index;
+ALTER TABLE backtest_metrics DROP COLUMN downside_deviation;
+ALTER TABLE backtest_metrics DROP COLUMN volatility;
+ALTER TABLE backtest_metrics DROP COLUMN cagr;
+ALTER TABLE backtest_metrics DROP COLUMN calmar;
+ALTER TABLE backtest_metrics DROP COLUMN sharpe;
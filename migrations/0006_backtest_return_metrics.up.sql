@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE backtest_metrics ADD COLUMN sharpe REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN calmar REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN cagr REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN volatility REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN downside_deviation REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN ulcer_index REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE
+    IF NOT EXISTS backtest_optimizations (
+        id INTEGER PRIMARY KEY,
+        symbol TEXT NOT NULL,
+        exchange TEXT NOT NULL,
+        market_data_type TEXT NOT NULL,
+        date_start INTEGER NOT NULL,
+        date_end INTEGER NOT NULL,
+        rank_by TEXT NOT NULL,
+        request TEXT NOT NULL,
+        results TEXT NOT NULL
+    );
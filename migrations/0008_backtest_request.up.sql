@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE backtest_data ADD COLUMN request TEXT;
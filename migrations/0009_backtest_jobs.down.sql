@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS backtest_jobs_status;
+DROP TABLE IF EXISTS backtest_jobs;
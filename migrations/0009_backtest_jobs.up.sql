@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE
+    IF NOT EXISTS backtest_jobs (
+        id INTEGER PRIMARY KEY,
+        user_id INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        progress REAL NOT NULL DEFAULT 0,
+        request TEXT NOT NULL,
+        result_id INTEGER,
+        error TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL,
+        FOREIGN KEY (result_id) REFERENCES backtest_data (id) ON UPDATE CASCADE ON DELETE SET NULL
+    );
+
+CREATE INDEX IF NOT EXISTS backtest_jobs_status ON backtest_jobs (status);
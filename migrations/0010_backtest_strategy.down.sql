@@ -0,0 +1,57 @@
+-- Add down migration script here
+ALTER TABLE backtest_jobs DROP COLUMN strategy;
+
+-- The results of the other strategies have no grid columns
+DELETE FROM backtest_data WHERE strategy != 'grid';
+
+CREATE TABLE
+    backtest_data_old (
+        id INTEGER PRIMARY KEY,
+        metrics_id INTEGER NOT NULL,
+        symbol TEXT NOT NULL,
+        exchange TEXT NOT NULL,
+        market_data_type TEXT NOT NULL,
+        chart_market_data_type TEXT NOT NULL,
+        date_start INTEGER NOT NULL,
+        date_end INTEGER NOT NULL,
+        deposit REAL NOT NULL,
+        commission REAL NOT NULL,
+        price_low REAL NOT NULL,
+        price_high REAL NOT NULL,
+        grid_count INTEGER NOT NULL,
+        grid_trigger REAL NOT NULL,
+        grid_sl REAL,
+        grid_tp REAL,
+        sell_all BOOLEAN NOT NULL,
+        positions TEXT NOT NULL,
+        equity TEXT NOT NULL DEFAULT '[]',
+        request TEXT,
+        FOREIGN KEY (metrics_id) REFERENCES backtest_metrics (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
+
+INSERT INTO
+    backtest_data_old (
+        id, metrics_id, symbol, exchange, market_data_type, chart_market_data_type,
+        date_start, date_end, deposit, commission, price_low, price_high, grid_count,
+        grid_trigger, grid_sl, grid_tp, sell_all, positions, equity, request
+    )
+SELECT
+    id, metrics_id, symbol, exchange, market_data_type, chart_market_data_type,
+    date_start, date_end, deposit, commission, COALESCE(price_low, 0), COALESCE(price_high, 0),
+    COALESCE(grid_count, 0), COALESCE(grid_trigger, 0), grid_sl, grid_tp, COALESCE(sell_all, 0),
+    positions, equity, request
+FROM
+    backtest_data;
+
+CREATE TEMP TABLE job_results AS
+SELECT id, result_id FROM backtest_jobs WHERE result_id IS NOT NULL;
+
+DROP TABLE backtest_data;
+
+ALTER TABLE backtest_data_old RENAME TO backtest_data;
+
+UPDATE backtest_jobs
+SET result_id = (SELECT result_id FROM job_results WHERE job_results.id = backtest_jobs.id)
+WHERE id IN (SELECT id FROM job_results);
+
+DROP TABLE job_results;
@@ -0,0 +1,57 @@
+-- Add up migration script here
+-- The results of any strategy are stored in backtest_data, so the grid columns are nullable.
+-- The parameters of a strategy are in the request.
+CREATE TABLE
+    backtest_data_new (
+        id INTEGER PRIMARY KEY,
+        metrics_id INTEGER NOT NULL,
+        strategy TEXT NOT NULL DEFAULT 'grid',
+        symbol TEXT NOT NULL,
+        exchange TEXT NOT NULL,
+        market_data_type TEXT NOT NULL,
+        chart_market_data_type TEXT NOT NULL,
+        date_start INTEGER NOT NULL,
+        date_end INTEGER NOT NULL,
+        deposit REAL NOT NULL,
+        commission REAL NOT NULL,
+        price_low REAL,
+        price_high REAL,
+        grid_count INTEGER,
+        grid_trigger REAL,
+        grid_sl REAL,
+        grid_tp REAL,
+        sell_all BOOLEAN,
+        positions TEXT NOT NULL,
+        equity TEXT NOT NULL DEFAULT '[]',
+        request TEXT,
+        FOREIGN KEY (metrics_id) REFERENCES backtest_metrics (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
+
+INSERT INTO
+    backtest_data_new (
+        id, metrics_id, symbol, exchange, market_data_type, chart_market_data_type,
+        date_start, date_end, deposit, commission, price_low, price_high, grid_count,
+        grid_trigger, grid_sl, grid_tp, sell_all, positions, equity, request
+    )
+SELECT
+    id, metrics_id, symbol, exchange, market_data_type, chart_market_data_type,
+    date_start, date_end, deposit, commission, price_low, price_high, grid_count,
+    grid_trigger, grid_sl, grid_tp, sell_all, positions, equity, request
+FROM
+    backtest_data;
+
+-- Dropping the table sets the result ids of the jobs to NULL, so they are restored after it
+CREATE TEMP TABLE job_results AS
+SELECT id, result_id FROM backtest_jobs WHERE result_id IS NOT NULL;
+
+DROP TABLE backtest_data;
+
+ALTER TABLE backtest_data_new RENAME TO backtest_data;
+
+UPDATE backtest_jobs
+SET result_id = (SELECT result_id FROM job_results WHERE job_results.id = backtest_jobs.id)
+WHERE id IN (SELECT id FROM job_results);
+
+DROP TABLE job_results;
+
+ALTER TABLE backtest_jobs ADD COLUMN strategy TEXT NOT NULL DEFAULT 'grid';
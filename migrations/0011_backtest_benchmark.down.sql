@@ -0,0 +1,10 @@
+-- Add down migration script here
+ALTER TABLE backtest_data DROP COLUMN benchmark_equity;
+ALTER TABLE backtest_metrics DROP COLUMN down_capture;
+ALTER TABLE backtest_metrics DROP COLUMN up_capture;
+ALTER TABLE backtest_metrics DROP COLUMN information_ratio;
+ALTER TABLE backtest_metrics DROP COLUMN correlation;
+ALTER TABLE backtest_metrics DROP COLUMN beta;
+ALTER TABLE backtest_metrics DROP COLUMN alpha;
+ALTER TABLE backtest_metrics DROP COLUMN excess_return;
+ALTER TABLE backtest_metrics DROP COLUMN benchmark_return;
@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE backtest_metrics ADD COLUMN benchmark_return REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN excess_return REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN alpha REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN beta REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN correlation REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN information_ratio REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN up_capture REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_metrics ADD COLUMN down_capture REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_data ADD COLUMN benchmark_equity TEXT NOT NULL DEFAULT '[]';
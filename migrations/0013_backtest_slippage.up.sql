@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE backtest_metrics ADD COLUMN slippage REAL NOT NULL DEFAULT 0;
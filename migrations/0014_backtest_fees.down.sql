@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE backtest_metrics DROP COLUMN fees;
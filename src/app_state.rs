@@ -2,10 +2,11 @@ use std::sync::Arc;
 
 use tera::Tera;
 
-use crate::config::AppSettings;
+use crate::{config::AppSettings, jobs::RunningJobs};
 
 pub struct AppState {
     pub app_settings: Arc<AppSettings>,
     pub tera: Arc<Tera>,
     pub pool: Arc<sqlx::sqlite::SqlitePool>,
+    pub jobs: RunningJobs,
 }
@@ -1,68 +1,441 @@
-use std::path::PathBuf;
+use std::{fs, path::PathBuf};
 
-use crate::data_models::market_data::{metrics::Metrics, position::Position};
+use log::{error, info};
+use rayon::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::data_models::market_data::{
+    enums::{Annualization, MarketDataType},
+    equity::EquityPoint,
+    metrics::Metrics,
+    position::Position,
+};
 
 use super::{
+    equity::{merge_curves, EquityRecorder},
+    portfolio::Portfolio,
+    progress::Progress,
     settings::{BacktestSettings, StrategySettings},
-    strategies::{strategy_trait::Strategy, strategy_utils::get_klines},
+    strategies::{
+        strategy_trait::Strategy,
+        strategy_utils::{get_funding_rates, get_kline_source},
+    },
 };
 
-pub fn run_sequentially<S: Strategy>(
+/// The state of a backtest between two chunks of the period
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint<S> {
+    /// The start of the next chunk
+    pub date: i64,
+    pub strategies: Vec<S>,
+    pub portfolio: Portfolio,
+    /// The equity of the strategies run in parallel, it's merged into the portfolio at the end
+    pub equity: Vec<EquityRecorder>,
+}
+
+/// Runs the strategies in parallel if they don't share the cash, otherwise sequentially
+pub fn run<S: Strategy + Clone + Send + Serialize + DeserializeOwned>(
+    backtest_settings: BacktestSettings,
+    strategies: &mut Vec<S>,
+    portfolio: &mut Portfolio,
+    data_path: PathBuf,
+) {
+    let progress = backtest_settings.progress.clone();
+    if portfolio.allocation.is_shared() {
+        run_sequentially(backtest_settings, strategies, portfolio, data_path);
+    } else {
+        run_parallel(backtest_settings, strategies, portfolio, data_path);
+    }
+    if !progress.is_cancelled() {
+        progress.finish();
+    }
+}
+
+pub fn run_sequentially<S: Strategy + Clone + Serialize + DeserializeOwned>(
+    backtest_settings: BacktestSettings,
+    strategies: &mut Vec<S>,
+    portfolio: &mut Portfolio,
+    data_path: PathBuf,
+) {
+    backtest_settings.progress.start(
+        backtest_settings.date_start,
+        backtest_settings.date_end,
+        &[portfolio.cash],
+    );
+    let resume_date = resume(&backtest_settings, strategies, portfolio, &mut Vec::new());
+    for range in backtest_time_range(&backtest_settings) {
+        if range.0 < resume_date {
+            continue;
+        }
+        for strategy in strategies.iter_mut() {
+            load_klines(&backtest_settings, strategy, range, &data_path);
+        }
+        for_each_timestamp(
+            &backtest_settings,
+            strategies,
+            range,
+            |strategies, timestamp| {
+                for (i, strategy) in strategies.iter_mut().enumerate() {
+                    with_portfolio(portfolio, i, strategy, |s| s.run_kline(timestamp));
+                }
+                if portfolio
+                    .equity
+                    .record(timestamp, portfolio.cash, strategies)
+                {
+                    report_progress(
+                        &backtest_settings.progress,
+                        0,
+                        strategies,
+                        &portfolio.equity,
+                    );
+                }
+            },
+        );
+        if backtest_settings.progress.is_cancelled() {
+            break;
+        }
+        save_checkpoint(&backtest_settings, range.1, strategies, portfolio, &[]);
+    }
+    for (i, strategy) in strategies.iter_mut().enumerate() {
+        with_portfolio(portfolio, i, strategy, close_strategy);
+    }
+    if let Some(date) = last_date(strategies) {
+        portfolio.equity.finish(date, portfolio.cash, strategies);
+    }
+    remove_checkpoint(&backtest_settings);
+}
+
+/// Runs every strategy on its own thread of the rayon pool.
+/// The strategies don't share anything, so each one loads and iterates its own klines.
+/// The cash of the strategies is settled with the portfolio after the run.
+pub fn run_parallel<S: Strategy + Clone + Send + Serialize + DeserializeOwned>(
     backtest_settings: BacktestSettings,
     strategies: &mut Vec<S>,
+    portfolio: &mut Portfolio,
     data_path: PathBuf,
 ) {
-    let mut time_range = vec![(backtest_settings.date_start, backtest_settings.date_end)];
-    if let Some(recomended_period) = backtest_settings.market_data_type.period() {
-        time_range = generate_time_range(
+    assert!(
+        !portfolio.allocation.is_shared(),
+        "Strategies with the shared cash can't run in parallel"
+    );
+    let initial_cash: Vec<f64> = (0..strategies.len())
+        .map(|i| portfolio.available(i))
+        .collect();
+    for (strategy, &cash) in strategies.iter_mut().zip(initial_cash.iter()) {
+        strategy.set_current_budget(cash);
+    }
+    let mut equity = vec![EquityRecorder::new(portfolio.equity.interval); strategies.len()];
+    backtest_settings.progress.start(
+        backtest_settings.date_start,
+        backtest_settings.date_end,
+        &initial_cash,
+    );
+    let resume_date = resume(&backtest_settings, strategies, portfolio, &mut equity);
+    for range in backtest_time_range(&backtest_settings) {
+        if range.0 < resume_date {
+            continue;
+        }
+        strategies
+            .par_iter_mut()
+            .zip(equity.par_iter_mut())
+            .enumerate()
+            .for_each(|(i, (strategy, equity))| {
+                run_range(&backtest_settings, i, strategy, equity, range, &data_path)
+            });
+        if backtest_settings.progress.is_cancelled() {
+            break;
+        }
+        save_checkpoint(&backtest_settings, range.1, strategies, portfolio, &equity);
+    }
+    strategies
+        .par_iter_mut()
+        .zip(equity.par_iter_mut())
+        .for_each(|(strategy, equity)| finish_strategy(strategy, equity));
+    for (i, strategy) in strategies.iter().enumerate() {
+        portfolio.apply(i, strategy.current_budget() - initial_cash[i]);
+    }
+    let curves: Vec<Vec<EquityPoint>> = equity.into_iter().map(|e| e.points).collect();
+    portfolio.equity.points = merge_curves(&curves, &initial_cash);
+    remove_checkpoint(&backtest_settings);
+}
+
+/// Lends the portfolio cash to the strategy for the `f` call and takes back the change
+fn with_portfolio<S: Strategy>(
+    portfolio: &mut Portfolio,
+    index: usize,
+    strategy: &mut S,
+    f: impl FnOnce(&mut S),
+) {
+    let budget = portfolio.available(index);
+    strategy.set_current_budget(budget);
+    f(strategy);
+    portfolio.apply(index, strategy.current_budget() - budget);
+}
+
+/// Runs the strategy with the `index` over the `range`
+fn run_range<S: Strategy>(
+    backtest_settings: &BacktestSettings,
+    index: usize,
+    strategy: &mut S,
+    equity: &mut EquityRecorder,
+    range: (i64, i64),
+    data_path: &PathBuf,
+) {
+    load_klines(backtest_settings, strategy, range, data_path);
+    for_each_timestamp(
+        backtest_settings,
+        std::slice::from_mut(strategy),
+        range,
+        |strategies, timestamp| {
+            strategies[0].run_kline(timestamp);
+            if equity.record(timestamp, strategies[0].current_budget(), strategies) {
+                report_progress(&backtest_settings.progress, index, strategies, equity);
+            }
+        },
+    );
+}
+
+/// Reports the positions of the strategies and their last equity sample
+fn report_progress<S: Strategy>(
+    progress: &Progress,
+    part: usize,
+    strategies: &[S],
+    equity: &EquityRecorder,
+) {
+    if let Some(point) = equity.points.last() {
+        progress.record(
+            part,
+            point.date,
+            strategies.iter().map(|s| s.positions_opened().len()).sum(),
+            strategies.iter().map(|s| s.positions_closed().len()).sum(),
+            point.equity,
+        );
+    }
+}
+
+fn finish_strategy<S: Strategy>(strategy: &mut S, equity: &mut EquityRecorder) {
+    close_strategy(strategy);
+    if let Some(date) = last_date(std::slice::from_ref(strategy)) {
+        equity.finish(
+            date,
+            strategy.current_budget(),
+            std::slice::from_ref(strategy),
+        );
+    }
+}
+
+/// Restores the state of the last checkpoint and returns the date to continue from
+fn resume<S: DeserializeOwned>(
+    backtest_settings: &BacktestSettings,
+    strategies: &mut Vec<S>,
+    portfolio: &mut Portfolio,
+    equity: &mut Vec<EquityRecorder>,
+) -> i64 {
+    let Some(checkpoint) = &backtest_settings.checkpoint else {
+        return i64::MIN;
+    };
+    let Ok(data) = fs::read_to_string(&checkpoint.path) else {
+        return i64::MIN;
+    };
+    match serde_json::from_str::<Checkpoint<S>>(&data) {
+        Ok(checkpoint) => {
+            info!("Resuming the backtest from {}", checkpoint.date);
+            *strategies = checkpoint.strategies;
+            *portfolio = checkpoint.portfolio;
+            *equity = checkpoint.equity;
+            checkpoint.date
+        }
+        Err(e) => {
+            error!("Error reading the checkpoint {:?}: {}", checkpoint.path, e);
+            i64::MIN
+        }
+    }
+}
+
+/// Saves the state of the backtest before the chunk starting at the `date`.
+/// The file is replaced at once, so a crash while saving keeps the previous checkpoint.
+fn save_checkpoint<S: Clone + Serialize>(
+    backtest_settings: &BacktestSettings,
+    date: i64,
+    strategies: &[S],
+    portfolio: &Portfolio,
+    equity: &[EquityRecorder],
+) {
+    let Some(checkpoint) = &backtest_settings.checkpoint else {
+        return;
+    };
+    if date >= backtest_settings.date_end {
+        return;
+    }
+    let data = serde_json::to_string(&Checkpoint {
+        date,
+        strategies: strategies.to_vec(),
+        portfolio: portfolio.clone(),
+        equity: equity.to_vec(),
+    })
+    .unwrap();
+    let temp_path = checkpoint.path.with_extension("tmp");
+    let result = checkpoint
+        .path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&temp_path, data))
+        .and_then(|_| fs::rename(&temp_path, &checkpoint.path));
+    if let Err(e) = result {
+        error!("Error saving the checkpoint {:?}: {}", checkpoint.path, e);
+    }
+}
+
+fn remove_checkpoint(backtest_settings: &BacktestSettings) {
+    if let Some(checkpoint) = &backtest_settings.checkpoint {
+        let _ = fs::remove_file(&checkpoint.path);
+    }
+}
+
+/// Splits the period into the chunks loaded at once, a checkpoint is saved after every chunk
+fn backtest_time_range(backtest_settings: &BacktestSettings) -> Vec<(i64, i64)> {
+    let checkpoint_interval = backtest_settings
+        .checkpoint
+        .as_ref()
+        .map(|checkpoint| checkpoint.interval)
+        .filter(|&interval| interval > 0);
+    match [
+        backtest_settings.market_data_type.period(),
+        checkpoint_interval,
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+    {
+        Some(period) => generate_time_range(
             backtest_settings.date_start,
             backtest_settings.date_end,
-            recomended_period,
-        );
+            period,
+        ),
+        None => vec![(backtest_settings.date_start, backtest_settings.date_end)],
     }
+}
 
-    for range in time_range {
-        for strategy in strategies.iter_mut() {
-            let klines = get_klines(
-                data_path.clone(),
-                backtest_settings.exchange.clone(),
-                strategy.strategy_settings().symbol.clone(),
-                strategy.strategy_settings().market_data_type.clone(),
-                backtest_settings.date_start,
-                backtest_settings.date_end,
-            );
-            strategy.set_klines(klines);
+/// Calls `f` for every timestamp of the `range` in the time order.
+/// The klines have a fixed interval, but the trades can come at any moment,
+/// so for the trades the next timestamp is the earliest next trade of all the strategies.
+/// It stops if the backtest is cancelled.
+fn for_each_timestamp<S: Strategy>(
+    backtest_settings: &BacktestSettings,
+    strategies: &mut [S],
+    range: (i64, i64),
+    mut f: impl FnMut(&mut [S], i64),
+) {
+    let progress = &backtest_settings.progress;
+    let mut f = |strategies: &mut [S], timestamp: i64| {
+        f(strategies, timestamp);
+        progress.update(
+            timestamp,
+            backtest_settings.date_start,
+            backtest_settings.date_end,
+        );
+        !progress.is_cancelled()
+    };
+    match backtest_settings.market_data_type {
+        MarketDataType::Trade => {
+            while let Some(timestamp) = strategies
+                .iter()
+                .filter_map(|strategy| strategy.klines().peek())
+                .map(|kline| kline.date)
+                .min()
+            {
+                if !f(strategies, timestamp) {
+                    return;
+                }
+            }
         }
-        for timestamp in generate_time_period(
-            range.0,
-            range.1,
-            backtest_settings.market_data_type.value().1,
-        ) {
-            for strategy in strategies.iter_mut() {
-                strategy.run_kline(timestamp);
+        _ => {
+            for timestamp in generate_time_period(
+                range.0,
+                range.1,
+                backtest_settings.market_data_type.value().1,
+            ) {
+                if !f(strategies, timestamp) {
+                    return;
+                }
             }
         }
     }
-    for strategy in strategies {
-        strategy.close_all_positions(
-            strategy.klines().last().unwrap().date,
-            strategy.klines().last().unwrap().close,
-        )
+}
+
+/// Opens the klines and loads the funding rates of the `range` for the strategy.
+/// The end of the range isn't included, it's the start of the next one.
+fn load_klines<S: Strategy>(
+    backtest_settings: &BacktestSettings,
+    strategy: &mut S,
+    range: (i64, i64),
+    data_path: &PathBuf,
+) {
+    let klines = get_kline_source(
+        data_path.clone(),
+        backtest_settings.exchange.clone(),
+        strategy.strategy_settings().symbol.clone(),
+        strategy.strategy_settings().market_data_type.clone(),
+        range.0,
+        range.1 - 1,
+    )
+    .with_last_kline(strategy.klines().last_kline());
+    strategy.set_klines(klines);
+    // Only the perpetual futures positions pay the funding
+    if strategy.strategy_settings().futures.is_some() {
+        let funding_rates = get_funding_rates(
+            data_path.clone(),
+            backtest_settings.exchange.clone(),
+            strategy.strategy_settings().symbol.clone(),
+            range.0,
+            range.1 - 1,
+        );
+        strategy.set_funding_rates(funding_rates.into());
+    }
+}
+
+/// Returns the date of the last kline run by the strategies
+fn last_date<S: Strategy>(strategies: &[S]) -> Option<i64> {
+    strategies
+        .iter()
+        .filter_map(|strategy| strategy.klines().last_kline())
+        .map(|kline| kline.date)
+        .max()
+}
+
+fn close_strategy<S: Strategy>(strategy: &mut S) {
+    if let Some(kline) = strategy.klines().last_kline() {
+        strategy.close_all_positions(&kline)
     }
 }
 
 pub fn strategies_settings(backtest_settings: BacktestSettings) -> Vec<StrategySettings> {
+    let deposits = backtest_settings
+        .allocation
+        .deposits(backtest_settings.deposit, backtest_settings.symbols.len());
     backtest_settings
         .symbols
         .iter()
-        .map(|s| StrategySettings {
+        .zip(deposits)
+        .enumerate()
+        .map(|(i, (s, deposit))| StrategySettings {
             symbol: s.clone(),
             exchange: backtest_settings.exchange.clone(),
             market_data_type: backtest_settings.market_data_type.clone(),
-            deposit: backtest_settings.deposit,
+            deposit,
             commission: backtest_settings.commission,
             date_start: backtest_settings.date_start,
             date_end: backtest_settings.date_end,
+            price_path: backtest_settings.price_path.clone(),
+            timeframes: backtest_settings.timeframes.clone(),
+            // Every strategy gets its own sequence of the ids
+            seed: backtest_settings
+                .seed
+                .map(|seed| seed.wrapping_add(i as u64)),
+            futures: backtest_settings.futures.clone(),
+            execution: backtest_settings.execution.clone(),
+            fees: backtest_settings.fees.clone(),
+            rules: None,
         })
         .collect()
 }
@@ -75,19 +448,574 @@ pub fn get_positions_from_strategies<T: Strategy>(strategies: Vec<T>) -> Vec<Pos
         .collect()
 }
 
-pub fn get_metrics(positions: &Vec<Position>, start_deposit: f64, finish_deposit: f64) -> Metrics {
-    Metrics::new(&positions, start_deposit, finish_deposit)
+pub fn get_metrics(
+    positions: &Vec<Position>,
+    start_deposit: f64,
+    finish_deposit: f64,
+    equity: &Vec<EquityPoint>,
+    annualization: &Annualization,
+) -> Metrics {
+    Metrics::new(
+        &positions,
+        start_deposit,
+        finish_deposit,
+        equity,
+        annualization,
+    )
 }
 
-pub fn generate_time_period(date_start: i64, date_end: i64, step: i64) -> Vec<i64> {
-    (date_start..date_end).step_by(step as usize).collect()
+pub fn generate_time_period(
+    date_start: i64,
+    date_end: i64,
+    step: i64,
+) -> impl Iterator<Item = i64> {
+    (date_start..date_end).step_by(step as usize)
 }
 
 pub fn generate_time_range(date_start: i64, date_end: i64, period: i64) -> Vec<(i64, i64)> {
     (date_start..date_end)
         .step_by(period as usize)
-        .collect::<Vec<i64>>()
-        .iter()
-        .map(|&start| (start, start + period))
+        .map(|start| (start, (start + period).min(date_end)))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all};
+
+    use crate::{
+        backtest::{
+            execution::ExecutionSettings,
+            portfolio::Allocation,
+            settings::CheckpointSettings,
+            strategies::grid::{bot::GridBot, settings::GridSettings, strategy::GridStrategy},
+        },
+        data_handlers::bin_files::{bin_file_name, create_and_write_to_file},
+        data_models::market_data::{
+            enums::PricePath, kline::KLine, market_trade::MarketTrade, order::Order,
+        },
+    };
+
+    use super::*;
+
+    const DATE_START: i64 = 1_600_000_000_000;
+    const MINUTE: i64 = 60 * 1000;
+
+    fn write_klines(data_path: &PathBuf, symbol: &str, phase: f64) {
+        let klines: Vec<KLine> = (0..600)
+            .map(|i| {
+                let close = 50.0 + 40.0 * ((i as f64) / 20.0 + phase).sin();
+                KLine {
+                    date: DATE_START + i * MINUTE,
+                    open: close,
+                    high: close + 1.0,
+                    low: close - 1.0,
+                    close,
+                    volume: 1.0,
+                }
+            })
+            .collect();
+        let file_path = data_path.join(bin_file_name(
+            "binance".into(),
+            symbol.into(),
+            MarketDataType::KLine1m,
+        ));
+        create_and_write_to_file(&klines, file_path).unwrap();
+    }
+
+    /// Writes the trades with a `step` between the timestamps, two trades per timestamp
+    fn write_trades(data_path: &PathBuf, symbol: &str, step: i64) -> usize {
+        let trades: Vec<MarketTrade> = (0..1200)
+            .map(|i| {
+                let price = 50.0 + 40.0 * ((i as f64) / 40.0).sin();
+                MarketTrade {
+                    id: i,
+                    price,
+                    qty: 1.0,
+                    base_qty: price,
+                    timestamp: DATE_START + i / 2 * step,
+                }
+            })
+            .collect();
+        let file_path = data_path.join(bin_file_name(
+            "binance".into(),
+            symbol.into(),
+            MarketDataType::Trade,
+        ));
+        create_and_write_to_file(&trades, file_path).unwrap();
+        trades.len()
+    }
+
+    fn get_strategies(backtest_settings: &BacktestSettings) -> Vec<GridStrategy> {
+        strategies_settings(backtest_settings.clone())
+            .into_iter()
+            .map(|s| {
+                let bot = GridBot::new(GridSettings::new(
+                    0.0, 100.0, 20, s.deposit, 0.0, None, None, true,
+                ));
+                GridStrategy::new(s, bot)
+            })
+            .collect()
+    }
+
+    fn positions_summary(strategy: &GridStrategy) -> Vec<(String, Vec<Order>, Option<f64>)> {
+        strategy
+            .positions_closed
+            .iter()
+            .map(|p| (p.symbol.clone(), p.orders.clone(), p.pnl))
+            .collect()
+    }
+
+    #[test]
+    fn test_run_parallel_matches_sequential() {
+        let data_path = PathBuf::from("test_run_parallel");
+        create_dir_all(&data_path).unwrap();
+        let symbols = vec!["aaausdt", "bbbusdt", "cccusdt"];
+        for (i, symbol) in symbols.iter().enumerate() {
+            write_klines(&data_path, symbol, i as f64);
+        }
+        let backtest_settings = BacktestSettings {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            exchange: "binance".into(),
+            market_data_type: MarketDataType::KLine1m,
+            date_start: DATE_START,
+            date_end: DATE_START + 600 * MINUTE,
+            deposit: 3000.0,
+            commission: 0.1,
+            allocation: Allocation::FixedSplit,
+            price_path: PricePath::OpenHighLowClose,
+            timeframes: Vec::new(),
+            equity_interval: 10 * MINUTE,
+            annualization: Annualization::default(),
+            seed: None,
+            futures: None,
+            execution: ExecutionSettings::default(),
+            fees: None,
+            checkpoint: None,
+            progress: Progress::default(),
+        };
+
+        let mut sequential = get_strategies(&backtest_settings);
+        let mut sequential_portfolio = Portfolio::from_settings(&backtest_settings);
+        run_sequentially(
+            backtest_settings.clone(),
+            &mut sequential,
+            &mut sequential_portfolio,
+            data_path.clone(),
+        );
+        let mut parallel = get_strategies(&backtest_settings);
+        let mut parallel_portfolio = Portfolio::from_settings(&backtest_settings);
+        run_parallel(
+            backtest_settings.clone(),
+            &mut parallel,
+            &mut parallel_portfolio,
+            data_path.clone(),
+        );
+        remove_dir_all(&data_path).unwrap();
+
+        // The cash is accumulated in a different order, so the float sums may differ a bit
+        assert!((sequential_portfolio.cash - parallel_portfolio.cash).abs() < 1e-6);
+        let (sequential_equity, parallel_equity) = (
+            &sequential_portfolio.equity.points,
+            &parallel_portfolio.equity.points,
+        );
+        // The start isn't aligned with the interval, so it's 61 intervals and the final sample
+        assert_eq!(sequential_equity.len(), 62);
+        assert_eq!(sequential_equity.len(), parallel_equity.len());
+        for (s, p) in sequential_equity.iter().zip(parallel_equity.iter()) {
+            assert_eq!(s.date, p.date);
+            assert!((s.equity - p.equity).abs() < 1e-6);
+            assert!((s.exposure - p.exposure).abs() < 1e-6);
+        }
+        assert!(sequential_equity.iter().any(|p| p.exposure > 0.0));
+        assert!(sequential_equity.last().unwrap().exposure < 1e-6);
+        assert_eq!(sequential.len(), parallel.len());
+        for (s, p) in sequential.iter().zip(parallel.iter()) {
+            assert!(!s.positions_closed.is_empty());
+            assert_eq!(positions_summary(s), positions_summary(p));
+            assert_eq!(s.current_budget, p.current_budget);
+            assert_eq!(s.current_qty, p.current_qty);
+        }
+    }
+
+    #[test]
+    fn test_run_shared_portfolio() {
+        let data_path = PathBuf::from("test_run_shared_portfolio");
+        create_dir_all(&data_path).unwrap();
+        let symbols = vec!["aaausdt", "bbbusdt"];
+        for (i, symbol) in symbols.iter().enumerate() {
+            write_klines(&data_path, symbol, i as f64);
+        }
+        let backtest_settings = BacktestSettings {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            exchange: "binance".into(),
+            market_data_type: MarketDataType::KLine1m,
+            date_start: DATE_START,
+            date_end: DATE_START + 600 * MINUTE,
+            deposit: 1000.0,
+            commission: 0.0,
+            allocation: Allocation::FirstCome,
+            price_path: PricePath::WorstCase,
+            timeframes: Vec::new(),
+            equity_interval: 0,
+            annualization: Annualization::default(),
+            seed: None,
+            futures: None,
+            execution: ExecutionSettings::default(),
+            fees: None,
+            checkpoint: None,
+            progress: Progress::default(),
+        };
+
+        let mut strategies = get_strategies(&backtest_settings);
+        let mut portfolio = Portfolio::from_settings(&backtest_settings);
+        run(
+            backtest_settings.clone(),
+            &mut strategies,
+            &mut portfolio,
+            data_path.clone(),
+        );
+        remove_dir_all(&data_path).unwrap();
+
+        let positions = get_positions_from_strategies(strategies.clone());
+        assert!(strategies.iter().all(|s| !s.positions_closed.is_empty()));
+        let pnl = positions.iter().map(|p| p.pnl.unwrap()).sum::<f64>();
+        assert!((portfolio.cash - (portfolio.deposit + pnl)).abs() < 1e-6);
+        // Every bar is sampled, the last sample is after the positions are closed
+        let equity = &portfolio.equity.points;
+        assert_eq!(equity.len(), 600);
+        assert_eq!(equity[0].equity, portfolio.deposit);
+        assert!((equity.last().unwrap().equity - portfolio.cash).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_run_trades() {
+        let data_path = PathBuf::from("test_run_trades");
+        create_dir_all(&data_path).unwrap();
+        let symbols = vec!["aaausdt", "bbbusdt"];
+        let trades_len: Vec<usize> = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| write_trades(&data_path, symbol, 700 + 300 * i as i64))
+            .collect();
+        let mut backtest_settings = BacktestSettings {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            exchange: "binance".into(),
+            market_data_type: MarketDataType::Trade,
+            date_start: DATE_START,
+            date_end: DATE_START + 600 * MINUTE,
+            deposit: 1000.0,
+            commission: 0.0,
+            allocation: Allocation::FixedSplit,
+            price_path: PricePath::WorstCase,
+            timeframes: Vec::new(),
+            equity_interval: 0,
+            annualization: Annualization::default(),
+            seed: None,
+            futures: None,
+            execution: ExecutionSettings::default(),
+            fees: None,
+            checkpoint: None,
+            progress: Progress::default(),
+        };
+
+        let mut parallel = get_strategies(&backtest_settings);
+        let mut parallel_portfolio = Portfolio::from_settings(&backtest_settings);
+        run(
+            backtest_settings.clone(),
+            &mut parallel,
+            &mut parallel_portfolio,
+            data_path.clone(),
+        );
+        backtest_settings.allocation = Allocation::FirstCome;
+        let mut shared = get_strategies(&backtest_settings);
+        let mut shared_portfolio = Portfolio::from_settings(&backtest_settings);
+        run(
+            backtest_settings.clone(),
+            &mut shared,
+            &mut shared_portfolio,
+            data_path.clone(),
+        );
+        remove_dir_all(&data_path).unwrap();
+
+        for strategies in [&parallel, &shared] {
+            for (strategy, &len) in strategies.iter().zip(trades_len.iter()) {
+                // Every trade is run, including the ones in the same millisecond
+                assert_eq!(strategy.current_kline_position, len);
+                assert!(!strategy.positions_closed.is_empty());
+            }
+        }
+        let pnl = get_positions_from_strategies(shared)
+            .iter()
+            .map(|p| p.pnl.unwrap())
+            .sum::<f64>();
+        assert!((shared_portfolio.cash - (shared_portfolio.deposit + pnl)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_generate_time_range() {
+        assert_eq!(
+            generate_time_range(0, 25, 10),
+            vec![(0, 10), (10, 20), (20, 25)]
+        );
+    }
+
+    #[test]
+    fn test_run_chunks_matches_whole_range() {
+        let data_path = PathBuf::from("test_run_chunks");
+        create_dir_all(&data_path).unwrap();
+        write_klines(&data_path, "aaausdt", 0.0);
+        let backtest_settings = BacktestSettings {
+            symbols: vec!["aaausdt".into()],
+            exchange: "binance".into(),
+            market_data_type: MarketDataType::KLine1m,
+            date_start: DATE_START,
+            date_end: DATE_START + 600 * MINUTE,
+            deposit: 1000.0,
+            commission: 0.1,
+            allocation: Allocation::FixedSplit,
+            price_path: PricePath::OpenLowHighClose,
+            timeframes: Vec::new(),
+            equity_interval: 0,
+            annualization: Annualization::default(),
+            seed: None,
+            futures: None,
+            execution: ExecutionSettings::default(),
+            fees: None,
+            checkpoint: None,
+            progress: Progress::default(),
+        };
+
+        let mut whole = get_strategies(&backtest_settings).remove(0);
+        let mut equity = EquityRecorder::default();
+        for range in backtest_time_range(&backtest_settings) {
+            run_range(
+                &backtest_settings,
+                0,
+                &mut whole,
+                &mut equity,
+                range,
+                &data_path,
+            );
+        }
+        finish_strategy(&mut whole, &mut equity);
+        let mut chunked = get_strategies(&backtest_settings).remove(0);
+        for range in generate_time_range(
+            backtest_settings.date_start,
+            backtest_settings.date_end,
+            70 * MINUTE,
+        ) {
+            load_klines(&backtest_settings, &mut chunked, range, &data_path);
+            for timestamp in generate_time_period(range.0, range.1, MINUTE) {
+                chunked.run_kline(timestamp);
+            }
+        }
+        close_strategy(&mut chunked);
+        remove_dir_all(&data_path).unwrap();
+
+        // Every kline is run once
+        assert_eq!(whole.current_kline_position, 600);
+        assert_eq!(chunked.current_kline_position, 600);
+        assert!(!whole.positions_closed.is_empty());
+        assert_eq!(positions_summary(&whole), positions_summary(&chunked));
+        assert_eq!(whole.current_budget, chunked.current_budget);
+    }
+
+    #[test]
+    fn test_seeded_run_is_reproducible() {
+        let data_path = PathBuf::from("test_seeded_run");
+        create_dir_all(&data_path).unwrap();
+        let symbols = vec!["aaausdt", "bbbusdt"];
+        for (i, symbol) in symbols.iter().enumerate() {
+            write_klines(&data_path, symbol, i as f64);
+        }
+        let mut backtest_settings = BacktestSettings {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            exchange: "binance".into(),
+            market_data_type: MarketDataType::KLine1m,
+            date_start: DATE_START,
+            date_end: DATE_START + 600 * MINUTE,
+            deposit: 2000.0,
+            commission: 0.1,
+            allocation: Allocation::FixedSplit,
+            price_path: PricePath::OpenHighLowClose,
+            timeframes: Vec::new(),
+            equity_interval: 0,
+            annualization: Annualization::default(),
+            seed: Some(7),
+            futures: None,
+            execution: ExecutionSettings::default(),
+            fees: None,
+            checkpoint: None,
+            progress: Progress::default(),
+        };
+        let positions_json = |backtest_settings: &BacktestSettings| {
+            let mut strategies = get_strategies(backtest_settings);
+            let mut portfolio = Portfolio::from_settings(backtest_settings);
+            run(
+                backtest_settings.clone(),
+                &mut strategies,
+                &mut portfolio,
+                data_path.clone(),
+            );
+            serde_json::to_string(&get_positions_from_strategies(strategies)).unwrap()
+        };
+
+        let seeded = positions_json(&backtest_settings);
+        assert_eq!(seeded, positions_json(&backtest_settings));
+        backtest_settings.seed = None;
+        assert_ne!(seeded, positions_json(&backtest_settings));
+        remove_dir_all(&data_path).unwrap();
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let data_path = PathBuf::from("test_checkpoint");
+        create_dir_all(&data_path).unwrap();
+        let symbols = vec!["aaausdt", "bbbusdt"];
+        for (i, symbol) in symbols.iter().enumerate() {
+            write_klines(&data_path, symbol, i as f64);
+        }
+        let mut backtest_settings = BacktestSettings {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            exchange: "binance".into(),
+            market_data_type: MarketDataType::KLine1m,
+            date_start: DATE_START,
+            date_end: DATE_START + 600 * MINUTE,
+            deposit: 2000.0,
+            commission: 0.1,
+            allocation: Allocation::FixedSplit,
+            price_path: PricePath::OpenHighLowClose,
+            timeframes: Vec::new(),
+            equity_interval: 10 * MINUTE,
+            annualization: Annualization::default(),
+            seed: None,
+            futures: None,
+            execution: ExecutionSettings::default(),
+            fees: None,
+            checkpoint: None,
+            progress: Progress::default(),
+        };
+        let mut reference = get_strategies(&backtest_settings);
+        let mut reference_portfolio = Portfolio::from_settings(&backtest_settings);
+        run(
+            backtest_settings.clone(),
+            &mut reference,
+            &mut reference_portfolio,
+            data_path.clone(),
+        );
+
+        let checkpoint_path = data_path.join("checkpoints").join("backtest.json");
+        backtest_settings.checkpoint = Some(CheckpointSettings {
+            path: checkpoint_path.clone(),
+            interval: 100 * MINUTE,
+        });
+        let ranges = backtest_time_range(&backtest_settings);
+        assert_eq!(ranges.len(), 6);
+        // The run dies after the first three chunks
+        let mut crashed = get_strategies(&backtest_settings);
+        let portfolio = Portfolio::from_settings(&backtest_settings);
+        let mut equity = vec![EquityRecorder::new(portfolio.equity.interval); crashed.len()];
+        for range in ranges[..3].iter() {
+            for (strategy, equity) in crashed.iter_mut().zip(equity.iter_mut()) {
+                run_range(&backtest_settings, 0, strategy, equity, *range, &data_path);
+            }
+            save_checkpoint(&backtest_settings, range.1, &crashed, &portfolio, &equity);
+        }
+        assert!(checkpoint_path.exists());
+        assert!(!crashed[0].positions_closed.is_empty());
+
+        let mut resumed = get_strategies(&backtest_settings);
+        let mut resumed_portfolio = Portfolio::from_settings(&backtest_settings);
+        run(
+            backtest_settings.clone(),
+            &mut resumed,
+            &mut resumed_portfolio,
+            data_path.clone(),
+        );
+        let checkpoint_removed = !checkpoint_path.exists();
+        remove_dir_all(&data_path).unwrap();
+
+        assert!(checkpoint_removed);
+        // The positions closed before the crash are taken from the checkpoint
+        assert_eq!(
+            resumed[0].positions_closed[0].id,
+            crashed[0].positions_closed[0].id
+        );
+        for (r, s) in reference.iter().zip(resumed.iter()) {
+            assert_eq!(positions_summary(r), positions_summary(s));
+        }
+        assert!((reference_portfolio.cash - resumed_portfolio.cash).abs() < 1e-6);
+        let (reference_equity, resumed_equity) = (
+            &reference_portfolio.equity.points,
+            &resumed_portfolio.equity.points,
+        );
+        assert_eq!(reference_equity.len(), resumed_equity.len());
+        for (r, s) in reference_equity.iter().zip(resumed_equity.iter()) {
+            assert_eq!(r.date, s.date);
+            assert!((r.equity - s.equity).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_cancel() {
+        let data_path = PathBuf::from("test_cancel");
+        create_dir_all(&data_path).unwrap();
+        write_klines(&data_path, "aaausdt", 0.0);
+        let backtest_settings = BacktestSettings {
+            symbols: vec!["aaausdt".into()],
+            exchange: "binance".into(),
+            market_data_type: MarketDataType::KLine1m,
+            date_start: DATE_START,
+            date_end: DATE_START + 600 * MINUTE,
+            deposit: 1000.0,
+            commission: 0.1,
+            allocation: Allocation::FixedSplit,
+            price_path: PricePath::OpenHighLowClose,
+            timeframes: Vec::new(),
+            equity_interval: 10 * MINUTE,
+            annualization: Annualization::default(),
+            seed: None,
+            futures: None,
+            execution: ExecutionSettings::default(),
+            fees: None,
+            checkpoint: None,
+            progress: Progress::default(),
+        };
+        let mut strategies = get_strategies(&backtest_settings);
+        let mut portfolio = Portfolio::from_settings(&backtest_settings);
+        let progress = backtest_settings.progress.clone();
+        run(
+            backtest_settings.clone(),
+            &mut strategies,
+            &mut portfolio,
+            data_path.clone(),
+        );
+        assert_eq!(progress.percent(), 100.0);
+        let positions_number = strategies[0].positions_closed.len();
+        // The last event is the last equity sample before the positions are closed
+        let event = progress.event();
+        let point = portfolio.equity.points.iter().rev().nth(1).unwrap();
+        assert_eq!((event.date, event.equity), (point.date, point.equity));
+        assert!(event.positions_closed > 0);
+
+        // The cancelled backtest stops right after the first timestamp
+        let cancelled = BacktestSettings {
+            progress: Progress::default(),
+            ..backtest_settings
+        };
+        cancelled.progress.cancel();
+        let mut strategies = get_strategies(&cancelled);
+        let mut portfolio = Portfolio::from_settings(&cancelled);
+        run(
+            cancelled.clone(),
+            &mut strategies,
+            &mut portfolio,
+            data_path.clone(),
+        );
+        remove_dir_all(&data_path).unwrap();
+
+        assert!(cancelled.progress.percent() < 1.0);
+        assert!(strategies[0].positions_closed.len() < positions_number);
+    }
+}
@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use crate::data_models::market_data::{equity::EquityPoint, kline::KLine};
+
+use super::{
+    backtest::strategies_settings, settings::BacktestSettings,
+    strategies::strategy_utils::get_kline_source,
+};
+
+/// Returns the equity of buying and holding the symbols of the backtest, sampled at the `dates`
+/// of the strategy equity. Every symbol is bought with the deposit of its strategy.
+pub fn benchmark_equity(
+    backtest_settings: &BacktestSettings,
+    dates: &[i64],
+    data_path: &PathBuf,
+) -> Vec<EquityPoint> {
+    strategies_settings(backtest_settings.clone())
+        .into_iter()
+        .map(|s| {
+            let klines = get_kline_source(
+                data_path.clone(),
+                backtest_settings.exchange.clone(),
+                s.symbol,
+                s.market_data_type,
+                s.date_start,
+                s.date_end - 1,
+            );
+            hold_equity(klines, s.deposit, dates)
+        })
+        .reduce(|total, curve| {
+            total
+                .iter()
+                .zip(curve.iter())
+                .map(|(a, b)| EquityPoint {
+                    date: a.date,
+                    equity: a.equity + b.equity,
+                    cash: a.cash + b.cash,
+                    exposure: a.exposure + b.exposure,
+                    margin: a.margin + b.margin,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the equity of the `deposit` bought at the open of the first kline.
+/// The value at a date is by the close of the last kline before it, it's the cash before the purchase.
+pub fn hold_equity(
+    klines: impl Iterator<Item = KLine>,
+    deposit: f64,
+    dates: &[i64],
+) -> Vec<EquityPoint> {
+    let mut klines = klines.peekable();
+    let mut qty: Option<f64> = None;
+    let mut close = 0.0;
+    dates
+        .iter()
+        .map(|&date| {
+            while let Some(kline) = klines.next_if(|kline| kline.date <= date) {
+                if qty.is_none() && kline.open > 0.0 {
+                    qty = Some(deposit / kline.open);
+                }
+                close = kline.close;
+            }
+            match qty {
+                Some(qty) => EquityPoint {
+                    date,
+                    equity: qty * close,
+                    cash: 0.0,
+                    exposure: qty * close,
+                    margin: 0.0,
+                },
+                None => EquityPoint {
+                    date,
+                    equity: deposit,
+                    cash: deposit,
+                    ..Default::default()
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hold_equity() {
+        let klines = vec![
+            KLine::blank()
+                .with_date(10)
+                .with_open(40.0)
+                .with_close(50.0),
+            KLine::blank()
+                .with_date(20)
+                .with_open(50.0)
+                .with_close(60.0),
+            KLine::blank()
+                .with_date(30)
+                .with_open(60.0)
+                .with_close(20.0),
+        ];
+        let equity = hold_equity(klines.into_iter(), 100.0, &[0, 10, 25, 30]);
+        let values: Vec<(i64, f64, f64)> =
+            equity.iter().map(|p| (p.date, p.equity, p.cash)).collect();
+        assert_eq!(
+            values,
+            vec![
+                (0, 100.0, 100.0),
+                (10, 125.0, 0.0),
+                (25, 150.0, 0.0),
+                (30, 50.0, 0.0)
+            ]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_models::market_data::equity::EquityPoint;
+
+use super::strategies::strategy_trait::Strategy;
+
+/// Samples the equity of the strategies while the backtest runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EquityRecorder {
+    /// The sampling interval in milliseconds, every bar is sampled if it's 0
+    pub interval: i64,
+    pub points: Vec<EquityPoint>,
+}
+
+impl EquityRecorder {
+    pub fn new(interval: i64) -> Self {
+        Self {
+            interval,
+            points: Vec::new(),
+        }
+    }
+
+    /// Records the equity at the `date` if it's the first date of the next interval.
+    /// Returns true if the point is recorded.
+    pub fn record<S: Strategy>(&mut self, date: i64, cash: f64, strategies: &[S]) -> bool {
+        if let Some(last) = self.points.last() {
+            if date <= last.date
+                || (self.interval > 0
+                    && date.div_euclid(self.interval) == last.date.div_euclid(self.interval))
+            {
+                return false;
+            }
+        }
+        self.points.push(equity_point(date, cash, strategies));
+        true
+    }
+
+    /// Records the equity after the positions are closed, the sample of the same date is replaced
+    pub fn finish<S: Strategy>(&mut self, date: i64, cash: f64, strategies: &[S]) {
+        if self.points.last().is_some_and(|last| last.date >= date) {
+            self.points.pop();
+        }
+        self.points.push(equity_point(date, cash, strategies));
+    }
+}
+
+fn equity_point<S: Strategy>(date: i64, cash: f64, strategies: &[S]) -> EquityPoint {
+    let (value, exposure) = strategies
+        .iter()
+        .filter_map(|strategy| {
+            let close = strategy.klines().last_kline()?.close;
+            Some((
+                strategy.current_qty() * close,
+                strategy.current_qty().abs() * close,
+            ))
+        })
+        .fold((0.0, 0.0), |acc, v| (acc.0 + v.0, acc.1 + v.1));
+    EquityPoint {
+        date,
+        equity: cash + value,
+        cash,
+        exposure,
+        margin: strategies
+            .iter()
+            .map(|strategy| strategy.used_margin())
+            .sum(),
+    }
+}
+
+/// Sums the equity curves of the strategies which were run separately.
+/// A curve keeps its last value until its next sample, before the first one it's the `initial_cash`.
+pub fn merge_curves(curves: &[Vec<EquityPoint>], initial_cash: &[f64]) -> Vec<EquityPoint> {
+    let mut dates: Vec<i64> = curves.iter().flatten().map(|p| p.date).collect();
+    dates.sort_unstable();
+    dates.dedup();
+
+    let mut positions = vec![0; curves.len()];
+    dates
+        .iter()
+        .map(|&date| {
+            let mut point = EquityPoint {
+                date,
+                ..Default::default()
+            };
+            for (i, curve) in curves.iter().enumerate() {
+                while positions[i] < curve.len() && curve[positions[i]].date <= date {
+                    positions[i] += 1;
+                }
+                match positions[i].checked_sub(1).map(|p| curve[p]) {
+                    Some(last) => {
+                        point.equity += last.equity;
+                        point.cash += last.cash;
+                        point.exposure += last.exposure;
+                        point.margin += last.margin;
+                    }
+                    None => {
+                        point.equity += initial_cash[i];
+                        point.cash += initial_cash[i];
+                    }
+                }
+            }
+            point
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(date: i64, cash: f64, exposure: f64) -> EquityPoint {
+        EquityPoint {
+            date,
+            equity: cash + exposure,
+            cash,
+            exposure,
+            margin: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_merge_curves() {
+        let curves = vec![
+            vec![point(1, 50.0, 0.0), point(3, 20.0, 35.0)],
+            vec![point(2, 10.0, 45.0), point(3, 60.0, 0.0)],
+        ];
+        assert_eq!(
+            merge_curves(&curves, &[50.0, 50.0]),
+            vec![
+                point(1, 100.0, 0.0),
+                point(2, 60.0, 45.0),
+                point(3, 80.0, 35.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+use crate::data_models::market_data::{enums::Side, kline::KLine};
+
+/// How much worse than the kline price a market order is filled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Slippage {
+    /// A fixed share of the price in basis points
+    Fixed {
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        bps: f64,
+    },
+    /// A share of the kline range, so the volatile klines cost more
+    Volatility {
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        factor: f64,
+    },
+    /// The `bps` of taking the whole kline volume, scaled by the share of the volume
+    /// the order takes. The klines without the volume are taken whole.
+    Volume {
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        bps: f64,
+    },
+}
+
+impl Slippage {
+    pub fn validate(&self) -> Result<(), String> {
+        let value = match self {
+            Slippage::Fixed { bps } | Slippage::Volume { bps } => bps,
+            Slippage::Volatility { factor } => factor,
+        };
+        if *value < 0.0 {
+            return Err("The slippage can't be negative".into());
+        }
+        Ok(())
+    }
+
+    /// Returns the price shift of the order of the `qty` at the `price` of the `kline`
+    pub fn shift(&self, price: f64, qty: f64, kline: &KLine) -> f64 {
+        match self {
+            Slippage::Fixed { bps } => price * bps / 10_000.0,
+            Slippage::Volatility { factor } => (kline.high - kline.low) * factor,
+            Slippage::Volume { bps } => {
+                let participation = match kline.volume > 0.0 {
+                    true => (qty / kline.volume).min(1.0),
+                    false => 1.0,
+                };
+                price * bps / 10_000.0 * participation
+            }
+        }
+    }
+}
+
+/// The delay between sending a market order and its execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Latency {
+    /// The order is executed by the first kline at least the milliseconds after the one it's sent on
+    Ms(i64),
+    /// The order is executed by the kline the number of klines after the one it's sent on
+    Bars(usize),
+}
+
+impl Latency {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Latency::Ms(ms) if *ms < 0 => Err("The latency can't be negative".into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true if the order sent on the kline at the `sent` date and position
+    /// is executed by the kline at the `now` date and position
+    pub fn is_due(&self, sent: (i64, usize), now: (i64, usize)) -> bool {
+        match self {
+            Latency::Ms(ms) => now.0 >= sent.0 + ms,
+            Latency::Bars(bars) => now.1 >= sent.1 + bars,
+        }
+    }
+}
+
+/// The execution models of the market orders, the orders are filled at once
+/// by the kline price if they're not set
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ExecutionSettings {
+    #[serde(default)]
+    pub slippage: Option<Slippage>,
+    #[serde(default)]
+    pub latency: Option<Latency>,
+}
+
+impl ExecutionSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(slippage) = &self.slippage {
+            slippage.validate()?;
+        }
+        if let Some(latency) = &self.latency {
+            latency.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the executed price of the market order of the `qty` at the `price`
+    /// and the cost of the slippage
+    pub fn market_price(&self, price: f64, side: &Side, qty: f64, kline: &KLine) -> (f64, f64) {
+        let shift = self
+            .slippage
+            .as_ref()
+            .map_or(0.0, |slippage| slippage.shift(price, qty, kline));
+        let executed = match side {
+            Side::Buy => price + shift,
+            Side::Sell => (price - shift).max(0.0),
+        };
+        (executed, (executed - price).abs() * qty)
+    }
+
+    /// Returns true if the market order sent on the kline at the `sent` date and position
+    /// is executed by the kline at the `now` date and position
+    pub fn is_due(&self, sent: (i64, usize), now: (i64, usize)) -> bool {
+        self.latency
+            .as_ref()
+            .map_or(true, |latency| latency.is_due(sent, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_market_price() {
+        let kline = KLine::blank()
+            .with_high(110.0)
+            .with_low(90.0)
+            .with_close(100.0)
+            .with_volume(4.0);
+        let execution = |slippage| ExecutionSettings {
+            slippage: Some(slippage),
+            latency: None,
+        };
+
+        assert_eq!(
+            ExecutionSettings::default().market_price(100.0, &Side::Buy, 1.0, &kline),
+            (100.0, 0.0)
+        );
+        assert_eq!(
+            execution(Slippage::Fixed { bps: 25.0 }).market_price(100.0, &Side::Buy, 2.0, &kline),
+            (100.25, 0.5)
+        );
+        assert_eq!(
+            execution(Slippage::Volatility { factor: 0.1 }).market_price(
+                100.0,
+                &Side::Sell,
+                2.0,
+                &kline
+            ),
+            (98.0, 4.0)
+        );
+        // The order takes a quarter of the volume
+        assert_eq!(
+            execution(Slippage::Volume { bps: 100.0 }).market_price(
+                100.0,
+                &Side::Sell,
+                1.0,
+                &kline
+            ),
+            (99.75, 0.25)
+        );
+        let slippage: Slippage = serde_json::from_str(r#"{"model": "fixed", "bps": "5"}"#).unwrap();
+        assert_eq!(slippage, Slippage::Fixed { bps: 5.0 });
+        assert!(Slippage::Volatility { factor: -1.0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_is_due() {
+        let execution = |latency| ExecutionSettings {
+            slippage: None,
+            latency: Some(latency),
+        };
+        assert!(ExecutionSettings::default().is_due((60_000, 1), (60_000, 1)));
+        // The order sent on the 1m kline is executed 200ms later by the next one
+        assert!(!execution(Latency::Ms(200)).is_due((60_000, 1), (60_000, 1)));
+        assert!(execution(Latency::Ms(200)).is_due((60_000, 1), (120_000, 2)));
+        assert!(!execution(Latency::Bars(2)).is_due((60_000, 1), (120_000, 2)));
+        assert!(execution(Latency::Bars(2)).is_due((60_000, 1), (180_000, 3)));
+        let latency: Latency = serde_json::from_str(r#"{"bars": 1}"#).unwrap();
+        assert_eq!(latency, Latency::Bars(1));
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use serde_aux::field_attributes::deserialize_option_number_from_string;
+
+use crate::data_models::market_data::enums::OrderType;
+
+/// The Binance spot fees of the VIP tiers in percent as (maker, taker)
+const SPOT_TIERS: [(f64, f64); 10] = [
+    (0.1, 0.1),
+    (0.09, 0.1),
+    (0.08, 0.1),
+    (0.042, 0.06),
+    (0.042, 0.054),
+    (0.036, 0.048),
+    (0.03, 0.042),
+    (0.024, 0.036),
+    (0.018, 0.03),
+    (0.012, 0.024),
+];
+
+/// The Binance USD-M futures fees of the VIP tiers in percent as (maker, taker)
+const FUTURES_TIERS: [(f64, f64); 10] = [
+    (0.02, 0.05),
+    (0.016, 0.04),
+    (0.014, 0.035),
+    (0.012, 0.032),
+    (0.01, 0.03),
+    (0.008, 0.027),
+    (0.006, 0.025),
+    (0.004, 0.022),
+    (0.002, 0.02),
+    (0.0, 0.017),
+];
+
+/// The BNB discount of the spot and the futures fees in percent
+const SPOT_BNB_DISCOUNT: f64 = 25.0;
+const FUTURES_BNB_DISCOUNT: f64 = 10.0;
+
+/// The asset the fees are charged in
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeAsset {
+    /// The fee is taken from the asset the order receives like on the exchange: the buys pay it
+    /// in the base, so the strategy holds less than it bought, and the sells pay it in the quote
+    Base,
+    /// The fee is taken from the cash
+    #[default]
+    Quote,
+    /// The fee is paid from the balance of a third asset like BNB, the balances of the strategy
+    /// don't change, but the fee is still in the profit of the positions
+    Other,
+}
+
+/// The fee schedule of the backtest, the flat commission is used if it's not set
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct FeeSettings {
+    /// The VIP tier of the exchange fee table
+    #[serde(default)]
+    pub vip_tier: usize,
+    /// The maker fee in percent, it overrides the fee of the tier
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub maker: Option<f64>,
+    /// The taker fee in percent, it overrides the fee of the tier
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub taker: Option<f64>,
+    /// The fees are discounted if they are paid in BNB
+    #[serde(default)]
+    pub bnb_discount: bool,
+    #[serde(default)]
+    pub asset: FeeAsset,
+}
+
+impl FeeSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.vip_tier >= SPOT_TIERS.len() {
+            return Err(format!(
+                "The VIP tier should be from 0 to {}",
+                SPOT_TIERS.len() - 1
+            ));
+        }
+        if self.maker.is_some_and(|fee| fee < 0.0) || self.taker.is_some_and(|fee| fee < 0.0) {
+            return Err("The fees can't be negative".into());
+        }
+        // The discount is given for paying in BNB
+        if self.bnb_discount && self.asset != FeeAsset::Other {
+            return Err("The BNB discount needs the fees in the other asset".into());
+        }
+        Ok(())
+    }
+
+    /// Returns the fees of the spot or the `futures` by the tier table
+    pub fn fees(&self, futures: bool) -> Fees {
+        let (tiers, discount) = match futures {
+            true => (FUTURES_TIERS, FUTURES_BNB_DISCOUNT),
+            false => (SPOT_TIERS, SPOT_BNB_DISCOUNT),
+        };
+        let (maker, taker) = tiers[self.vip_tier];
+        let discount = match self.bnb_discount {
+            true => 1.0 - discount / 100.0,
+            false => 1.0,
+        };
+        Fees {
+            maker: self.maker.unwrap_or(maker) * discount,
+            taker: self.taker.unwrap_or(taker) * discount,
+            asset: self.asset.clone(),
+        }
+    }
+}
+
+/// The fees of the strategy in percent of the order value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fees {
+    pub maker: f64,
+    pub taker: f64,
+    pub asset: FeeAsset,
+}
+
+impl Fees {
+    /// The same `commission` for all the orders, it's charged in the quote
+    pub fn flat(commission: f64) -> Self {
+        Self {
+            maker: commission,
+            taker: commission,
+            asset: FeeAsset::Quote,
+        }
+    }
+
+    /// Returns the fee of the order in percent. The limit orders and the take profits rest
+    /// in the order book, so they are the makers, the rest are executed by the market.
+    pub fn rate(&self, order_type: &OrderType) -> f64 {
+        match order_type {
+            OrderType::Limit | OrderType::TakeProfit => self.maker,
+            _ => self.taker,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fees() {
+        let settings = FeeSettings {
+            vip_tier: 3,
+            ..Default::default()
+        };
+        let fees = settings.fees(false);
+        assert_eq!((fees.maker, fees.taker), (0.042, 0.06));
+        assert_eq!(fees.rate(&OrderType::Limit), 0.042);
+        assert_eq!(fees.rate(&OrderType::TakeProfit), 0.042);
+        assert_eq!(fees.rate(&OrderType::Stop), 0.06);
+        assert_eq!(fees.rate(&OrderType::Market), 0.06);
+        let fees = settings.fees(true);
+        assert_eq!((fees.maker, fees.taker), (0.012, 0.032));
+
+        // The own fees override the tier and get the discount
+        let settings = FeeSettings {
+            vip_tier: 0,
+            maker: Some(0.08),
+            taker: None,
+            bnb_discount: true,
+            asset: FeeAsset::Other,
+        };
+        assert!(settings.validate().is_ok());
+        let fees = settings.fees(false);
+        assert_eq!((fees.maker, fees.taker), (0.08 * 0.75, 0.1 * 0.75));
+        assert_eq!(fees.asset, FeeAsset::Other);
+
+        let settings: FeeSettings =
+            serde_json::from_str(r#"{"vip_tier": 10, "asset": "base"}"#).unwrap();
+        assert!(settings.validate().is_err());
+        let settings = FeeSettings {
+            bnb_discount: true,
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
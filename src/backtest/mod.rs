@@ -1,4 +1,15 @@
 pub mod action;
 pub mod backtest;
+pub mod benchmark;
+pub mod equity;
+pub mod execution;
+pub mod fees;
+pub mod monte_carlo;
+pub mod optimizer;
+pub mod portfolio;
+pub mod progress;
+pub mod runner;
+pub mod schedule;
 pub mod settings;
 pub mod strategies;
+pub mod walk_forward;
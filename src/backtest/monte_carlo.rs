@@ -0,0 +1,283 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::data_models::market_data::position::Position;
+
+/// The percentiles of the reported bands
+const PERCENTILES: [f64; 5] = [5.0, 25.0, 50.0, 75.0, 95.0];
+
+/// The limit of the runs one simulation can make
+pub const MAX_RUNS: usize = 100_000;
+
+/// The way the PnL sequences of the runs are made from the positions
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Resampling {
+    /// The PnLs are drawn with replacement, so the final equity changes
+    Resample,
+    /// The PnLs are reordered, so only the path to the same final equity changes
+    Shuffle,
+}
+
+impl Default for Resampling {
+    fn default() -> Self {
+        Self::Resample
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonteCarloRequest {
+    /// The id of the stored backtest result
+    pub id: i64,
+    #[serde(default = "default_runs")]
+    pub runs: usize,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub resampling: Resampling,
+    /// The loss of the deposit in percent that counts as the ruin
+    #[serde(default = "default_ruin_percent")]
+    pub ruin_percent: f64,
+}
+
+impl MonteCarloRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.runs > MAX_RUNS {
+            return Err(format!(
+                "{} runs requested, the limit is {}",
+                self.runs, MAX_RUNS
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn default_runs() -> usize {
+    1000
+}
+
+fn default_ruin_percent() -> f64 {
+    50.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Percentile {
+    pub percentile: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Distribution {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub percentiles: Vec<Percentile>,
+}
+
+impl Distribution {
+    fn new(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Self {
+                mean: 0.0,
+                min: 0.0,
+                max: 0.0,
+                percentiles: Vec::new(),
+            };
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        Self {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            min: values[0],
+            max: values[values.len() - 1],
+            percentiles: PERCENTILES
+                .iter()
+                .map(|&percentile| Percentile {
+                    percentile,
+                    value: percentile_of_sorted(&values, percentile),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonteCarloReport {
+    pub runs: usize,
+    pub seed: u64,
+    pub resampling: Resampling,
+    pub final_equity: Distribution,
+    pub max_drawdown_percent: Distribution,
+    pub probability_of_ruin: f64,
+}
+
+/// The result of one simulated PnL sequence
+struct Run {
+    final_equity: f64,
+    max_drawdown_percent: f64,
+    ruined: bool,
+}
+
+/// Simulates the `runs` sequences of the closed positions PnLs starting from the `deposit`.
+/// Every run has its own generator seeded by the master generator of the `seed` in the run order,
+/// so the report is the same for the same request however the runs are spread over the threads.
+pub fn simulate(
+    positions: &Vec<Position>,
+    deposit: f64,
+    request: &MonteCarloRequest,
+) -> MonteCarloReport {
+    let pnls: Vec<f64> = positions.iter().filter_map(|p| p.pnl).collect();
+    let ruin_equity = deposit * (1.0 - request.ruin_percent / 100.0);
+    let mut master = StdRng::seed_from_u64(request.seed);
+    let seeds: Vec<u64> = (0..request.runs).map(|_| master.gen()).collect();
+    let runs: Vec<Run> = seeds
+        .into_par_iter()
+        .map(|seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let sequence = match request.resampling {
+                Resampling::Resample => (0..pnls.len())
+                    .map(|_| pnls[rng.gen_range(0..pnls.len())])
+                    .collect(),
+                Resampling::Shuffle => {
+                    let mut sequence = pnls.clone();
+                    sequence.shuffle(&mut rng);
+                    sequence
+                }
+            };
+            run(&sequence, deposit, ruin_equity)
+        })
+        .collect();
+    let ruined = runs.iter().filter(|run| run.ruined).count();
+    MonteCarloReport {
+        runs: request.runs,
+        seed: request.seed,
+        resampling: request.resampling,
+        final_equity: Distribution::new(runs.iter().map(|run| run.final_equity).collect()),
+        max_drawdown_percent: Distribution::new(
+            runs.iter().map(|run| run.max_drawdown_percent).collect(),
+        ),
+        probability_of_ruin: if runs.is_empty() {
+            0.0
+        } else {
+            ruined as f64 / runs.len() as f64
+        },
+    }
+}
+
+fn run(pnls: &[f64], deposit: f64, ruin_equity: f64) -> Run {
+    let mut equity = deposit;
+    let mut peak = deposit;
+    let mut max_drawdown_percent: f64 = 0.0;
+    let mut ruined = false;
+    for pnl in pnls {
+        equity += pnl;
+        peak = peak.max(equity);
+        if peak > 0.0 {
+            max_drawdown_percent = max_drawdown_percent.max((peak - equity) / peak * 100.0);
+        }
+        ruined |= equity <= ruin_equity;
+    }
+    Run {
+        final_equity: equity,
+        max_drawdown_percent,
+        ruined,
+    }
+}
+
+/// Returns the linearly interpolated percentile of the sorted values
+fn percentile_of_sorted(values: &[f64], percentile: f64) -> f64 {
+    let rank = percentile / 100.0 * (values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    values[lower] + (values[upper] - values[lower]) * (rank - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(pnls: &[f64]) -> Vec<Position> {
+        pnls.iter()
+            .map(|&pnl| {
+                let mut position = Position::new("BTCUSDT".into());
+                position.pnl = Some(pnl);
+                position
+            })
+            .collect()
+    }
+
+    fn request(resampling: Resampling) -> MonteCarloRequest {
+        MonteCarloRequest {
+            id: 1,
+            runs: 200,
+            seed: 42,
+            resampling,
+            ruin_percent: 50.0,
+        }
+    }
+
+    #[test]
+    fn test_percentile_of_sorted() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile_of_sorted(&values, 0.0), 1.0);
+        assert_eq!(percentile_of_sorted(&values, 50.0), 3.0);
+        assert_eq!(percentile_of_sorted(&values, 100.0), 5.0);
+        assert_eq!(percentile_of_sorted(&values, 12.5), 1.5);
+    }
+
+    #[test]
+    fn test_run() {
+        let run = run(&[100.0, -550.0, 50.0], 1000.0, 600.0);
+        assert_eq!(run.final_equity, 600.0);
+        assert_eq!(run.max_drawdown_percent, 50.0);
+        assert!(run.ruined);
+    }
+
+    #[test]
+    fn test_shuffle_keeps_final_equity() {
+        let positions = positions(&[100.0, -200.0, 50.0, 30.0, -10.0]);
+        let report = simulate(&positions, 1000.0, &request(Resampling::Shuffle));
+        assert!((report.final_equity.min - 970.0).abs() < 1e-9);
+        assert!((report.final_equity.max - 970.0).abs() < 1e-9);
+        assert!(report.max_drawdown_percent.max > report.max_drawdown_percent.min);
+        assert_eq!(report.probability_of_ruin, 0.0);
+    }
+
+    #[test]
+    fn test_resample_is_seeded() {
+        let positions = positions(&[100.0, -200.0, 50.0, 30.0, -10.0]);
+        let report = simulate(&positions, 1000.0, &request(Resampling::Resample));
+        assert!(report.final_equity.max > report.final_equity.min);
+        assert_eq!(
+            report.final_equity,
+            simulate(&positions, 1000.0, &request(Resampling::Resample)).final_equity
+        );
+        let percentiles = &report.final_equity.percentiles;
+        assert!(percentiles.windows(2).all(|w| w[0].value <= w[1].value));
+
+        let mut other = request(Resampling::Resample);
+        other.seed = 7;
+        assert_ne!(
+            report.final_equity,
+            simulate(&positions, 1000.0, &other).final_equity
+        );
+        // The next seed doesn't repeat the runs of the previous one
+        other.seed = 43;
+        other.runs = 1;
+        let mut first = request(Resampling::Resample);
+        first.runs = 2;
+        let other = simulate(&positions, 1000.0, &other);
+        let first = simulate(&positions, 1000.0, &first);
+        assert!(
+            other.final_equity.mean != first.final_equity.min
+                && other.final_equity.mean != first.final_equity.max
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut request = request(Resampling::Resample);
+        assert!(request.validate().is_ok());
+        request.runs = MAX_RUNS + 1;
+        assert!(request.validate().is_err());
+    }
+}
@@ -0,0 +1,350 @@
+use std::{cmp::Ordering, path::PathBuf};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::data_models::market_data::metrics::Metrics;
+
+use super::strategies::grid::{runner::run_grid, settings::GridSettingsRequest};
+
+/// The limit of the backtests one optimization can run, the request waits for all of them
+pub const MAX_COMBINATIONS: usize = 500;
+
+/// The values of one optimized parameter, either listed or generated with a step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParameterRange {
+    Values(Vec<f64>),
+    Range { start: f64, stop: f64, step: f64 },
+}
+
+impl ParameterRange {
+    /// Returns the values of the range, the `stop` is included
+    pub fn values(&self) -> Result<Vec<f64>, String> {
+        match self {
+            ParameterRange::Values(values) => Ok(values.clone()),
+            ParameterRange::Range { start, stop, step } => {
+                if *step <= 0.0 || stop < start {
+                    return Err(format!(
+                        "Invalid range: start {}, stop {}, step {}",
+                        start, stop, step
+                    ));
+                }
+                // The values are multiplied instead of summed, so the error doesn't accumulate
+                let count = ((stop - start) / step + 1e-9).floor() as usize + 1;
+                if count > MAX_COMBINATIONS {
+                    return Err(format!(
+                        "The range has more than {} values",
+                        MAX_COMBINATIONS
+                    ));
+                }
+                Ok((0..count).map(|i| start + step * i as f64).collect())
+            }
+        }
+    }
+}
+
+/// One combination of the optimized parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridParameters {
+    pub price_low: f64,
+    pub price_high: f64,
+    pub grids_count: i64,
+    pub commission: f64,
+}
+
+impl GridParameters {
+    /// Returns the request settings with the parameters of the combination
+    pub fn apply(&self, settings: &GridSettingsRequest) -> GridSettingsRequest {
+        let mut settings = settings.clone();
+        settings.price_low = self.price_low;
+        settings.price_high = self.price_high;
+        settings.grids_count = self.grids_count;
+        settings.backtest.commission = self.commission;
+        settings
+    }
+}
+
+/// The grid search over the grid settings. The parameters without a range keep
+/// the value of the `settings`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GridOptimizationRequest {
+    pub settings: GridSettingsRequest,
+    pub price_low: Option<ParameterRange>,
+    pub price_high: Option<ParameterRange>,
+    pub grids_count: Option<ParameterRange>,
+    pub commission: Option<ParameterRange>,
+    /// The name of the `Metrics` field the results are ranked by
+    pub rank_by: String,
+    /// The results are ranked from the highest value unless it's set
+    #[serde(default)]
+    pub ascending: bool,
+}
+
+impl GridOptimizationRequest {
+    /// Checks the settings and every combination can be run like a single backtest
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rank_by == "id" || metric_value(&Metrics::default(), &self.rank_by).is_none() {
+            return Err(format!("Unknown metric: {}", self.rank_by));
+        }
+        self.settings.validate()?;
+        for parameters in self.combinations()? {
+            parameters
+                .apply(&self.settings)
+                .validate()
+                .map_err(|e| format!("Invalid combination {:?}: {}", parameters, e))?;
+        }
+        Ok(())
+    }
+
+    /// Returns all the valid combinations of the parameter values
+    pub fn combinations(&self) -> Result<Vec<GridParameters>, String> {
+        let values = |range: &Option<ParameterRange>, default: f64| match range {
+            Some(range) => range.values(),
+            None => Ok(vec![default]),
+        };
+        let prices_low = values(&self.price_low, self.settings.price_low)?;
+        let prices_high = values(&self.price_high, self.settings.price_high)?;
+        let grids_counts = values(&self.grids_count, self.settings.grids_count as f64)?;
+        let commissions = values(&self.commission, self.settings.backtest.commission)?;
+
+        let total = prices_low.len() * prices_high.len() * grids_counts.len() * commissions.len();
+        if total > MAX_COMBINATIONS {
+            return Err(format!(
+                "{} combinations requested, the limit is {}",
+                total, MAX_COMBINATIONS
+            ));
+        }
+
+        let mut combinations = Vec::new();
+        for &price_low in prices_low.iter() {
+            for &price_high in prices_high.iter() {
+                if price_low >= price_high {
+                    continue;
+                }
+                for &grids_count in grids_counts.iter() {
+                    let grids_count = grids_count.round() as i64;
+                    if grids_count < 1 {
+                        continue;
+                    }
+                    for &commission in commissions.iter() {
+                        combinations.push(GridParameters {
+                            price_low,
+                            price_high,
+                            grids_count,
+                            commission,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(combinations)
+    }
+}
+
+/// The result of the backtest of one combination
+#[derive(Debug, Serialize)]
+pub struct GridOptimizationRow {
+    #[serde(flatten)]
+    pub parameters: GridParameters,
+    pub metrics: Metrics,
+}
+
+/// Returns the value of the `Metrics` field with the `name`,
+/// `None` if there is no numeric field with the name
+pub fn metric_value(metrics: &Metrics, name: &str) -> Option<f64> {
+    serde_json::to_value(metrics).ok()?.get(name)?.as_f64()
+}
+
+/// Runs the backtest of every combination in parallel and returns the results ranked by
+/// the `rank_by` metric. The combinations read the same memory mapped files, so the data
+/// is loaded from the disk once for all of them.
+pub fn optimize(
+    request: &GridOptimizationRequest,
+    data_path: PathBuf,
+) -> Result<Vec<GridOptimizationRow>, String> {
+    request.validate()?;
+    let combinations = request.combinations()?;
+    let rows: Vec<GridOptimizationRow> = combinations
+        .into_par_iter()
+        .map(|parameters| {
+            let result = run_grid(&parameters.apply(&request.settings), data_path.clone());
+            GridOptimizationRow {
+                parameters,
+                metrics: result.metrics,
+            }
+        })
+        .collect();
+    Ok(rank(rows, &request.rank_by, request.ascending))
+}
+
+/// Sorts the rows by the metric, the rows without a value go last
+fn rank(
+    rows: Vec<GridOptimizationRow>,
+    rank_by: &str,
+    ascending: bool,
+) -> Vec<GridOptimizationRow> {
+    let mut ranked: Vec<(Option<f64>, GridOptimizationRow)> = rows
+        .into_iter()
+        .map(|row| {
+            let value = metric_value(&row.metrics, rank_by).filter(|v| !v.is_nan());
+            (value, row)
+        })
+        .collect();
+    ranked.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) if ascending => a.partial_cmp(b).unwrap(),
+        (Some(a), Some(b)) => b.partial_cmp(a).unwrap(),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    ranked.into_iter().map(|(_, row)| row).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all};
+
+    use crate::{
+        backtest::strategies::grid::settings::GridMode,
+        data_handlers::{
+            bin_files::{bin_file_name, create_and_write_to_file},
+            utils::datetime_str_to_i64,
+        },
+        data_models::market_data::{enums::MarketDataType, kline::KLine},
+    };
+
+    use super::*;
+
+    const MINUTE: i64 = 60 * 1000;
+
+    fn optimization_request(ranges: serde_json::Value) -> GridOptimizationRequest {
+        let mut request = serde_json::json!({
+            "settings": {
+                "symbol": "BTCUSDT",
+                "exchange": "binance",
+                "market_data_type": "1m",
+                "chart_market_data_type": "1m",
+                "date_start": "2020-09-13",
+                "date_end": "2020-09-14",
+                "deposit": 1000.0,
+                "commission": 0.0,
+                "price_low": 20.0,
+                "price_high": 80.0,
+                "grids_count": 10,
+                "grid_trigger": 0.0,
+                "grid_sl": null,
+                "grid_tp": null,
+                "sell_all": true,
+            },
+            "rank_by": "total_profit",
+        });
+        for (key, value) in ranges.as_object().unwrap() {
+            request[key] = value.clone();
+        }
+        serde_json::from_value(request).unwrap()
+    }
+
+    #[test]
+    fn test_parameter_values() {
+        let range: ParameterRange =
+            serde_json::from_str(r#"{"start": 0.1, "stop": 0.3, "step": 0.1}"#).unwrap();
+        assert_eq!(range.values().unwrap().len(), 3);
+        let range: ParameterRange = serde_json::from_str("[1, 5, 2]").unwrap();
+        assert_eq!(range.values().unwrap(), vec![1.0, 5.0, 2.0]);
+        let range = ParameterRange::Range {
+            start: 1.0,
+            stop: 0.0,
+            step: 1.0,
+        };
+        assert!(range.values().is_err());
+    }
+
+    #[test]
+    fn test_combinations() {
+        let request = optimization_request(serde_json::json!({
+            "price_low": [10, 50, 90],
+            "price_high": {"start": 60, "stop": 80, "step": 20},
+            "grids_count": [0, 5],
+        }));
+        let combinations = request.combinations().unwrap();
+        // The combinations with the low price above the high one and without grids are skipped
+        assert_eq!(combinations.len(), 4);
+        assert!(combinations
+            .iter()
+            .all(|c| c.price_low < c.price_high && c.grids_count == 5 && c.commission == 0.0));
+
+        let request = optimization_request(serde_json::json!({
+            "price_low": {"start": 0, "stop": 100, "step": 1},
+            "grids_count": {"start": 1, "stop": 100, "step": 1},
+        }));
+        assert!(request.combinations().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut request = optimization_request(serde_json::json!({
+            "grids_count": [2, 10],
+            "commission": [0.0, 0.001],
+        }));
+        assert!(request.validate().is_ok());
+        request.rank_by = "unknown".into();
+        assert!(request.validate().is_err());
+        request.rank_by = "total_profit".into();
+
+        // The settings are checked like the ones of a single backtest
+        request.settings.mode = GridMode::Short;
+        assert!(request.validate().is_err());
+        request.settings.mode = GridMode::Long;
+        request.settings.backtest.market_data_type = MarketDataType::FundingRate;
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_optimize() {
+        let data_path = PathBuf::from("test_optimize");
+        create_dir_all(&data_path).unwrap();
+        let date_start = datetime_str_to_i64("2020-09-13".into());
+        let klines: Vec<KLine> = (0..600)
+            .map(|i| {
+                let close = 50.0 + 40.0 * ((i as f64) / 20.0).sin();
+                KLine {
+                    date: date_start + i * MINUTE,
+                    open: close,
+                    high: close + 1.0,
+                    low: close - 1.0,
+                    close,
+                    volume: 1.0,
+                }
+            })
+            .collect();
+        let file_path = data_path.join(bin_file_name(
+            "binance".into(),
+            "btcusdt".into(),
+            MarketDataType::KLine1m,
+        ));
+        create_and_write_to_file(&klines, file_path).unwrap();
+
+        let request = optimization_request(serde_json::json!({
+            "grids_count": [2, 10, 20],
+            "commission": [0.0, 0.001],
+        }));
+        let rows = optimize(&request, data_path.clone()).unwrap();
+        assert_eq!(rows.len(), 6);
+        assert!(rows
+            .windows(2)
+            .all(|w| w[0].metrics.total_profit >= w[1].metrics.total_profit));
+        // The same combination gives the same result as a single backtest
+        let single = run_grid(
+            &rows[0].parameters.apply(&request.settings),
+            data_path.clone(),
+        );
+        assert_eq!(single.metrics.total_profit, rows[0].metrics.total_profit);
+
+        let mut request = request;
+        request.rank_by = "unknown".into();
+        assert!(optimize(&request, data_path.clone()).is_err());
+
+        remove_dir_all(data_path).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use super::{equity::EquityRecorder, settings::BacktestSettings};
+
+/// The rule of the deposit distribution between the strategies of one backtest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Allocation {
+    /// Every strategy gets an equal part of the deposit and can't use the cash of the others
+    FixedSplit,
+    /// All strategies share the whole cash, the first one to place an order takes the money
+    FirstCome,
+    /// Every strategy gets a part of the deposit proportional to its weight
+    Weighted(Vec<f64>),
+}
+
+impl Default for Allocation {
+    fn default() -> Self {
+        Self::FixedSplit
+    }
+}
+
+impl Allocation {
+    /// Returns the initial deposit of every strategy
+    pub fn deposits(&self, deposit: f64, strategies_number: usize) -> Vec<f64> {
+        match self {
+            Allocation::FixedSplit => {
+                vec![deposit / strategies_number as f64; strategies_number]
+            }
+            Allocation::FirstCome => vec![deposit; strategies_number],
+            Allocation::Weighted(weights) => {
+                // Strategies without a weight get nothing
+                let weights: Vec<f64> = (0..strategies_number)
+                    .map(|i| weights.get(i).copied().unwrap_or(0.0).max(0.0))
+                    .collect();
+                let total = weights.iter().sum::<f64>();
+                if total == 0.0 {
+                    return vec![0.0; strategies_number];
+                }
+                weights.iter().map(|w| deposit * w / total).collect()
+            }
+        }
+    }
+
+    /// Returns true if the strategies draw from the same cash
+    pub fn is_shared(&self) -> bool {
+        *self == Allocation::FirstCome
+    }
+}
+
+/// The cash account of a backtest. The strategies draw from it when they buy
+/// and return the cash to it when they sell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Portfolio {
+    pub deposit: f64,
+    pub cash: f64,
+    pub allocation: Allocation,
+    pub accounts: Vec<f64>,
+    pub equity: EquityRecorder,
+}
+
+impl Portfolio {
+    pub fn new(deposit: f64, allocation: Allocation, strategies_number: usize) -> Self {
+        Self {
+            deposit,
+            cash: deposit,
+            accounts: allocation.deposits(deposit, strategies_number),
+            allocation,
+            equity: EquityRecorder::default(),
+        }
+    }
+
+    pub fn from_settings(backtest_settings: &BacktestSettings) -> Self {
+        let mut portfolio = Self::new(
+            backtest_settings.deposit,
+            backtest_settings.allocation.clone(),
+            backtest_settings.symbols.len(),
+        );
+        portfolio.equity = EquityRecorder::new(backtest_settings.equity_interval);
+        portfolio
+    }
+
+    /// Returns the cash available for the strategy with the `index`
+    pub fn available(&self, index: usize) -> f64 {
+        if self.allocation.is_shared() {
+            self.cash
+        } else {
+            self.accounts[index]
+        }
+    }
+
+    /// Applies the cash change made by the strategy with the `index`.
+    /// A negative `delta` is a withdrawal, a positive one is a return.
+    pub fn apply(&mut self, index: usize, delta: f64) {
+        self.cash += delta;
+        if !self.allocation.is_shared() {
+            self.accounts[index] += delta;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deposits() {
+        assert_eq!(Allocation::FixedSplit.deposits(90.0, 3), vec![30.0; 3]);
+        assert_eq!(Allocation::FirstCome.deposits(90.0, 3), vec![90.0; 3]);
+        assert_eq!(
+            Allocation::Weighted(vec![1.0, 2.0, 3.0]).deposits(90.0, 3),
+            vec![15.0, 30.0, 45.0]
+        );
+        assert_eq!(
+            Allocation::Weighted(vec![1.0]).deposits(90.0, 2),
+            vec![90.0, 0.0]
+        );
+        assert_eq!(
+            Allocation::Weighted(vec![]).deposits(90.0, 2),
+            vec![0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_fixed_split() {
+        let mut portfolio = Portfolio::new(100.0, Allocation::FixedSplit, 2);
+        assert_eq!(portfolio.available(0), 50.0);
+        portfolio.apply(0, -40.0);
+        assert_eq!(portfolio.available(0), 10.0);
+        assert_eq!(portfolio.available(1), 50.0);
+        assert_eq!(portfolio.cash, 60.0);
+        portfolio.apply(0, 45.0);
+        assert_eq!(portfolio.available(0), 55.0);
+        assert_eq!(portfolio.cash, 105.0);
+    }
+
+    #[test]
+    fn test_first_come() {
+        let mut portfolio = Portfolio::new(100.0, Allocation::FirstCome, 2);
+        assert_eq!(portfolio.available(0), 100.0);
+        portfolio.apply(0, -80.0);
+        assert_eq!(portfolio.available(0), 20.0);
+        assert_eq!(portfolio.available(1), 20.0);
+        portfolio.apply(1, -20.0);
+        assert_eq!(portfolio.available(0), 0.0);
+        assert_eq!(portfolio.cash, 0.0);
+    }
+}
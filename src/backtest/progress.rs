@@ -0,0 +1,181 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use serde::Serialize;
+
+/// The progress of a running backtest shared with the one who waits for it.
+/// It's also the way to stop the backtest before the end of the period.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    state: Arc<ProgressState>,
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    /// Hundredths of a percent, so it fits an atomic integer
+    basis_points: AtomicU64,
+    cancelled: AtomicBool,
+    snapshot: Mutex<Snapshot>,
+}
+
+#[derive(Debug, Default)]
+struct Snapshot {
+    date_start: i64,
+    date_end: i64,
+    /// One part for the strategies run together or one per strategy run in parallel
+    parts: Vec<PartProgress>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PartProgress {
+    date: i64,
+    positions_opened: usize,
+    positions_closed: usize,
+    equity: f64,
+}
+
+/// The state of a running backtest
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProgressEvent {
+    /// The date all the strategies have reached
+    pub date: i64,
+    pub date_start: i64,
+    pub date_end: i64,
+    pub percent: f64,
+    pub positions_opened: usize,
+    pub positions_closed: usize,
+    /// The last equity sample of the backtest
+    pub equity: f64,
+}
+
+impl Progress {
+    /// Moves the progress to the `date` of the period. The strategies run in parallel
+    /// report their own dates, so the progress never goes back.
+    pub fn update(&self, date: i64, date_start: i64, date_end: i64) {
+        if date_end <= date_start {
+            return;
+        }
+        let part = (date - date_start) as f64 / (date_end - date_start) as f64;
+        let basis_points = (part.clamp(0.0, 1.0) * 10_000.0) as u64;
+        self.state
+            .basis_points
+            .fetch_max(basis_points, Ordering::Relaxed);
+    }
+
+    /// Resets the parts of the backtest to their initial equity
+    pub fn start(&self, date_start: i64, date_end: i64, equity: &[f64]) {
+        let mut snapshot = self.state.snapshot.lock().unwrap();
+        snapshot.date_start = date_start;
+        snapshot.date_end = date_end;
+        snapshot.parts = equity
+            .iter()
+            .map(|&equity| PartProgress {
+                date: date_start,
+                equity,
+                ..Default::default()
+            })
+            .collect();
+    }
+
+    /// Saves the state of the `part` at the `date`
+    pub fn record(
+        &self,
+        part: usize,
+        date: i64,
+        positions_opened: usize,
+        positions_closed: usize,
+        equity: f64,
+    ) {
+        let mut snapshot = self.state.snapshot.lock().unwrap();
+        if let Some(part) = snapshot.parts.get_mut(part) {
+            *part = PartProgress {
+                date,
+                positions_opened,
+                positions_closed,
+                equity,
+            };
+        }
+    }
+
+    /// Returns the state of all the parts together
+    pub fn event(&self) -> ProgressEvent {
+        let snapshot = self.state.snapshot.lock().unwrap();
+        let parts = &snapshot.parts;
+        ProgressEvent {
+            date: parts
+                .iter()
+                .map(|part| part.date)
+                .min()
+                .unwrap_or(snapshot.date_start),
+            date_start: snapshot.date_start,
+            date_end: snapshot.date_end,
+            percent: self.percent(),
+            positions_opened: parts.iter().map(|part| part.positions_opened).sum(),
+            positions_closed: parts.iter().map(|part| part.positions_closed).sum(),
+            equity: parts.iter().map(|part| part.equity).sum(),
+        }
+    }
+
+    pub fn finish(&self) {
+        self.state.basis_points.store(10_000, Ordering::Relaxed);
+    }
+
+    pub fn percent(&self) -> f64 {
+        self.state.basis_points.load(Ordering::Relaxed) as f64 / 100.0
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress() {
+        let progress = Progress::default();
+        let shared = progress.clone();
+        progress.update(50, 0, 200);
+        assert_eq!(shared.percent(), 25.0);
+        progress.update(20, 0, 200);
+        assert_eq!(shared.percent(), 25.0);
+        progress.update(500, 0, 200);
+        assert_eq!(shared.percent(), 100.0);
+
+        assert!(!shared.is_cancelled());
+        progress.cancel();
+        assert!(shared.is_cancelled());
+    }
+
+    #[test]
+    fn test_event() {
+        let progress = Progress::default();
+        progress.start(0, 100, &[50.0, 50.0]);
+        assert_eq!(progress.event().equity, 100.0);
+        progress.record(0, 40, 1, 2, 60.0);
+        progress.record(1, 20, 3, 0, 45.0);
+        // The parts which don't exist are ignored
+        progress.record(2, 30, 1, 1, 10.0);
+        progress.update(20, 0, 100);
+        assert_eq!(
+            progress.event(),
+            ProgressEvent {
+                date: 20,
+                date_start: 0,
+                date_end: 100,
+                percent: 20.0,
+                positions_opened: 4,
+                positions_closed: 2,
+                equity: 105.0,
+            }
+        );
+    }
+}
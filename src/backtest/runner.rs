@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use serde::{de::DeserializeOwned, Serialize};
+use sha3::Digest;
+
+use crate::data_models::market_data::{equity::EquityPoint, metrics::Metrics, position::Position};
+
+use super::{
+    backtest::{self, get_metrics, get_positions_from_strategies, strategies_settings},
+    benchmark::benchmark_equity,
+    portfolio::Portfolio,
+    progress::Progress,
+    settings::{BacktestRequest, BacktestSettings, CheckpointSettings, StrategySettings},
+    strategies::strategy_trait::Strategy,
+};
+
+/// The result of the backtest of one request
+pub struct StrategyBacktest {
+    /// The request the backtest is run with
+    pub request: serde_json::Value,
+    pub backtest_settings: BacktestSettings,
+    pub positions: Vec<Position>,
+    pub portfolio: Portfolio,
+    /// The equity of buying and holding the symbols, sampled at the dates of the portfolio equity
+    pub benchmark: Vec<EquityPoint>,
+    pub metrics: Metrics,
+}
+
+/// Runs the strategy made by the `new_strategy` for every symbol of the `request`.
+/// The `backtest` is the part of the request common for all the strategies.
+pub fn run_strategy<R, S>(
+    request: &R,
+    backtest: &BacktestRequest,
+    data_path: PathBuf,
+    progress: Progress,
+    new_strategy: impl Fn(StrategySettings) -> S,
+) -> StrategyBacktest
+where
+    R: Serialize,
+    S: Strategy + Clone + Send + Serialize + DeserializeOwned,
+{
+    // The resolved rules are stored with the request,
+    // so the result is reproduced with them after the rules of the exchange are refreshed
+    let rules = backtest.symbol_rules(data_path.clone());
+    let mut request = serde_json::to_value(request).unwrap();
+    request["rules"] = serde_json::to_value(&rules).unwrap();
+    let mut backtest_settings = backtest.backtest_settings();
+    backtest_settings.progress = progress;
+    backtest_settings.checkpoint = backtest.checkpoint_interval.map(|interval| {
+        // The same request resumes from the checkpoint of the run that didn't finish
+        let request_hash = sha3::Sha3_256::digest(request.to_string());
+        CheckpointSettings {
+            path: data_path
+                .join("checkpoints")
+                .join(format!("{:x}.json", request_hash)),
+            interval,
+        }
+    });
+    let mut strategies: Vec<S> = strategies_settings(backtest_settings.clone())
+        .into_iter()
+        .map(|mut s| {
+            s.rules = rules.as_ref().map(|rules| rules[&s.symbol].clone());
+            new_strategy(s)
+        })
+        .collect();
+    let mut portfolio = Portfolio::from_settings(&backtest_settings);
+    backtest::run(
+        backtest_settings.clone(),
+        &mut strategies,
+        &mut portfolio,
+        data_path.clone(),
+    );
+    let dates: Vec<i64> = portfolio.equity.points.iter().map(|p| p.date).collect();
+    let benchmark = benchmark_equity(&backtest_settings, &dates, &data_path);
+    let positions = get_positions_from_strategies(strategies);
+    let metrics = get_metrics(
+        &positions,
+        portfolio.deposit,
+        portfolio.cash,
+        &portfolio.equity.points,
+        &backtest_settings.annualization,
+    )
+    .with_benchmark(
+        &portfolio.equity.points,
+        &benchmark,
+        &backtest_settings.annualization,
+    );
+    StrategyBacktest {
+        request,
+        backtest_settings,
+        positions,
+        portfolio,
+        benchmark,
+        metrics,
+    }
+}
+
+/// Returns the byte offset of the first difference between the positions JSON
+/// of the result and the `stored_positions`, `None` if they are identical
+pub fn first_difference(result: &StrategyBacktest, stored_positions: &str) -> Option<usize> {
+    let positions = serde_json::to_string(&result.positions).unwrap();
+    if positions == stored_positions {
+        return None;
+    }
+    Some(
+        positions
+            .bytes()
+            .zip(stored_positions.bytes())
+            .position(|(a, b)| a != b)
+            .unwrap_or(positions.len().min(stored_positions.len())),
+    )
+}
@@ -0,0 +1,234 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// The days a schedule fires on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulePeriod {
+    Daily,
+    /// The day of the week like "monday" or "mon"
+    Weekly(String),
+    /// The day of the month, the last day is used in the shorter months
+    Monthly(u32),
+}
+
+/// What to do if there is no bar at the scheduled time
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingBar {
+    /// The time is skipped
+    Skip,
+    /// The first bar after the time is used
+    #[default]
+    Shift,
+}
+
+/// A calendar schedule like "every Monday 09:00 Europe/Berlin".
+/// The time is local, a time in the DST gap fires at the end of the gap
+/// and a time repeated by the DST end fires only the first time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub period: SchedulePeriod,
+    /// The local time as "HH:MM"
+    pub time: String,
+    /// The IANA time zone
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(default)]
+    pub missing_bar: MissingBar,
+}
+
+fn default_timezone() -> String {
+    "UTC".into()
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<(), String> {
+        self.parse().map(|_| ())
+    }
+
+    fn parse(&self) -> Result<(NaiveTime, Tz, Option<Weekday>), String> {
+        let time = NaiveTime::parse_from_str(&self.time, "%H:%M")
+            .map_err(|_| format!("Invalid schedule time: {}", self.time))?;
+        let timezone: Tz = self
+            .timezone
+            .parse()
+            .map_err(|_| format!("Invalid time zone: {}", self.timezone))?;
+        let weekday = match &self.period {
+            SchedulePeriod::Weekly(weekday) => Some(
+                weekday
+                    .parse()
+                    .map_err(|_| format!("Invalid weekday: {}", weekday))?,
+            ),
+            SchedulePeriod::Monthly(day) if !(1..=31).contains(day) => {
+                return Err(format!("Invalid day of the month: {}", day))
+            }
+            _ => None,
+        };
+        Ok((time, timezone, weekday))
+    }
+
+    /// Returns the first scheduled time after the `date` in milliseconds
+    pub fn next_after(&self, date: i64) -> i64 {
+        let (time, timezone, weekday) = self.parse().unwrap();
+        let mut day = timezone.timestamp_millis_opt(date).unwrap().date_naive();
+        loop {
+            let matches = match &self.period {
+                SchedulePeriod::Daily => true,
+                SchedulePeriod::Weekly(_) => Some(day.weekday()) == weekday,
+                SchedulePeriod::Monthly(month_day) => {
+                    day.day() == (*month_day).min(month_days(day))
+                }
+            };
+            if matches {
+                let scheduled = local_to_utc(&timezone, day, time).timestamp_millis();
+                if scheduled > date {
+                    return scheduled;
+                }
+            }
+            day = day.succ_opt().unwrap();
+        }
+    }
+
+    /// Returns true if the bar at the `date` is the one to act on. The `next` scheduled time
+    /// is kept between the calls, the bars should come in the time order.
+    pub fn is_due(&self, next: &mut Option<i64>, date: i64) -> bool {
+        let mut scheduled = next.unwrap_or_else(|| self.next_after(date - 1));
+        let mut due = false;
+        // All the times missed between the bars result in one shifted action
+        while scheduled <= date {
+            due |= scheduled == date || self.missing_bar == MissingBar::Shift;
+            scheduled = self.next_after(scheduled);
+        }
+        *next = Some(scheduled);
+        due
+    }
+}
+
+fn month_days(day: NaiveDate) -> u32 {
+    let (year, month) = match day.month() {
+        12 => (day.year() + 1, 1),
+        month => (day.year(), month + 1),
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .unwrap()
+        .pred_opt()
+        .unwrap()
+        .day()
+}
+
+/// Returns the moment of the local time, the first one if it's repeated
+/// and the end of the DST gap if it doesn't exist
+fn local_to_utc(timezone: &Tz, day: NaiveDate, time: NaiveTime) -> DateTime<Tz> {
+    let mut local = day.and_time(time);
+    loop {
+        match timezone.from_local_datetime(&local) {
+            LocalResult::Single(moment) | LocalResult::Ambiguous(moment, _) => return moment,
+            LocalResult::None => local += Duration::minutes(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, min: u32) -> i64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, min, 0)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn schedule(period: SchedulePeriod, time: &str, missing_bar: MissingBar) -> Schedule {
+        Schedule {
+            period,
+            time: time.into(),
+            timezone: "Europe/Berlin".into(),
+            missing_bar,
+        }
+    }
+
+    #[test]
+    fn test_next_after() {
+        // The DST starts on 2024-03-31 in Berlin
+        let monday = schedule(
+            SchedulePeriod::Weekly("monday".into()),
+            "09:00",
+            MissingBar::Skip,
+        );
+        assert_eq!(
+            monday.next_after(utc(2024, 3, 21, 0, 0)),
+            utc(2024, 3, 25, 8, 0)
+        );
+        assert_eq!(
+            monday.next_after(utc(2024, 3, 25, 8, 0)),
+            utc(2024, 4, 1, 7, 0)
+        );
+
+        // 02:30 doesn't exist on the DST start and repeats on the DST end
+        let daily = schedule(SchedulePeriod::Daily, "02:30", MissingBar::Skip);
+        assert_eq!(
+            daily.next_after(utc(2024, 3, 30, 12, 0)),
+            utc(2024, 3, 31, 1, 0)
+        );
+        assert_eq!(
+            daily.next_after(utc(2024, 10, 26, 12, 0)),
+            utc(2024, 10, 27, 0, 30)
+        );
+        assert_eq!(
+            daily.next_after(utc(2024, 10, 27, 0, 30)),
+            utc(2024, 10, 28, 1, 30)
+        );
+
+        // The last day of the shorter month
+        let monthly = schedule(SchedulePeriod::Monthly(31), "00:00", MissingBar::Skip);
+        assert_eq!(
+            monthly.next_after(utc(2024, 2, 1, 0, 0)),
+            utc(2024, 2, 28, 23, 0)
+        );
+
+        assert!(
+            schedule(SchedulePeriod::Monthly(32), "00:00", MissingBar::Skip)
+                .validate()
+                .is_err()
+        );
+        assert!(schedule(
+            SchedulePeriod::Weekly("someday".into()),
+            "00:00",
+            MissingBar::Skip
+        )
+        .validate()
+        .is_err());
+        assert!(schedule(SchedulePeriod::Daily, "25:00", MissingBar::Skip)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_is_due() {
+        // Daily bars at 00:00 UTC never match 09:00 Berlin
+        let bars: Vec<i64> = (1..=3).map(|day| utc(2024, 1, day, 0, 0)).collect();
+        let skip = schedule(SchedulePeriod::Daily, "09:00", MissingBar::Skip);
+        let mut next = None;
+        assert!(bars.iter().all(|&bar| !skip.is_due(&mut next, bar)));
+
+        let shift = schedule(SchedulePeriod::Daily, "09:00", MissingBar::Shift);
+        let mut next = None;
+        let due: Vec<bool> = bars
+            .iter()
+            .map(|&bar| shift.is_due(&mut next, bar))
+            .collect();
+        assert_eq!(due, vec![false, true, true]);
+
+        // Hourly bars match it exactly
+        let mut next = None;
+        let due: Vec<i64> = (0..48)
+            .map(|hour| utc(2024, 1, 1, 0, 0) + hour * 60 * 60 * 1000)
+            .filter(|&bar| skip.is_due(&mut next, bar))
+            .collect();
+        assert_eq!(due, vec![utc(2024, 1, 1, 8, 0), utc(2024, 1, 2, 8, 0)]);
+    }
+}
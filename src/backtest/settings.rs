@@ -1,6 +1,30 @@
-use serde::Deserialize;
+use std::{collections::BTreeMap, path::PathBuf};
 
-use crate::data_models::market_data::enums::MarketDataType;
+use serde::{Deserialize, Serialize};
+use serde_aux::field_attributes::{
+    deserialize_number_from_string, deserialize_option_number_from_string,
+};
+use strum::IntoEnumIterator;
+
+use crate::{
+    data_handlers::utils::datetime_str_to_i64,
+    data_models::market_data::{
+        enums::{Annualization, MarketDataType, PricePath},
+        symbol_rules::SymbolRules,
+    },
+};
+
+use super::{
+    execution::ExecutionSettings,
+    fees::{FeeAsset, FeeSettings, Fees},
+    portfolio::Allocation,
+    progress::Progress,
+    strategies::{
+        registry::{ParameterKind, ParameterSchema},
+        strategy_utils::get_symbol_rules,
+        timeframes::Timeframes,
+    },
+};
 
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct BacktestSettings {
@@ -11,9 +35,48 @@ pub struct BacktestSettings {
     pub date_end: i64,
     pub deposit: f64,
     pub commission: f64,
+    #[serde(default)]
+    pub allocation: Allocation,
+    #[serde(default)]
+    pub price_path: PricePath,
+    /// The higher timeframes the strategies read besides the `market_data_type`
+    #[serde(default)]
+    pub timeframes: Vec<MarketDataType>,
+    /// The equity sampling interval in milliseconds, every bar is sampled if it's 0
+    #[serde(default)]
+    pub equity_interval: i64,
+    #[serde(default)]
+    pub annualization: Annualization,
+    /// The seed of the position ids, the ids are random if it's not set
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub checkpoint: Option<CheckpointSettings>,
+    /// The strategies trade the futures if it's set, otherwise the spot
+    #[serde(default)]
+    pub futures: Option<FuturesSettings>,
+    /// The slippage and the latency of the market orders
+    #[serde(default)]
+    pub execution: ExecutionSettings,
+    /// The maker and taker fees, the flat `commission` is used if it's not set
+    #[serde(default)]
+    pub fees: Option<FeeSettings>,
+    #[serde(skip)]
+    pub progress: Progress,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+/// The backtest state is saved to the `path` while it runs and the next run
+/// with the same `path` continues from it
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckpointSettings {
+    pub path: PathBuf,
+    /// The period between the checkpoints in milliseconds,
+    /// they're saved after every chunk of the data if it's 0
+    #[serde(default)]
+    pub interval: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct StrategySettings {
     pub symbol: String,
     pub exchange: String,
@@ -22,4 +85,366 @@ pub struct StrategySettings {
     pub date_end: i64,
     pub deposit: f64,
     pub commission: f64,
+    #[serde(default)]
+    pub price_path: PricePath,
+    /// The higher timeframes the strategy reads besides the `market_data_type`
+    #[serde(default)]
+    pub timeframes: Vec<MarketDataType>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub futures: Option<FuturesSettings>,
+    #[serde(default)]
+    pub execution: ExecutionSettings,
+    #[serde(default)]
+    pub fees: Option<FeeSettings>,
+    /// The trading rules of the symbol, the orders aren't rounded if they aren't downloaded
+    #[serde(default)]
+    pub rules: Option<SymbolRules>,
+}
+
+impl StrategySettings {
+    /// Returns the leverage of the futures, it's 1 for the spot
+    pub fn leverage(&self) -> f64 {
+        self.futures
+            .as_ref()
+            .map_or(1.0, |futures| futures.leverage)
+    }
+
+    /// Returns the fees of the fee schedule, the flat commission if it's not set
+    pub fn fees(&self) -> Fees {
+        match &self.fees {
+            Some(fees) => fees.fees(self.futures.is_some()),
+            None => Fees::flat(self.commission),
+        }
+    }
+}
+
+/// How the margin of the futures positions is shared
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarginMode {
+    /// Every position has its own margin and is liquidated alone
+    #[default]
+    Isolated,
+    /// All the positions of the strategy share the balance and are liquidated together
+    Cross,
+}
+
+/// The settings of the USD-M futures trading
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FuturesSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub leverage: f64,
+    #[serde(default)]
+    pub margin_mode: MarginMode,
+    /// The maintenance margin rate in percent of the position value
+    #[serde(
+        default = "default_maintenance_margin",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub maintenance_margin: f64,
+    /// The fee of the liquidation in percent of the position value
+    #[serde(
+        default = "default_liquidation_fee",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub liquidation_fee: f64,
+}
+
+fn default_maintenance_margin() -> f64 {
+    0.5
+}
+
+fn default_liquidation_fee() -> f64 {
+    1.25
+}
+
+impl FuturesSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1.0..=125.0).contains(&self.leverage) {
+            return Err("The leverage should be from 1 to 125".into());
+        }
+        // The position would be liquidated right after the opening
+        if self.maintenance_margin < 0.0 || self.maintenance_margin >= 100.0 / self.leverage {
+            return Err("The maintenance margin should be below the initial margin".into());
+        }
+        if self.liquidation_fee < 0.0 {
+            return Err("The liquidation fee can't be negative".into());
+        }
+        Ok(())
+    }
+}
+
+/// The settings of a backtest request common for all the strategies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestRequest {
+    pub symbol: String,
+    /// The symbols traded besides the `symbol`, each one by its own strategy
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    pub exchange: String,
+    pub market_data_type: MarketDataType,
+    pub chart_market_data_type: MarketDataType,
+    pub date_start: String,
+    pub date_end: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub deposit: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub commission: f64,
+    /// The deposit distribution between the strategies of the symbols
+    #[serde(default)]
+    pub allocation: Allocation,
+    #[serde(default)]
+    pub price_path: PricePath,
+    /// The higher timeframes the strategies read besides the `market_data_type`
+    #[serde(default)]
+    pub timeframes: Vec<MarketDataType>,
+    /// The equity sampling interval in milliseconds, the chart interval is used if it's not set
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub equity_interval: Option<i64>,
+    #[serde(default)]
+    pub annualization: Annualization,
+    /// The seed of the position ids, the same seed gives the byte-identical positions
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub seed: Option<u64>,
+    /// The period between the checkpoints in milliseconds, the run isn't checkpointed if it's not set
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub checkpoint_interval: Option<i64>,
+    /// The futures settings, the spot is traded if it's not set
+    #[serde(default)]
+    pub futures: Option<FuturesSettings>,
+    /// The slippage and the latency models, the market orders are filled at once
+    /// by the kline price if they're not set
+    #[serde(default)]
+    pub execution: ExecutionSettings,
+    /// The maker and taker fee schedule, the flat `commission` is used if it's not set
+    #[serde(default)]
+    pub fees: Option<FeeSettings>,
+    /// The trading rules of the spot symbols by the lowercase symbol. They're read from the rules
+    /// of the exchange if they're not set and stored with the request, so the result is
+    /// reproduced with them.
+    #[serde(default)]
+    pub rules: Option<BTreeMap<String, SymbolRules>>,
+}
+
+impl BacktestRequest {
+    pub fn backtest_settings(&self) -> BacktestSettings {
+        BacktestSettings {
+            symbols: self.all_symbols(),
+            exchange: self.exchange.to_lowercase(),
+            date_start: datetime_str_to_i64(self.date_start.clone()),
+            date_end: datetime_str_to_i64(self.date_end.clone()),
+            deposit: self.deposit,
+            commission: self.commission,
+            market_data_type: self.market_data_type.clone(),
+            allocation: self.allocation.clone(),
+            price_path: self.price_path.clone(),
+            timeframes: self.timeframes.clone(),
+            equity_interval: self
+                .equity_interval
+                .unwrap_or(self.chart_market_data_type.value().1),
+            annualization: self.annualization.clone(),
+            seed: self.seed,
+            checkpoint: None,
+            futures: self.futures.clone(),
+            execution: self.execution.clone(),
+            fees: self.fees.clone(),
+            progress: Progress::default(),
+        }
+    }
+
+    /// Returns the lowercase symbols of the strategies, the `symbol` is the first one
+    pub fn all_symbols(&self) -> Vec<String> {
+        std::iter::once(&self.symbol)
+            .chain(&self.symbols)
+            .map(|symbol| symbol.to_lowercase())
+            .collect()
+    }
+
+    /// Returns the trading rules of the backtest by the lowercase symbol, the rules without
+    /// the filters if the symbol has no saved rules. The saved rules are of the spot,
+    /// so the futures have none.
+    pub fn symbol_rules(&self, data_path: PathBuf) -> Option<BTreeMap<String, SymbolRules>> {
+        if self.futures.is_some() {
+            return None;
+        }
+        let rules = self
+            .all_symbols()
+            .into_iter()
+            .map(|symbol| {
+                let rules = self
+                    .rules
+                    .as_ref()
+                    .and_then(|rules| rules.get(&symbol).cloned())
+                    .or_else(|| {
+                        get_symbol_rules(data_path.clone(), self.exchange.clone(), symbol.clone())
+                    });
+                (symbol, rules.unwrap_or_default())
+            })
+            .collect();
+        Some(rules)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if [&self.market_data_type, &self.chart_market_data_type]
+            .contains(&&MarketDataType::FundingRate)
+        {
+            return Err("The funding rates are not klines".into());
+        }
+        Timeframes::validate(&self.market_data_type, &self.timeframes)?;
+        let symbols = self.all_symbols();
+        if (1..symbols.len()).any(|i| symbols[..i].contains(&symbols[i])) {
+            return Err("The symbols should be different".into());
+        }
+        if let Allocation::Weighted(weights) = &self.allocation {
+            if weights.len() != symbols.len() {
+                return Err("Every symbol should have a weight".into());
+            }
+            if weights
+                .iter()
+                .any(|weight| !weight.is_finite() || *weight < 0.0)
+            {
+                return Err("The weights should be finite and not negative".into());
+            }
+            if weights.iter().sum::<f64>() <= 0.0 {
+                return Err("The sum of the weights should be positive".into());
+            }
+        }
+        self.execution.validate()?;
+        if let Some(fees) = &self.fees {
+            fees.validate()?;
+            // The margin of the USD-M futures is in the quote
+            if self.futures.is_some() && fees.asset == FeeAsset::Base {
+                return Err("The futures fees can't be paid in the base asset".into());
+            }
+        }
+        match &self.futures {
+            Some(_) if self.rules.is_some() => {
+                Err("The trading rules are of the spot symbols".into())
+            }
+            Some(futures) => futures.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the schema of the fields
+    pub fn parameters() -> Vec<ParameterSchema> {
+        let market_data_types: Vec<String> = MarketDataType::iter()
+            .filter(|mdt| *mdt != MarketDataType::FundingRate)
+            .map(|mdt| mdt.value().0)
+            .collect();
+        vec![
+            ParameterSchema::required("symbol", ParameterKind::Text, "The traded symbol"),
+            ParameterSchema::optional(
+                "symbols",
+                ParameterKind::Object,
+                Some(serde_json::json!([])),
+                "The symbols traded besides the symbol with the same parameters",
+            ),
+            ParameterSchema::required("exchange", ParameterKind::Text, "The exchange of the data"),
+            ParameterSchema::required(
+                "market_data_type",
+                ParameterKind::Options,
+                "The klines the strategy runs on",
+            )
+            .with_options(market_data_types.clone()),
+            ParameterSchema::required(
+                "chart_market_data_type",
+                ParameterKind::Options,
+                "The klines of the chart and the default equity interval",
+            )
+            .with_options(market_data_types),
+            ParameterSchema::required("date_start", ParameterKind::Date, "The first day"),
+            ParameterSchema::required(
+                "date_end",
+                ParameterKind::Date,
+                "The day after the last one",
+            ),
+            ParameterSchema::required("deposit", ParameterKind::Number, "The initial cash"),
+            ParameterSchema::required(
+                "commission",
+                ParameterKind::Number,
+                "The commission of an order in percent if there are no fees",
+            ),
+            ParameterSchema::optional(
+                "allocation",
+                ParameterKind::Object,
+                serde_json::to_value(Allocation::default()).ok(),
+                "The deposit distribution between the symbols: \"FixedSplit\", \"FirstCome\" \
+                 or {\"Weighted\": [...]} with a weight of every symbol, the symbol first",
+            ),
+            ParameterSchema::optional(
+                "price_path",
+                ParameterKind::Options,
+                serde_json::to_value(PricePath::default()).ok(),
+                "The order of the prices inside a kline",
+            )
+            .with_options(
+                ["Close", "OpenHighLowClose", "OpenLowHighClose", "WorstCase"]
+                    .map(String::from)
+                    .to_vec(),
+            ),
+            ParameterSchema::optional(
+                "timeframes",
+                ParameterKind::Object,
+                Some(serde_json::json!([])),
+                "The higher timeframes the strategy reads like [\"1h\", \"1d\"], \
+                 every one a multiple of the market data type",
+            ),
+            ParameterSchema::optional(
+                "equity_interval",
+                ParameterKind::Integer,
+                None,
+                "The equity sampling interval in milliseconds",
+            ),
+            ParameterSchema::optional(
+                "annualization",
+                ParameterKind::Object,
+                serde_json::to_value(Annualization::default()).ok(),
+                "How the returns are annualized in the metrics",
+            ),
+            ParameterSchema::optional(
+                "seed",
+                ParameterKind::Integer,
+                None,
+                "The seed of the position ids, the run is reproducible if it's set",
+            ),
+            ParameterSchema::optional(
+                "checkpoint_interval",
+                ParameterKind::Integer,
+                None,
+                "The period between the checkpoints in milliseconds",
+            ),
+            ParameterSchema::optional(
+                "futures",
+                ParameterKind::Object,
+                None,
+                "The leverage, the margin mode, the maintenance margin and the liquidation fee \
+                 of the futures, the spot is traded if it's empty",
+            ),
+            ParameterSchema::optional(
+                "execution",
+                ParameterKind::Object,
+                serde_json::to_value(ExecutionSettings::default()).ok(),
+                "The slippage model of the market orders like {\"model\": \"fixed\", \"bps\": 5} \
+                 and their latency like {\"ms\": 200} or {\"bars\": 1}",
+            ),
+            ParameterSchema::optional(
+                "fees",
+                ParameterKind::Object,
+                None,
+                "The VIP tier, the maker and taker fees in percent, the BNB discount and the fee \
+                 asset (base, quote or other), the flat commission is used if it's empty",
+            ),
+            ParameterSchema::optional(
+                "rules",
+                ParameterKind::Object,
+                None,
+                "The tick size, the step size, the minimal and maximal quantity and the minimal \
+                 value of the spot orders by the lowercase symbol, the saved rules of the symbols \
+                 are used if it's empty",
+            ),
+        ]
+    }
 }
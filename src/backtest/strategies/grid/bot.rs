@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use crate::data_models::market_data::{
     enums::{OrderType, Side},
     kline::KLine,
@@ -5,16 +7,29 @@ use crate::data_models::market_data::{
 };
 
 use super::{
-    grid_trigger::{generate_grid_triggers, GridTrigger},
-    settings::GridSettings,
+    grid_trigger::{generate_grid_triggers, generate_short_grid_triggers, GridTrigger},
+    settings::{GridMode, GridSettings},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GridBot {
     pub settings: GridSettings,
     pub current_price: f64,
+    pub last_price: f64,
     pub order_size: f64,
     pub triggers: Vec<GridTrigger>,
+    #[serde(default)]
+    pub short: ShortGrid,
+}
+
+/// The short side of the grid. A Sell trigger opens a short when the price rises to it,
+/// a Buy trigger waits for the price to fall back to it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShortGrid {
+    pub current_price: f64,
+    pub triggers: Vec<GridTrigger>,
+    /// The grid level of the first trigger
+    pub offset: usize,
 }
 
 impl GridBot {
@@ -22,29 +37,75 @@ impl GridBot {
         Self {
             settings: settings.clone(),
             current_price: 0.0,
+            last_price: 0.0,
             order_size: settings.deposit / settings.grids_count as f64,
             triggers: Vec::new(),
+            short: ShortGrid::default(),
         }
     }
 
+    /// Returns the grid level and the orders of the position to open
     pub fn run(&mut self, kline: &KLine) -> Option<(usize, Vec<Order>)> {
+        if self.triggers.is_empty() && self.short.triggers.is_empty() {
+            self.init(kline.close);
+        }
+        let last_price = self.last_price;
+        self.last_price = kline.close;
+        let long = self.run_long(kline, last_price);
+        let short = self.run_short(kline, last_price);
+        long.or(short)
+    }
+
+    fn init(&mut self, price: f64) {
+        self.current_price = price;
+        self.last_price = price;
+        self.short.current_price = price;
+        let settings = &self.settings;
+        let long = generate_grid_triggers(
+            settings.price_low,
+            settings.price_high,
+            settings.grids_count,
+            price,
+        );
+        let short = generate_short_grid_triggers(
+            settings.price_low,
+            settings.price_high,
+            settings.grids_count,
+            price,
+        );
+        match settings.mode {
+            GridMode::Long => self.triggers = long,
+            GridMode::Short => self.short.triggers = short,
+            // The longs are opened below the start price and the shorts above it
+            GridMode::Neutral => {
+                let long_end = long
+                    .iter()
+                    .position(|trigger| trigger.price >= price)
+                    .map_or(long.len(), |i| i + 1);
+                let short_start = short
+                    .iter()
+                    .rposition(|trigger| trigger.price <= price)
+                    .unwrap_or(0);
+                self.triggers = long[..long_end].to_vec();
+                self.short.triggers = short[short_start..].to_vec();
+                self.short.offset = short_start;
+            }
+        }
+    }
+
+    fn run_long(&mut self, kline: &KLine, last_price: f64) -> Option<(usize, Vec<Order>)> {
         if self.triggers.is_empty() {
-            self.current_price = kline.close;
-            self.triggers = generate_grid_triggers(
-                self.settings.price_low,
-                self.settings.price_high,
-                self.settings.grids_count,
-                kline.close,
-            )
+            return None;
         }
         if kline.close <= self.current_price {
             if let Some(i) = check_buy_action(&mut self.triggers, kline.close) {
-                self.triggers[i].trigger_type = Side::Sell;
                 let price = self.triggers[i].price;
-                // It is a crutch. TODO: Fix it.
-                if price > kline.high || price < kline.low {
+                // The trigger is above the price, so the price has to come down through it
+                if price > last_price {
+                    self.current_price = kline.close;
                     return None;
                 }
+                self.triggers[i].trigger_type = Side::Sell;
                 return Some((
                     i,
                     vec![
@@ -73,6 +134,46 @@ impl GridBot {
         self.current_price = kline.close;
         None
     }
+
+    fn run_short(&mut self, kline: &KLine, last_price: f64) -> Option<(usize, Vec<Order>)> {
+        if self.short.triggers.is_empty() {
+            return None;
+        }
+        let short = &mut self.short;
+        if kline.close >= short.current_price {
+            if let Some(i) = check_short_action(&short.triggers, kline.close) {
+                let price = short.triggers[i].price;
+                // The trigger is below the price, so the price has to come up through it
+                if price < last_price {
+                    short.current_price = kline.close;
+                    return None;
+                }
+                short.triggers[i].trigger_type = Side::Buy;
+                return Some((
+                    short.offset + i,
+                    vec![
+                        Order::new(kline.date, price, Side::Sell, OrderType::Limit)
+                            .updated(kline.date)
+                            .with_price_executed(price)
+                            .with_qty(self.order_size / price)
+                            .filled(),
+                        Order::new(
+                            kline.date,
+                            short.triggers[i - 1].price,
+                            Side::Buy,
+                            OrderType::TakeProfit,
+                        )
+                        .with_qty(self.order_size / price),
+                    ],
+                ));
+            }
+        } else if let Some(i) = check_cover_action(&short.triggers, kline.close) {
+            short.triggers[i].trigger_type = Side::Sell;
+            return None;
+        }
+        short.current_price = kline.close;
+        None
+    }
 }
 
 fn check_buy_action(triggers: &Vec<GridTrigger>, last_price: f64) -> Option<usize> {
@@ -94,6 +195,24 @@ fn check_sell_action(triggers: &Vec<GridTrigger>, last_price: f64) -> Option<usi
     None
 }
 
+fn check_short_action(triggers: &Vec<GridTrigger>, last_price: f64) -> Option<usize> {
+    for i in (1..triggers.len()).rev() {
+        if triggers[i].trigger_type == Side::Sell && last_price >= triggers[i].price {
+            return Some(i);
+        }
+    }
+    None
+}
+
+fn check_cover_action(triggers: &Vec<GridTrigger>, last_price: f64) -> Option<usize> {
+    for i in 0..triggers.len() {
+        if triggers[i].trigger_type == Side::Buy && last_price <= triggers[i].price {
+            return Some(i);
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -154,6 +273,66 @@ mod test {
         assert_eq!(bot.run(&KLine::blank().with_close(10.0)), None);
     }
 
+    fn get_orders_sell(
+        price: f64,
+        tp_price: f64,
+        qty: f64,
+        grid_position: usize,
+    ) -> Option<(usize, Vec<Order>)> {
+        Some((
+            grid_position,
+            vec![
+                Order::new(0, price, Side::Sell, OrderType::Limit)
+                    .updated(0)
+                    .with_price_executed(price)
+                    .with_qty(qty)
+                    .filled(),
+                Order::new(0, tp_price, Side::Buy, OrderType::TakeProfit).with_qty(qty),
+            ],
+        ))
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_run_short() {
+        let mut bot = GridBot::new(GridSettings::new(
+            0.0, 10.0, 5, 100.0, 5.0, None, None, true,
+        ).with_mode(GridMode::Short));
+
+        assert_eq!(bot.run(&KLine::blank().with_close(5.0)), None);
+        assert!(bot.triggers.is_empty());
+        assert_eq!(bot.run(&KLine::blank().with_close(5.9)), None);
+        assert_eq!(bot.run(&KLine::blank().with_close(6.1)), get_orders_sell(6.0, 4.0, 20.0 / 6.0, 3));
+        assert_eq!(bot.run(&KLine::blank().with_close(6.5)), None);
+        assert_eq!(bot.run(&KLine::blank().with_close(8.0)), get_orders_sell(8.0, 6.0, 2.5, 4));
+        // The levels below the start wait for the price to fall to them, one level per kline
+        assert_eq!(bot.run(&KLine::blank().with_close(3.9)), None);
+        assert_eq!(bot.short.triggers[2].trigger_type, Side::Sell);
+        assert_eq!(bot.run(&KLine::blank().with_close(3.9)), None);
+        assert_eq!(bot.run(&KLine::blank().with_close(3.9)), None);
+        assert_eq!(bot.run(&KLine::blank().with_close(3.9)), None);
+        assert_eq!(bot.run(&KLine::blank().with_close(4.1)), get_orders_sell(4.0, 2.0, 5.0, 2));
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_run_neutral() {
+        let mut bot = GridBot::new(GridSettings::new(
+            0.0, 10.0, 5, 100.0, 5.0, None, None, true,
+        ).with_mode(GridMode::Neutral));
+
+        assert_eq!(bot.run(&KLine::blank().with_close(5.0)), None);
+        // The longs are on the levels up to 6 and the shorts are from 4
+        assert_eq!(bot.triggers.len(), 4);
+        assert_eq!(bot.short.triggers.len(), 4);
+        assert_eq!(bot.short.offset, 2);
+        assert_eq!(bot.run(&KLine::blank().with_close(3.9)), get_orders_buy(4.0, 6.0, 5.0, 2));
+        assert_eq!(bot.run(&KLine::blank().with_close(6.1)), get_orders_sell(6.0, 4.0, 20.0 / 6.0, 3));
+        // The long grid doesn't buy above the start
+        assert_eq!(bot.run(&KLine::blank().with_close(5.9)), None);
+        assert_eq!(bot.run(&KLine::blank().with_close(8.1)), get_orders_sell(8.0, 6.0, 2.5, 4));
+    }
+
     #[rustfmt::skip]
     #[test]
     fn test_run_2() {
@@ -193,6 +372,23 @@ mod test {
         assert_eq!(bot.run(&KLine::blank().with_close(3.9)), None);
     }
 
+    #[rustfmt::skip]
+    #[test]
+    fn test_run_untouched_trigger() {
+        let mut bot = GridBot::new(GridSettings::new(
+            0.0, 10.0, 5, 100.0, 5.0, None, None, true,
+        ));
+
+        assert_eq!(bot.run(&KLine::blank().with_close(5.0)), None);
+        assert_eq!(bot.run(&KLine::blank().with_close(6.5)), None);
+        assert_eq!(bot.triggers[3].trigger_type, Side::Buy);
+        assert_eq!(bot.run(&KLine::blank().with_close(5.5)), None);
+        // The trigger at 6.0 is above the prices since the last crossing
+        assert_eq!(bot.run(&KLine::blank().with_close(5.2)), None);
+        assert_eq!(bot.triggers[3].trigger_type, Side::Buy);
+        assert_eq!(bot.run(&KLine::blank().with_close(3.9)), get_orders_buy(4.0, 6.0, 5.0, 2));
+    }
+
     fn get_triggers() -> Vec<GridTrigger> {
         vec![
             GridTrigger {
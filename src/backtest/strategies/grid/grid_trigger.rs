@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use crate::data_models::market_data::enums::Side;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GridTrigger {
     pub price: f64,
     pub trigger_type: Side,
@@ -38,6 +40,31 @@ pub fn generate_grid_triggers(
         .collect()
 }
 
+/// Returns the triggers of the short grid, the levels above the start price wait
+/// for the price to rise to them
+pub fn generate_short_grid_triggers(
+    price_low: f64,
+    price_high: f64,
+    grids_count: i64,
+    start_price: f64,
+) -> Vec<GridTrigger> {
+    generate_trigger_prices(price_low, price_high, grids_count)
+        .iter()
+        .map(|price| {
+            let trigger_type = if *price > start_price {
+                Side::Sell
+            } else {
+                Side::Buy
+            };
+
+            GridTrigger {
+                price: *price,
+                trigger_type,
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1,4 +1,5 @@
 pub mod bot;
 pub mod grid_trigger;
+pub mod runner;
 pub mod settings;
 pub mod strategy;
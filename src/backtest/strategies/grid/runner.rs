@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+
+use crate::backtest::{
+    progress::Progress,
+    runner::{run_strategy, StrategyBacktest},
+};
+
+use super::{bot::GridBot, settings::GridSettingsRequest, strategy::GridStrategy};
+
+/// Runs the grid strategy with the settings of the request
+pub fn run_grid(request: &GridSettingsRequest, data_path: PathBuf) -> StrategyBacktest {
+    run_grid_with_progress(request, data_path, Progress::default())
+}
+
+/// Runs the grid strategy reporting the progress, the result is partial if it's cancelled
+pub fn run_grid_with_progress(
+    request: &GridSettingsRequest,
+    data_path: PathBuf,
+    progress: Progress,
+) -> StrategyBacktest {
+    run_strategy(request, &request.backtest, data_path, progress, |s| {
+        // The futures grid orders are sized by the leveraged deposit
+        let bot = GridBot::new(request.grid_settings(s.deposit * s.leverage()));
+        GridStrategy::new(s, bot)
+    })
+}
@@ -1,12 +1,15 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use serde_aux::field_attributes::{
     deserialize_number_from_string, deserialize_option_number_from_string,
 };
 
-use crate::data_models::market_data::enums::MarketDataType;
+use crate::backtest::{
+    settings::BacktestRequest,
+    strategies::registry::{ParameterKind, ParameterSchema},
+};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GridSettings {
     pub price_low: f64,
     pub price_high: f64,
@@ -16,6 +19,21 @@ pub struct GridSettings {
     pub grid_sl: Option<f64>,
     pub grid_tp: Option<f64>,
     pub sell_all: bool, // true by default
+    #[serde(default)]
+    pub mode: GridMode,
+}
+
+/// The direction of the grid positions, the short and neutral grids trade the futures
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GridMode {
+    /// Buys below the price and sells one level above
+    #[default]
+    Long,
+    /// Sells above the price and buys back one level below
+    Short,
+    /// Buys on the levels below the start price and sells on the levels above it
+    Neutral,
 }
 
 impl GridSettings {
@@ -39,22 +57,21 @@ impl GridSettings {
             grid_sl,
             grid_tp,
             sell_all,
+            mode: GridMode::Long,
         }
     }
+
+    #[cfg(test)]
+    pub fn with_mode(mut self, mode: GridMode) -> Self {
+        self.mode = mode;
+        self
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GridSettingsRequest {
-    pub symbol: String,
-    pub exchange: String,
-    pub market_data_type: MarketDataType,
-    pub chart_market_data_type: MarketDataType,
-    pub date_start: String,
-    pub date_end: String,
-    #[serde(deserialize_with = "deserialize_number_from_string")]
-    pub deposit: f64,
-    #[serde(deserialize_with = "deserialize_number_from_string")]
-    pub commission: f64,
+    #[serde(flatten)]
+    pub backtest: BacktestRequest,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub price_low: f64,
     #[serde(deserialize_with = "deserialize_number_from_string")]
@@ -69,4 +86,76 @@ pub struct GridSettingsRequest {
     pub grid_tp: Option<f64>,
     #[serde(default)]
     pub sell_all: bool, // true by default
+    #[serde(default)]
+    pub mode: GridMode,
+}
+
+impl GridSettingsRequest {
+    /// Returns the settings of the grid bot of the strategy with the `deposit`
+    pub fn grid_settings(&self, deposit: f64) -> GridSettings {
+        GridSettings {
+            price_low: self.price_low,
+            price_high: self.price_high,
+            grids_count: self.grids_count,
+            deposit,
+            grid_trigger: self.grid_trigger,
+            grid_sl: self.grid_sl,
+            grid_tp: self.grid_tp,
+            sell_all: self.sell_all,
+            mode: self.mode.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.backtest.validate()?;
+        if self.mode != GridMode::Long && self.backtest.futures.is_none() {
+            return Err("The short and neutral grids need the futures settings".into());
+        }
+        // The grid orders are on the exchange before the price reaches them
+        if self.backtest.execution.latency.is_some() {
+            return Err("The grid orders have no latency".into());
+        }
+        Ok(())
+    }
+
+    /// Returns the schema of the grid fields
+    pub fn parameters() -> Vec<ParameterSchema> {
+        vec![
+            ParameterSchema::required("price_low", ParameterKind::Number, "The lowest grid price"),
+            ParameterSchema::required(
+                "price_high",
+                ParameterKind::Number,
+                "The highest grid price",
+            ),
+            ParameterSchema::required(
+                "grids_count",
+                ParameterKind::Integer,
+                "The number of the grids between the prices",
+            ),
+            ParameterSchema::required(
+                "grid_trigger",
+                ParameterKind::Number,
+                "The grid start price",
+            ),
+            ParameterSchema::nullable("grid_sl", ParameterKind::Number, "The grid stop loss price"),
+            ParameterSchema::nullable(
+                "grid_tp",
+                ParameterKind::Number,
+                "The grid take profit price",
+            ),
+            ParameterSchema::optional(
+                "sell_all",
+                ParameterKind::Boolean,
+                Some(false.into()),
+                "Sell all the coins at the end",
+            ),
+            ParameterSchema::optional(
+                "mode",
+                ParameterKind::Options,
+                serde_json::to_value(GridMode::default()).ok(),
+                "The direction of the grid positions, the short and neutral grids need the futures",
+            )
+            .with_options(vec!["long".into(), "short".into(), "neutral".into()]),
+        ]
+    }
 }
@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     backtest::{
@@ -6,24 +8,36 @@ use crate::{
         strategies::{
             strategy_trait::Strategy,
             strategy_utils::{check_tp_sl, remove_closed_positions},
+            timeframes::Timeframes,
         },
     },
-    data_models::market_data::{enums::OrderStatus, kline::KLine, position::Position},
+    data_handlers::kline_source::KLineSource,
+    data_models::market_data::{
+        enums::{OrderStatus, Side},
+        funding_rate::FundingRate,
+        kline::KLine,
+        position::{Position, PositionIds, PositionSide},
+    },
 };
 
-use super::bot::GridBot;
+use super::{bot::GridBot, settings::GridMode};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GridStrategy {
     pub strategy_settings: StrategySettings,
     pub bot: GridBot,
-    pub grid_position_binding: HashMap<usize, String>,
-    pub klines: Vec<KLine>,
+    pub grid_position_binding: BTreeMap<usize, String>,
+    pub position_ids: PositionIds,
+    pub klines: KLineSource,
+    pub timeframes: Timeframes,
     pub positions_opened: Vec<Position>,
     pub positions_closed: Vec<Position>,
     pub current_budget: f64,
     pub current_qty: f64,
     pub current_kline_position: usize,
+    /// The funding rates are loaded with the klines of every range
+    #[serde(skip)]
+    pub funding_rates: VecDeque<FundingRate>,
 }
 
 impl GridStrategy {
@@ -31,13 +45,141 @@ impl GridStrategy {
         Self {
             strategy_settings: strategy_settings.clone(),
             bot,
-            grid_position_binding: HashMap::new(),
-            klines: Vec::new(),
+            grid_position_binding: BTreeMap::new(),
+            position_ids: PositionIds::new(strategy_settings.seed),
+            klines: KLineSource::default(),
+            timeframes: Timeframes::new(
+                &strategy_settings.market_data_type,
+                &strategy_settings.timeframes,
+            ),
             positions_opened: Vec::new(),
             positions_closed: Vec::new(),
             current_budget: strategy_settings.deposit,
             current_qty: 0.0,
             current_kline_position: 0,
+            funding_rates: VecDeque::new(),
+        }
+    }
+
+    /// Runs the grid for one segment of the price path of the `bar`
+    fn run_segment(&mut self, kline: &KLine, bar: &KLine) {
+        let positions_count = self.positions_opened.len();
+        self.check_liquidation(kline);
+        if self.positions_opened.len() != positions_count {
+            let positions_opened = &self.positions_opened;
+            self.grid_position_binding
+                .retain(|_, id| positions_opened.iter().any(|pos| &pos.id == id));
+        }
+
+        let fees = self.strategy_settings.fees();
+        check_tp_sl(
+            kline,
+            bar,
+            &mut self.positions_opened,
+            &fees,
+            &self.strategy_settings.price_path,
+            &self.strategy_settings.execution,
+        );
+        let mut closed_positions = remove_closed_positions(&mut self.positions_opened);
+        if !closed_positions.is_empty() {
+            for pos in closed_positions.iter_mut() {
+                match pos.side {
+                    PositionSide::Long => {
+                        self.update_strategy_data(
+                            pos.volume_sell() * pos.weighted_avg_price_sell(),
+                            -pos.volume_sell(),
+                        );
+                        self.pay_fee(
+                            pos.commission_sell(),
+                            pos.weighted_avg_price_sell(),
+                            &Side::Sell,
+                        );
+                    }
+                    PositionSide::Short => {
+                        self.update_strategy_data(
+                            -1.0 * pos.volume_sell() * pos.weighted_avg_price_buy(),
+                            pos.volume_sell(),
+                        );
+                        self.pay_fee(
+                            pos.commission_buy(),
+                            pos.weighted_avg_price_buy(),
+                            &Side::Buy,
+                        );
+                    }
+                }
+                pos.calculate_pnl();
+                for (key, value) in self.grid_position_binding.clone().iter() {
+                    if value == &pos.id {
+                        self.grid_position_binding.remove(key);
+                        break;
+                    }
+                }
+            }
+            self.positions_closed.extend(closed_positions);
+        }
+
+        match self.bot.run(kline) {
+            Some((grid_position, mut orders)) => {
+                if !self.can_open(self.bot.order_size, kline.close) {
+                    return;
+                }
+                if self.grid_position_binding.get(&grid_position).is_some() {
+                    return;
+                }
+                // The exchange rejects the orders below the minimums
+                if let Some(rules) = &self.strategy_settings.rules {
+                    if !rules.apply(&mut orders) {
+                        return;
+                    }
+                }
+                let side = match orders.first().map(|order| &order.side) {
+                    Some(Side::Sell) => PositionSide::Short,
+                    _ => PositionSide::Long,
+                };
+                let mut position = Position::new_with_id(
+                    self.position_ids.next_id(),
+                    self.strategy_settings.symbol.clone(),
+                )
+                .with_side(side);
+                for order in orders.iter_mut() {
+                    if order.status == OrderStatus::Filled {
+                        let qty = match order.side {
+                            Side::Buy => order.qty.unwrap(),
+                            Side::Sell => -order.qty.unwrap(),
+                        };
+                        self.update_strategy_data(-1.0 * qty * order.price, qty);
+                        order.set_commission(
+                            order.price_executed.unwrap(),
+                            order.qty.unwrap(),
+                            fees.rate(&order.order_type),
+                        );
+                        self.pay_fee(
+                            order.commission.unwrap(),
+                            order.price_executed.unwrap(),
+                            &order.side,
+                        );
+                    }
+                    position.orders.push(order.clone());
+                }
+                // The take profit sells only what is held after the buy fee taken from the base
+                let qty = self.held_qty(&position);
+                if position.side == PositionSide::Long && qty != position.volume_all() {
+                    let qty = match &self.strategy_settings.rules {
+                        Some(rules) => rules.round_qty(qty),
+                        None => qty,
+                    };
+                    for order in position.orders.iter_mut() {
+                        if order.status == OrderStatus::New {
+                            order.qty = Some(qty);
+                        }
+                    }
+                }
+
+                self.grid_position_binding
+                    .insert(grid_position, position.id.clone());
+                self.positions_opened.push(position);
+            }
+            None => (),
         }
     }
 }
@@ -46,9 +188,18 @@ impl Strategy for GridStrategy {
     fn strategy_settings(&self) -> StrategySettings {
         self.strategy_settings.clone()
     }
-    fn klines(&self) -> &Vec<KLine> {
+    fn klines(&self) -> &KLineSource {
         &self.klines
     }
+    fn klines_mut(&mut self) -> &mut KLineSource {
+        &mut self.klines
+    }
+    fn timeframes(&self) -> &Timeframes {
+        &self.timeframes
+    }
+    fn timeframes_mut(&mut self) -> &mut Timeframes {
+        &mut self.timeframes
+    }
     fn positions_opened(&self) -> &Vec<Position> {
         &self.positions_opened
     }
@@ -70,7 +221,10 @@ impl Strategy for GridStrategy {
     fn current_kline_position(&self) -> usize {
         self.current_kline_position
     }
-    fn set_klines(&mut self, klines: Vec<KLine>) {
+    fn funding_rates_mut(&mut self) -> &mut VecDeque<FundingRate> {
+        &mut self.funding_rates
+    }
+    fn set_klines(&mut self, klines: KLineSource) {
         self.klines = klines;
     }
     fn set_positions_opened(&mut self, positions_opened: Vec<Position>) {
@@ -88,60 +242,19 @@ impl Strategy for GridStrategy {
     fn set_current_kline_position(&mut self, current_kline_position: usize) {
         self.current_kline_position = current_kline_position;
     }
+    fn set_funding_rates(&mut self, funding_rates: VecDeque<FundingRate>) {
+        self.funding_rates = funding_rates;
+    }
 
     fn run(&mut self, kline: &KLine) {
-        check_tp_sl(
-            kline,
-            &mut self.positions_opened,
-            self.strategy_settings.commission,
-        );
-        let mut closed_positions = remove_closed_positions(&mut self.positions_opened);
-        if !closed_positions.is_empty() {
-            for pos in closed_positions.iter_mut() {
-                self.update_strategy_data(
-                    pos.volume_buy() * pos.weighted_avg_price_sell(),
-                    -pos.volume_buy(),
-                );
-                pos.calculate_pnl();
-                for (key, value) in self.grid_position_binding.clone().iter() {
-                    if value == &pos.id {
-                        self.grid_position_binding.remove(key);
-                        break;
-                    }
-                }
-            }
-            self.positions_closed.extend(closed_positions);
-        }
-
-        match self.bot.run(kline) {
-            Some((grid_position, mut orders)) => {
-                if self.current_budget < self.bot.order_size {
-                    return;
-                }
-                if self.grid_position_binding.get(&grid_position).is_some() {
-                    return;
-                }
-                let mut position = Position::new(self.strategy_settings.symbol.clone());
-                for order in orders.iter_mut() {
-                    if order.status == OrderStatus::Filled {
-                        self.update_strategy_data(
-                            -1.0 * order.qty.unwrap() * order.price,
-                            order.qty.unwrap(),
-                        );
-                        order.set_commission(
-                            order.price_executed.unwrap(),
-                            order.qty.unwrap(),
-                            self.strategy_settings.commission,
-                        );
-                    }
-                    position.orders.push(order.clone());
-                }
-
-                self.grid_position_binding
-                    .insert(grid_position, position.id.clone());
-                self.positions_opened.push(position);
-            }
-            None => (),
+        // The price goes against the net position, the one the grid opens if there is none
+        let side = match self.current_qty {
+            qty if qty < 0.0 => PositionSide::Short,
+            qty if qty == 0.0 && self.bot.settings.mode == GridMode::Short => PositionSide::Short,
+            _ => PositionSide::Long,
+        };
+        for segment in kline.price_path(&self.strategy_settings.price_path, &side) {
+            self.run_segment(&segment, kline);
         }
     }
 }
@@ -149,10 +262,21 @@ impl Strategy for GridStrategy {
 #[cfg(test)]
 mod test {
     use crate::{
-        backtest::strategies::grid::settings::GridSettings,
-        data_models::market_data::enums::{MarketDataType, OrderType, Side},
+        backtest::{
+            execution::{ExecutionSettings, Slippage},
+            fees::{FeeAsset, FeeSettings},
+            settings::{FuturesSettings, MarginMode},
+            strategies::grid::settings::{GridMode, GridSettings},
+        },
+        data_models::market_data::{
+            enums::{MarketDataType, OrderType, PricePath},
+            order::Order,
+            symbol_rules::SymbolRules,
+        },
     };
 
+    use crate::data_models::market_data::kline_trait::KLineTrait;
+
     use super::*;
 
     fn get_grid_settings() -> GridSettings {
@@ -172,6 +296,13 @@ mod test {
             date_end: 10,
             deposit: 100.0,
             commission: 0.0,
+            price_path: PricePath::Close,
+            timeframes: Vec::new(),
+            seed: None,
+            futures: None,
+            execution: ExecutionSettings::default(),
+            fees: None,
+            rules: None,
         }
     }
 
@@ -196,7 +327,7 @@ mod test {
             KLine::blank().with_date(8).with_close(00.0),
             KLine::blank().with_date(9).with_close(00.0),
             KLine::blank().with_date(10).with_close(00.0),
-        ]);
+        ].into());
 
         strategy.run_kline(0);
         assert_eq!(strategy.current_budget, 100.0);
@@ -243,4 +374,381 @@ mod test {
         assert_eq!(strategy.current_kline_position, 7);
         
     }
+
+    #[rustfmt::skip]
+    fn get_path_strategy(price_path: PricePath) -> GridStrategy {
+        let mut strategy_settings = get_grid_strategy_settings();
+        strategy_settings.price_path = price_path;
+        let mut strategy = GridStrategy::new(strategy_settings, get_grid_bot());
+        strategy.set_klines(vec![
+            KLine::zero_kline(0, 55.0),
+            KLine::blank().with_date(1).with_open(55.0).with_high(65.0).with_low(45.0).with_close(58.0),
+            KLine::blank().with_date(2).with_open(58.0).with_high(62.0).with_low(58.0).with_close(61.0),
+        ].into());
+        strategy
+    }
+
+    #[test]
+    fn test_run_price_path() {
+        let mut strategy = get_path_strategy(PricePath::OpenLowHighClose);
+        strategy.run_kline(0);
+        strategy.run_kline(1);
+        // Bought at 50 on the way down and sold at 60 on the way up inside one kline
+        assert_eq!(strategy.positions_opened.len(), 0);
+        assert_eq!(strategy.positions_closed.len(), 1);
+        assert_eq!(
+            strategy.positions_closed[0].orders[0].price_executed,
+            Some(50.0)
+        );
+        assert_eq!(
+            strategy.positions_closed[0].orders[1].price_executed,
+            Some(60.0)
+        );
+        assert_eq!(strategy.current_budget, 100.0 - 10.0 + 10.0 / 50.0 * 60.0);
+
+        let mut strategy = get_path_strategy(PricePath::OpenHighLowClose);
+        strategy.run_kline(0);
+        strategy.run_kline(1);
+        // The price went down after the high, so the position is still opened
+        assert_eq!(strategy.positions_opened.len(), 1);
+        assert_eq!(strategy.positions_closed.len(), 0);
+        assert_eq!(
+            strategy.positions_opened[0].orders[0].price_executed,
+            Some(50.0)
+        );
+    }
+
+    #[test]
+    fn test_run_price_path_worst_case() {
+        let mut strategy = get_path_strategy(PricePath::WorstCase);
+        strategy.run_kline(0);
+        strategy.run_kline(1);
+        // The take profit isn't filled in the kline of the entry
+        assert_eq!(strategy.positions_opened.len(), 1);
+        assert_eq!(strategy.positions_closed.len(), 0);
+        strategy.run_kline(2);
+        assert_eq!(strategy.positions_opened.len(), 0);
+        assert_eq!(strategy.positions_closed.len(), 1);
+        assert_eq!(
+            strategy.positions_closed[0].orders[1].price_executed,
+            Some(60.0)
+        );
+        assert_eq!(strategy.positions_closed[0].orders[1].date_update, Some(2));
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_stop_slippage() {
+        let mut strategy_settings = get_grid_strategy_settings();
+        strategy_settings.price_path = PricePath::OpenHighLowClose;
+        strategy_settings.execution = ExecutionSettings {
+            slippage: Some(Slippage::Volatility { factor: 0.1 }),
+            latency: None,
+        };
+        let mut strategy = GridStrategy::new(strategy_settings, get_grid_bot());
+        strategy.set_klines(vec![
+            KLine::zero_kline(0, 55.0),
+            KLine::blank().with_date(1).with_open(55.0).with_high(65.0).with_low(45.0).with_close(58.0),
+            KLine::blank().with_date(2).with_open(46.0).with_high(47.0).with_low(40.0).with_close(44.0),
+        ].into());
+        strategy.run_kline(0);
+        strategy.run_kline(1);
+        strategy.positions_opened[0]
+            .orders
+            .push(Order::new(1, 48.0, Side::Sell, OrderType::Stop).with_qty(10.0 / 50.0));
+        strategy.run_kline(2);
+        // The kline gapped below the stop, so it's filled on the first segment at the open
+        // less the slippage of the whole kline range
+        let order = &strategy.positions_closed[0].orders[2];
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert!((order.price_executed.unwrap() - (46.0 - 0.7)).abs() < 1e-9);
+        assert!((order.slippage.unwrap() - 0.7 * 10.0 / 50.0).abs() < 1e-9);
+    }
+
+    fn get_futures_strategy(
+        margin_mode: MarginMode,
+        mode: GridMode,
+        leverage: f64,
+        closes: &[f64],
+    ) -> GridStrategy {
+        let mut strategy_settings = get_grid_strategy_settings();
+        strategy_settings.futures = Some(FuturesSettings {
+            leverage,
+            margin_mode,
+            maintenance_margin: 0.5,
+            liquidation_fee: 1.25,
+        });
+        let grid_settings = GridSettings::new(
+            0.0,
+            100.0,
+            10,
+            strategy_settings.deposit * leverage,
+            0.0,
+            None,
+            None,
+            false,
+        )
+        .with_mode(mode);
+        let mut strategy = GridStrategy::new(strategy_settings, GridBot::new(grid_settings));
+        strategy.set_klines(
+            closes
+                .iter()
+                .enumerate()
+                .map(|(i, close)| KLine::blank().with_date(i as i64).with_close(*close))
+                .collect::<Vec<KLine>>()
+                .into(),
+        );
+        strategy
+    }
+
+    #[test]
+    fn test_run_short() {
+        let mut strategy = get_futures_strategy(
+            MarginMode::Isolated,
+            GridMode::Short,
+            1.0,
+            &[50.0, 61.0, 49.0],
+        );
+        strategy.run_kline(0);
+        strategy.run_kline(1);
+        assert_eq!(strategy.positions_opened.len(), 1);
+        assert_eq!(strategy.positions_opened[0].side, PositionSide::Short);
+        assert_eq!(strategy.current_budget, 110.0);
+        assert_eq!(strategy.current_qty, -10.0 / 60.0);
+        assert_eq!(strategy.used_margin(), 10.0);
+        strategy.run_kline(2);
+        // Bought back one level below
+        assert_eq!(strategy.positions_opened.len(), 0);
+        assert_eq!(strategy.current_budget, 110.0 - 10.0 / 60.0 * 50.0);
+        assert_eq!(strategy.current_qty, 0.0);
+        assert!((strategy.positions_closed[0].pnl.unwrap() - 10.0 / 60.0 * 10.0).abs() < 1e-9);
+        assert!(strategy.grid_position_binding.is_empty());
+    }
+
+    #[test]
+    fn test_liquidation() {
+        let closes = [55.0, 49.0, 46.0, 45.0];
+        // The isolated position of 100 with the margin of 10
+        let mut strategy =
+            get_futures_strategy(MarginMode::Isolated, GridMode::Long, 10.0, &closes);
+        for date in 0..3 {
+            strategy.run_kline(date);
+        }
+        assert_eq!(strategy.positions_opened.len(), 1);
+        assert_eq!(strategy.current_budget, 0.0);
+        assert_eq!(strategy.used_margin(), 10.0);
+        strategy.run_kline(3);
+        let price = 50.0 * (1.0 - 1.0 / 10.0) / (1.0 - 0.5 / 100.0);
+        assert_eq!(strategy.positions_opened.len(), 0);
+        assert!(strategy.grid_position_binding.is_empty());
+        let order = strategy.positions_closed[0].orders.last().unwrap();
+        assert_eq!(order.order_type, OrderType::Liquidation);
+        assert_eq!(order.price_executed, Some(price));
+        assert_eq!(
+            strategy.current_budget,
+            2.0 * price - 2.0 * price * 1.25 / 100.0
+        );
+
+        // The cross position of 1000 uses the whole deposit as the margin
+        let mut strategy = get_futures_strategy(
+            MarginMode::Cross,
+            GridMode::Long,
+            10.0,
+            &[55.0, 49.0, 46.0, 45.0],
+        );
+        strategy.bot.order_size = 1000.0;
+        for date in 0..3 {
+            strategy.run_kline(date);
+        }
+        assert_eq!(strategy.positions_opened.len(), 1);
+        assert_eq!(strategy.current_budget, -900.0);
+        // There is no free margin for one more position
+        assert!(!strategy.can_open(1000.0, 46.0));
+        strategy.run_kline(3);
+        assert_eq!(strategy.positions_opened.len(), 0);
+        assert_eq!(
+            strategy.positions_closed[0]
+                .orders
+                .last()
+                .unwrap()
+                .price_executed,
+            Some(900.0 / (20.0 - 0.5 / 100.0 * 20.0))
+        );
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_liquidation_worst_case_short() {
+        let mut strategy = get_futures_strategy(MarginMode::Isolated, GridMode::Short, 10.0, &[]);
+        strategy.strategy_settings.price_path = PricePath::WorstCase;
+        strategy.set_klines(vec![
+            KLine::blank().with_date(0).with_open(50.0).with_high(50.0).with_low(50.0).with_close(50.0),
+            KLine::blank().with_date(1).with_open(50.0).with_high(61.0).with_low(50.0).with_close(61.0),
+            // The low reaches the take profit and the high reaches the liquidation
+            KLine::blank().with_date(2).with_open(60.0).with_high(70.0).with_low(49.0).with_close(55.0),
+        ].into());
+        strategy.run_kline(0);
+        strategy.run_kline(1);
+        assert_eq!(strategy.positions_opened.len(), 1);
+        assert_eq!(strategy.positions_opened[0].side, PositionSide::Short);
+        strategy.run_kline(2);
+        // The price goes up against the short first, so it's liquidated before the take profit
+        let position = &strategy.positions_closed[0];
+        assert_eq!(position.orders.last().unwrap().order_type, OrderType::Liquidation);
+        let price = 60.0 * (1.0 + 1.0 / 10.0) / (1.0 + 0.5 / 100.0);
+        assert!((position.orders.last().unwrap().price_executed.unwrap() - price).abs() < 1e-9);
+        assert!(position.orders.iter().all(|order| order.order_type != OrderType::TakeProfit
+            || order.status != OrderStatus::Filled));
+    }
+
+    #[test]
+    fn test_funding() {
+        let mut strategy = get_futures_strategy(
+            MarginMode::Isolated,
+            GridMode::Short,
+            1.0,
+            &[50.0, 61.0, 62.0, 49.0],
+        );
+        strategy.set_funding_rates(
+            vec![
+                FundingRate {
+                    date: 0,
+                    interval_hours: 8,
+                    rate: 0.01,
+                },
+                FundingRate {
+                    date: 2,
+                    interval_hours: 8,
+                    rate: 0.01,
+                },
+                FundingRate {
+                    date: 3,
+                    interval_hours: 8,
+                    rate: -0.02,
+                },
+            ]
+            .into(),
+        );
+        strategy.run_kline(0);
+        strategy.run_kline(1);
+        // There was no position at the first funding time
+        assert_eq!(strategy.current_budget, 110.0);
+        strategy.run_kline(2);
+        // The short receives the positive funding by the price before the kline
+        let funding = 10.0 / 60.0 * 61.0 * 0.01;
+        assert_eq!(strategy.positions_opened[0].funding, funding);
+        assert_eq!(strategy.current_budget, 110.0 + funding);
+        strategy.run_kline(3);
+        let funding = funding - 10.0 / 60.0 * 62.0 * 0.02;
+        assert!(strategy.funding_rates.is_empty());
+        let position = &strategy.positions_closed[0];
+        assert_eq!(position.funding, funding);
+        assert!((position.pnl.unwrap() - (10.0 / 60.0 * 10.0 + funding)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fees() {
+        let mut strategy_settings = get_grid_strategy_settings();
+        strategy_settings.fees = Some(FeeSettings {
+            maker: Some(0.1),
+            taker: Some(0.5),
+            ..Default::default()
+        });
+        let mut strategy = GridStrategy::new(strategy_settings, get_grid_bot());
+        strategy.set_klines(
+            [50.0, 59.0, 61.0, 49.0, 61.0]
+                .iter()
+                .enumerate()
+                .map(|(i, close)| KLine::blank().with_date(i as i64).with_close(*close))
+                .collect::<Vec<KLine>>()
+                .into(),
+        );
+        for ts in 0..5 {
+            strategy.run_kline(ts);
+        }
+        // Both the grid order and the take profit rest in the book, so they pay the maker fee
+        let position = &strategy.positions_closed[0];
+        assert_eq!(position.commission_buy(), 10.0 * 0.001);
+        assert!((position.commission_sell() - 12.0 * 0.001).abs() < 1e-9);
+        assert!((position.pnl.unwrap() - (2.0 - 0.022)).abs() < 1e-9);
+        assert!((strategy.current_budget - (100.0 + 2.0 - 0.022)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fee_asset() {
+        let mut strategy_settings = get_grid_strategy_settings();
+        strategy_settings.fees = Some(FeeSettings {
+            maker: Some(1.0),
+            asset: FeeAsset::Base,
+            ..Default::default()
+        });
+        let mut strategy = GridStrategy::new(strategy_settings, get_grid_bot());
+        strategy.set_klines(
+            [50.0, 59.0, 61.0, 49.0, 61.0]
+                .iter()
+                .enumerate()
+                .map(|(i, close)| KLine::blank().with_date(i as i64).with_close(*close))
+                .collect::<Vec<KLine>>()
+                .into(),
+        );
+        for ts in 0..4 {
+            strategy.run_kline(ts);
+        }
+        // The take profit sells the 0.2 bought without the fee of 0.002
+        assert_eq!(strategy.current_qty, 0.2 - 0.002);
+        assert_eq!(
+            strategy.positions_opened[0].orders[1].qty,
+            Some(0.2 - 0.002)
+        );
+        strategy.run_kline(4);
+        assert!(strategy.positions_opened.is_empty());
+        assert_eq!(strategy.current_qty, 0.0);
+        let sold = 0.198 * 60.0;
+        assert!((strategy.current_budget - (90.0 + sold * 0.99)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut strategy_settings = get_grid_strategy_settings();
+        strategy_settings.rules = Some(SymbolRules {
+            tick_size: 1.0,
+            step_size: 0.03,
+            min_qty: 0.03,
+            max_qty: 0.0,
+            min_notional: 5.0,
+        });
+        let mut strategy = GridStrategy::new(strategy_settings.clone(), get_grid_bot());
+        strategy.set_klines(
+            [50.0, 59.0, 61.0, 49.0]
+                .iter()
+                .enumerate()
+                .map(|(i, close)| KLine::blank().with_date(i as i64).with_close(*close))
+                .collect::<Vec<KLine>>()
+                .into(),
+        );
+        for ts in 0..4 {
+            strategy.run_kline(ts);
+        }
+        // The 0.2 of the grid order is rounded down to the step
+        let position = &strategy.positions_opened[0];
+        assert_eq!(position.orders[0].qty, Some(0.18));
+        assert_eq!(position.orders[1].qty, Some(0.18));
+        assert_eq!(strategy.current_budget, 100.0 - 0.18 * 50.0);
+
+        // The grid order of 10 is below the minimal value
+        strategy_settings.rules.as_mut().unwrap().min_notional = 15.0;
+        let mut strategy = GridStrategy::new(strategy_settings, get_grid_bot());
+        strategy.set_klines(
+            [50.0, 49.0]
+                .iter()
+                .enumerate()
+                .map(|(i, close)| KLine::blank().with_date(i as i64).with_close(*close))
+                .collect::<Vec<KLine>>()
+                .into(),
+        );
+        strategy.run_kline(0);
+        strategy.run_kline(1);
+        assert!(strategy.positions_opened.is_empty());
+        assert_eq!(strategy.current_budget, 100.0);
+    }
 }
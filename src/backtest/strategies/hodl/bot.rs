@@ -1,29 +1,46 @@
+use serde::{Deserialize, Serialize};
+
 use crate::backtest::action::Action;
 
 use super::settings::HodlSettings;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HodlBot {
     pub settings: HodlSettings,
-    pub last_purchase_ts: i64,
+    /// `None` until the first purchase, which is made on the first kline
+    pub last_purchase_ts: Option<i64>,
+    /// The next time of the schedule, `None` until the first kline
+    #[serde(default)]
+    pub next_purchase_ts: Option<i64>,
 }
 
 impl HodlBot {
     pub fn new(settings: HodlSettings) -> Self {
         Self {
             settings,
-            last_purchase_ts: 0,
+            last_purchase_ts: None,
+            next_purchase_ts: None,
         }
     }
 
     pub fn run(&mut self, ts: i64, current_budget: f64) -> Option<Action> {
-        if ts < self.last_purchase_ts + self.settings.purchase_period {
+        if !self.is_purchase_time(ts) {
             return None;
         }
         if current_budget < self.settings.purchase_size {
             return None;
         }
-        self.last_purchase_ts = ts;
+        self.last_purchase_ts = Some(ts);
         Some(Action::Buy(self.settings.purchase_size))
     }
+
+    /// The purchases follow the schedule if it's set, otherwise the purchase period
+    fn is_purchase_time(&mut self, ts: i64) -> bool {
+        match &self.settings.schedule {
+            Some(schedule) => schedule.is_due(&mut self.next_purchase_ts, ts),
+            None => self
+                .last_purchase_ts
+                .map_or(true, |last| ts >= last + self.settings.purchase_period),
+        }
+    }
 }
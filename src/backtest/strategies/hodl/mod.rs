@@ -1,3 +1,4 @@
 pub mod bot;
+pub mod runner;
 pub mod settings;
 pub mod strategy;
@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+use crate::backtest::{
+    progress::Progress,
+    runner::{run_strategy, StrategyBacktest},
+};
+
+use super::{bot::HodlBot, settings::HodlSettingsRequest, strategy::HodlStrategy};
+
+/// Runs the hodl strategy reporting the progress, the result is partial if it's cancelled
+pub fn run_hodl_with_progress(
+    request: &HodlSettingsRequest,
+    data_path: PathBuf,
+    progress: Progress,
+) -> StrategyBacktest {
+    run_strategy(request, &request.backtest, data_path, progress, |s| {
+        let bot = HodlBot::new(request.hodl_settings(s.deposit));
+        HodlStrategy::new(s, bot)
+    })
+}
@@ -1,11 +1,22 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::backtest::settings::BacktestSettings;
+use serde_aux::field_attributes::{
+    deserialize_number_from_string, deserialize_option_number_from_string,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::backtest::{
+    schedule::Schedule,
+    settings::BacktestRequest,
+    strategies::registry::{ParameterKind, ParameterSchema},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HodlSettings {
     pub purchase_period: i64,
     pub purchase_size: f64,
+    /// The calendar of the purchases, it's used instead of the `purchase_period` if it's set
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
 }
 
 impl HodlSettings {
@@ -14,12 +25,74 @@ impl HodlSettings {
         Self {
             purchase_period,
             purchase_size,
+            schedule: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HodlSettingsRequest {
-    pub backtest_settings: BacktestSettings,
-    pub hodl_settings: HodlSettings,
+    #[serde(flatten)]
+    pub backtest: BacktestRequest,
+    /// The time between the purchases in milliseconds
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
+    pub purchase_period: i64,
+    /// The quote amount of every purchase, the whole deposit is bought at once if it's `None`
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub purchase_size: Option<f64>,
+    /// The calendar of the purchases like "every Monday 09:00 Europe/Berlin"
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+}
+
+impl HodlSettingsRequest {
+    /// Returns the settings of the hodl bot of the strategy with the `deposit`
+    pub fn hodl_settings(&self, deposit: f64) -> HodlSettings {
+        HodlSettings {
+            purchase_period: self.purchase_period,
+            purchase_size: self.purchase_size.unwrap_or(deposit),
+            schedule: self.schedule.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.backtest.validate()?;
+        if self.backtest.futures.is_some() {
+            return Err("The hodl strategy trades the spot only".into());
+        }
+        if self.purchase_period < 0 {
+            return Err("The purchase period can't be negative".into());
+        }
+        if self.purchase_size.is_some_and(|size| size <= 0.0) {
+            return Err("The purchase size should be positive".into());
+        }
+        match &self.schedule {
+            Some(schedule) => schedule.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the schema of the hodl fields
+    pub fn parameters() -> Vec<ParameterSchema> {
+        vec![
+            ParameterSchema::optional(
+                "purchase_period",
+                ParameterKind::Integer,
+                Some(0.into()),
+                "The time between the purchases in milliseconds",
+            ),
+            ParameterSchema::optional(
+                "purchase_size",
+                ParameterKind::Number,
+                None,
+                "The amount of every purchase, the whole deposit at once if it's empty",
+            ),
+            ParameterSchema::optional(
+                "schedule",
+                ParameterKind::Object,
+                None,
+                "The calendar of the purchases, it replaces the purchase period",
+            ),
+        ]
+    }
 }
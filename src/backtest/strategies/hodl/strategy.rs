@@ -1,25 +1,52 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    backtest::{action::Action, settings::StrategySettings, strategies::strategy_trait::Strategy},
+    backtest::{
+        action::Action,
+        fees::FeeAsset,
+        settings::StrategySettings,
+        strategies::{strategy_trait::Strategy, timeframes::Timeframes},
+    },
+    data_handlers::kline_source::KLineSource,
     data_models::market_data::{
         enums::{OrderType, Side},
+        funding_rate::FundingRate,
         kline::KLine,
         order::Order,
-        position::Position,
+        position::{Position, PositionIds},
     },
 };
 
 use super::bot::HodlBot;
 
-#[derive(Debug, Clone)]
+/// The market purchase of the `size` sent on the kline at the `date` and `kline_position`,
+/// it waits for the latency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingOrder {
+    pub date: i64,
+    pub kline_position: usize,
+    pub size: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HodlStrategy {
     pub strategy_settings: StrategySettings,
     pub bot: HodlBot,
-    pub klines: Vec<KLine>,
+    pub klines: KLineSource,
+    pub timeframes: Timeframes,
+    pub position_ids: PositionIds,
     pub positions_opened: Vec<Position>,
     pub positions_closed: Vec<Position>,
     pub current_budget: f64,
     pub current_qty: f64,
     pub current_kline_position: usize,
+    /// The funding rates are loaded with the klines of every range
+    #[serde(skip)]
+    pub funding_rates: VecDeque<FundingRate>,
+    #[serde(default)]
+    pub pending_orders: VecDeque<PendingOrder>,
 }
 
 impl HodlStrategy {
@@ -27,23 +54,90 @@ impl HodlStrategy {
         Self {
             strategy_settings: strategy_settings.clone(),
             bot,
-            klines: Vec::new(),
+            klines: KLineSource::default(),
+            timeframes: Timeframes::new(
+                &strategy_settings.market_data_type,
+                &strategy_settings.timeframes,
+            ),
+            position_ids: PositionIds::new(strategy_settings.seed),
             positions_opened: Vec::new(),
             positions_closed: Vec::new(),
             current_budget: strategy_settings.deposit,
             current_qty: 0.0,
             current_kline_position: 0,
+            funding_rates: VecDeque::new(),
+            pending_orders: VecDeque::new(),
         }
     }
+
+    /// Buys the `size` of the pending order by the market at the close of the `kline`.
+    /// The quote fee is paid from the size, so the purchase doesn't spend more than it.
+    fn buy(&mut self, pending_order: PendingOrder, kline: &KLine) {
+        let fees = self.strategy_settings.fees();
+        let rate = fees.rate(&OrderType::Market);
+        let mut value = match fees.asset {
+            FeeAsset::Quote => pending_order.size / (1.0 + rate / 100.0),
+            _ => pending_order.size,
+        };
+        let (price, _) = self.strategy_settings.execution.market_price(
+            kline.close,
+            &Side::Buy,
+            value / kline.close,
+            kline,
+        );
+        // The size is in the quote, so the slippage buys less
+        let mut qty = value / price;
+        if let Some(rules) = &self.strategy_settings.rules {
+            qty = rules.round_qty(qty);
+            // The exchange rejects the purchase below the minimums
+            if !rules.accepts(price, qty) {
+                return;
+            }
+            value = qty * price;
+        }
+        let slippage = (price - kline.close) * qty;
+        let mut order = Order::new(
+            pending_order.date,
+            kline.close,
+            Side::Buy,
+            OrderType::Market,
+        )
+        .updated(kline.date)
+        .with_price_executed(price)
+        .with_qty(qty)
+        .with_commission(price, qty, rate)
+        .filled();
+        if slippage > 0.0 {
+            order = order.with_slippage(slippage);
+        }
+        let commission = order.commission.unwrap();
+        let position = Position::new_with_id(
+            self.position_ids.next_id(),
+            self.strategy_settings.symbol.clone(),
+        )
+        .with_order(order);
+        self.positions_opened.push(position);
+        self.update_strategy_data(-value, qty);
+        self.pay_fee(commission, price, &Side::Buy);
+    }
 }
 
 impl Strategy for HodlStrategy {
     fn strategy_settings(&self) -> StrategySettings {
         self.strategy_settings.clone()
     }
-    fn klines(&self) -> &Vec<KLine> {
+    fn klines(&self) -> &KLineSource {
         &self.klines
     }
+    fn klines_mut(&mut self) -> &mut KLineSource {
+        &mut self.klines
+    }
+    fn timeframes(&self) -> &Timeframes {
+        &self.timeframes
+    }
+    fn timeframes_mut(&mut self) -> &mut Timeframes {
+        &mut self.timeframes
+    }
     fn positions_opened(&self) -> &Vec<Position> {
         &self.positions_opened
     }
@@ -65,7 +159,10 @@ impl Strategy for HodlStrategy {
     fn current_kline_position(&self) -> usize {
         self.current_kline_position
     }
-    fn set_klines(&mut self, klines: Vec<KLine>) {
+    fn funding_rates_mut(&mut self) -> &mut VecDeque<FundingRate> {
+        &mut self.funding_rates
+    }
+    fn set_klines(&mut self, klines: KLineSource) {
         self.klines = klines;
     }
     fn set_positions_opened(&mut self, positions_opened: Vec<Position>) {
@@ -83,27 +180,236 @@ impl Strategy for HodlStrategy {
     fn set_current_kline_position(&mut self, current_kline_position: usize) {
         self.current_kline_position = current_kline_position;
     }
+    fn set_funding_rates(&mut self, funding_rates: VecDeque<FundingRate>) {
+        self.funding_rates = funding_rates;
+    }
 
     fn run(&mut self, kline: &KLine) {
-        match self.bot.run(kline.date, self.current_budget) {
-            Some(action) => match action {
-                Action::Buy(size) => {
-                    let mut position = Position::new(self.strategy_settings.symbol.clone());
-                    position.orders.push(
-                        Order::new(kline.date, kline.close, Side::Buy, OrderType::Market)
-                            .with_qty(size / kline.close)
-                            .with_commission(
-                                kline.close,
-                                size / kline.close,
-                                self.strategy_settings.commission,
-                            ),
-                    );
-                    self.positions_opened.push(position.clone());
-                    self.update_strategy_data(-size, position.orders.last().unwrap().qty.unwrap());
-                }
-                _ => (),
-            },
-            None => (),
+        // The cash of the pending orders is reserved
+        let pending: f64 = self.pending_orders.iter().map(|order| order.size).sum();
+        if let Some(Action::Buy(size)) = self.bot.run(kline.date, self.current_budget - pending) {
+            self.pending_orders.push_back(PendingOrder {
+                date: kline.date,
+                kline_position: self.current_kline_position,
+                size,
+            });
         }
+        while let Some(pending_order) = self.pending_orders.front().filter(|order| {
+            self.strategy_settings.execution.is_due(
+                (order.date, order.kline_position),
+                (kline.date, self.current_kline_position),
+            )
+        }) {
+            let pending_order = pending_order.clone();
+            self.pending_orders.pop_front();
+            self.buy(pending_order, kline);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::{
+            execution::{ExecutionSettings, Latency, Slippage},
+            fees::FeeSettings,
+            strategies::hodl::settings::HodlSettings,
+        },
+        data_models::market_data::{
+            enums::{MarketDataType, OrderStatus, PricePath},
+            symbol_rules::SymbolRules,
+        },
+    };
+
+    use super::*;
+
+    fn get_hodl_strategy(purchase_period: i64, purchase_size: f64) -> HodlStrategy {
+        let mut strategy =
+            get_execution_strategy(purchase_period, purchase_size, ExecutionSettings::default());
+        strategy.strategy_settings.commission = 1.0;
+        strategy
+    }
+
+    fn get_execution_strategy(
+        purchase_period: i64,
+        purchase_size: f64,
+        execution: ExecutionSettings,
+    ) -> HodlStrategy {
+        let strategy_settings = StrategySettings {
+            symbol: "BTCUSDT".to_string(),
+            exchange: "binance".to_string(),
+            market_data_type: MarketDataType::KLine1m,
+            date_start: 0,
+            date_end: 4,
+            deposit: 100.0,
+            commission: 0.0,
+            price_path: PricePath::Close,
+            timeframes: Vec::new(),
+            seed: None,
+            futures: None,
+            execution,
+            fees: None,
+            rules: None,
+        };
+        let bot = HodlBot::new(HodlSettings::new(purchase_period, purchase_size));
+        let mut strategy = HodlStrategy::new(strategy_settings, bot);
+        strategy.set_klines(
+            vec![
+                KLine::blank().with_date(0).with_close(50.0),
+                KLine::blank().with_date(1).with_close(40.0),
+                KLine::blank().with_date(2).with_close(25.0),
+                KLine::blank().with_date(3).with_close(50.0),
+                KLine::blank().with_date(4).with_close(100.0),
+            ]
+            .into(),
+        );
+        strategy
+    }
+
+    #[test]
+    fn test_run_dca() {
+        let mut strategy = get_hodl_strategy(2, 25.0);
+        for ts in 0..5 {
+            strategy.run_kline(ts);
+        }
+        // The purchases are at 0, 2 and 4, the 1% fee is paid from every purchase
+        let value = 25.0 / 1.01;
+        assert_eq!(strategy.positions_opened.len(), 3);
+        assert!((strategy.current_budget - 25.0).abs() < 1e-9);
+        assert!((strategy.current_qty - value * (0.02 + 0.04 + 0.01)).abs() < 1e-9);
+        let order = &strategy.positions_opened[1].orders[0];
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.date_update, Some(2));
+        assert_eq!(order.price_executed, Some(25.0));
+        assert_eq!(order.qty, Some(value / 25.0));
+        assert!((order.commission.unwrap() - value / 100.0).abs() < 1e-9);
+        assert_eq!(strategy.positions_opened[1].volume_buy(), value / 25.0);
+    }
+
+    #[test]
+    fn test_hold_and_close() {
+        let mut strategy = get_hodl_strategy(0, 100.0);
+        for ts in 0..5 {
+            strategy.run_kline(ts);
+        }
+        // The whole deposit is bought once
+        let qty = 100.0 / 1.01 / 50.0;
+        assert_eq!(strategy.positions_opened.len(), 1);
+        assert!(strategy.current_budget.abs() < 1e-9);
+        assert_eq!(strategy.current_qty, qty);
+
+        strategy.close_all_positions(&KLine::blank().with_date(4).with_close(100.0));
+        assert!(strategy.positions_opened.is_empty());
+        assert!((strategy.current_budget - qty * 100.0 * 0.99).abs() < 1e-9);
+        assert_eq!(strategy.current_qty, 0.0);
+        let position = &strategy.positions_closed[0];
+        assert_eq!(position.volume_sell(), qty);
+        // The price doubled, the commission is 1% of both the purchase and the sale
+        let pnl = qty * 50.0 - qty * 0.5 - qty * 1.0;
+        assert!((position.pnl.unwrap() - pnl).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fee_asset() {
+        let mut strategy = get_hodl_strategy(0, 100.0);
+        strategy.strategy_settings.fees = Some(FeeSettings {
+            taker: Some(1.0),
+            asset: FeeAsset::Base,
+            ..Default::default()
+        });
+        strategy.run_kline(0);
+        // The fee of 1 is taken from the 2 bought
+        assert_eq!(strategy.current_budget, 0.0);
+        assert_eq!(strategy.current_qty, 2.0 - 0.02);
+        assert_eq!(strategy.positions_opened[0].volume_buy(), 2.0);
+        // Only the held qty is sold and the sell fee is paid in the quote
+        strategy.close_all_positions(&KLine::blank().with_date(1).with_close(100.0));
+        assert_eq!(strategy.current_qty, 0.0);
+        assert_eq!(strategy.positions_closed[0].volume_sell(), 2.0 - 0.02);
+        assert!((strategy.current_budget - 1.98 * 100.0 * 0.99).abs() < 1e-9);
+
+        let mut strategy = get_hodl_strategy(0, 100.0);
+        strategy.strategy_settings.fees = Some(FeeSettings {
+            taker: Some(1.0),
+            bnb_discount: true,
+            asset: FeeAsset::Other,
+            ..Default::default()
+        });
+        strategy.run_kline(0);
+        // The discounted fee is paid in the other asset
+        assert_eq!(strategy.current_budget, 0.0);
+        assert_eq!(strategy.current_qty, 2.0);
+        assert_eq!(
+            strategy.positions_opened[0].orders[0].commission,
+            Some(100.0 * 0.0075)
+        );
+    }
+
+    #[test]
+    fn test_latency() {
+        let execution = ExecutionSettings {
+            slippage: None,
+            latency: Some(Latency::Bars(1)),
+        };
+        let mut strategy = get_execution_strategy(2, 25.0, execution);
+        for ts in 0..5 {
+            strategy.run_kline(ts);
+        }
+        // The purchases sent at 0 and 2 are filled by the next klines, the last one is pending
+        assert_eq!(strategy.positions_opened.len(), 2);
+        assert_eq!(strategy.pending_orders.len(), 1);
+        assert_eq!(strategy.current_budget, 50.0);
+        assert_eq!(strategy.current_qty, 25.0 / 40.0 + 25.0 / 50.0);
+        let order = &strategy.positions_opened[0].orders[0];
+        assert_eq!(order.date, 0);
+        assert_eq!(order.date_update, Some(1));
+        assert_eq!(order.price_executed, Some(40.0));
+    }
+
+    #[test]
+    fn test_slippage() {
+        let execution = ExecutionSettings {
+            slippage: Some(Slippage::Fixed { bps: 100.0 }),
+            latency: None,
+        };
+        let mut strategy = get_execution_strategy(0, 100.0, execution);
+        strategy.run_kline(0);
+        let qty = 100.0 / 50.5;
+        assert_eq!(strategy.current_budget, 0.0);
+        assert_eq!(strategy.current_qty, qty);
+        let order = &strategy.positions_opened[0].orders[0];
+        assert_eq!(order.price, 50.0);
+        assert_eq!(order.price_executed, Some(50.5));
+
+        strategy.close_all_positions(&KLine::blank().with_date(4).with_close(100.0));
+        assert_eq!(strategy.current_budget, qty * 99.0);
+        // Both the purchase and the sale lost 1%
+        let position = &strategy.positions_closed[0];
+        assert!((position.slippage() - qty * 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut strategy = get_execution_strategy(0, 100.0, ExecutionSettings::default());
+        strategy.strategy_settings.rules = Some(SymbolRules {
+            tick_size: 0.01,
+            step_size: 0.3,
+            min_qty: 0.3,
+            max_qty: 0.0,
+            min_notional: 10.0,
+        });
+        strategy.run_kline(0);
+        // The 2 are rounded down to the step, the rest of the cash is left
+        assert_eq!(strategy.current_qty, 1.8);
+        assert_eq!(strategy.current_budget, 10.0);
+
+        let mut strategy = get_execution_strategy(0, 100.0, ExecutionSettings::default());
+        strategy.strategy_settings.rules = Some(SymbolRules {
+            min_notional: 150.0,
+            ..Default::default()
+        });
+        strategy.run_kline(0);
+        assert!(strategy.positions_opened.is_empty());
+        assert_eq!(strategy.current_budget, 100.0);
     }
 }
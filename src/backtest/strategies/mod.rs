@@ -1,4 +1,6 @@
 pub mod grid;
 pub mod hodl;
+pub mod registry;
 pub mod strategy_trait;
 pub mod strategy_utils;
+pub mod timeframes;
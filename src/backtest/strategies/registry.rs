@@ -0,0 +1,385 @@
+use std::path::PathBuf;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::backtest::{progress::Progress, runner::StrategyBacktest, settings::BacktestRequest};
+
+use super::{
+    grid::{runner::run_grid_with_progress, settings::GridSettingsRequest},
+    hodl::{runner::run_hodl_with_progress, settings::HodlSettingsRequest},
+};
+
+/// The type of a request field, the UI renders the input by it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterKind {
+    Text,
+    Number,
+    Integer,
+    Boolean,
+    Date,
+    /// One of the `options`
+    Options,
+    /// A JSON value
+    Object,
+}
+
+/// The description of one field of a strategy request
+#[derive(Debug, Clone, Serialize)]
+pub struct ParameterSchema {
+    pub name: &'static str,
+    pub kind: ParameterKind,
+    /// The field should be in the request, though it can be `null` if it's `nullable`
+    pub required: bool,
+    pub nullable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    pub description: &'static str,
+}
+
+impl ParameterSchema {
+    pub fn required(name: &'static str, kind: ParameterKind, description: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            required: true,
+            nullable: false,
+            default: None,
+            options: Vec::new(),
+            description,
+        }
+    }
+
+    /// The field should be in the request, but it can be `null`
+    pub fn nullable(name: &'static str, kind: ParameterKind, description: &'static str) -> Self {
+        Self {
+            nullable: true,
+            ..Self::required(name, kind, description)
+        }
+    }
+
+    /// The field can be omitted, the `default` is used then
+    pub fn optional(
+        name: &'static str,
+        kind: ParameterKind,
+        default: Option<serde_json::Value>,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            required: false,
+            nullable: default.is_none(),
+            default,
+            ..Self::required(name, kind, description)
+        }
+    }
+
+    pub fn with_options(mut self, options: Vec<String>) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// A strategy which can be run by its name
+pub struct StrategyDefinition {
+    /// The name of the strategy in the routes and the stored results
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Returns the schema of the strategy fields besides the common ones
+    pub parameters: fn() -> Vec<ParameterSchema>,
+    /// Checks the request can be run
+    pub validate: fn(&serde_json::Value) -> Result<(), String>,
+    /// Runs the backtest of the request
+    pub run: fn(&serde_json::Value, PathBuf, Progress) -> Result<StrategyBacktest, String>,
+}
+
+/// The strategy name and its fields for the discovery
+#[derive(Debug, Serialize)]
+pub struct StrategyInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: Vec<ParameterSchema>,
+}
+
+impl StrategyDefinition {
+    pub fn info(&self) -> StrategyInfo {
+        let mut parameters = BacktestRequest::parameters();
+        parameters.extend((self.parameters)());
+        StrategyInfo {
+            name: self.name,
+            description: self.description,
+            parameters,
+        }
+    }
+}
+
+/// Returns all the strategies, a new strategy is added here
+pub fn strategies() -> Vec<StrategyDefinition> {
+    vec![
+        StrategyDefinition {
+            name: "grid",
+            description: "Buys on every grid level below the price and sells one level above",
+            parameters: GridSettingsRequest::parameters,
+            validate: |request| parse::<GridSettingsRequest>(request)?.validate(),
+            run: |request, data_path, progress| {
+                let request = parse::<GridSettingsRequest>(request)?;
+                Ok(run_grid_with_progress(&request, data_path, progress))
+            },
+        },
+        StrategyDefinition {
+            name: "hodl",
+            description:
+                "Buys the whole deposit at once or by parts periodically and holds to the end",
+            parameters: HodlSettingsRequest::parameters,
+            validate: |request| parse::<HodlSettingsRequest>(request)?.validate(),
+            run: |request, data_path, progress| {
+                let request = parse::<HodlSettingsRequest>(request)?;
+                Ok(run_hodl_with_progress(&request, data_path, progress))
+            },
+        },
+    ]
+}
+
+pub fn find_strategy(name: &str) -> Option<StrategyDefinition> {
+    strategies()
+        .into_iter()
+        .find(|strategy| strategy.name == name)
+}
+
+fn parse<R: DeserializeOwned>(request: &serde_json::Value) -> Result<R, String> {
+    serde_json::from_value(request.clone()).map_err(|e| format!("Invalid request: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        path::Path,
+    };
+
+    use crate::{
+        backtest::portfolio::Allocation,
+        data_models::market_data::{enums::MarketDataType, symbol_rules::SymbolRules},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parameters_match_requests() {
+        let request = serde_json::json!({
+            "symbol": "BTCUSDT",
+            "exchange": "binance",
+            "market_data_type": "1m",
+            "chart_market_data_type": "1h",
+            "date_start": "2020-09-13",
+            "date_end": "2020-09-14",
+            "deposit": "1000",
+            "commission": 0.1,
+            "price_low": 20.0,
+            "price_high": 80.0,
+            "grids_count": 10,
+            "grid_trigger": 0.0,
+            "grid_sl": null,
+            "grid_tp": null,
+        });
+        let grid = find_strategy("grid").unwrap();
+        assert!((grid.validate)(&request).is_ok());
+
+        // The schema lists every field of the request
+        let parsed: GridSettingsRequest = parse(&request).unwrap();
+        let fields: BTreeSet<String> = serde_json::to_value(parsed)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let info = grid.info();
+        let names: BTreeSet<String> = info.parameters.iter().map(|p| p.name.into()).collect();
+        assert_eq!(fields, names);
+
+        // The required fields can't be omitted
+        for parameter in info.parameters.iter().filter(|p| p.required) {
+            let mut request = request.clone();
+            request.as_object_mut().unwrap().remove(parameter.name);
+            assert!((grid.validate)(&request).is_err(), "{}", parameter.name);
+        }
+        assert!(find_strategy("unknown").is_none());
+
+        // The stored rules are used instead of the saved ones, the rules without the filters
+        // are stored if there are no saved ones
+        let data_path = Path::new("/nonexistent");
+        let mut request = request.clone();
+        request["symbols"] = serde_json::json!(["ETHUSDT"]);
+        let rules = parsed_rules(&request, data_path).unwrap();
+        assert_eq!(rules["btcusdt"], SymbolRules::default());
+        assert_eq!(rules["ethusdt"], SymbolRules::default());
+        request["rules"] = serde_json::json!({"btcusdt": {
+            "tick_size": 0.01,
+            "step_size": 0.001,
+            "min_qty": 0.001,
+            "max_qty": 100.0,
+            "min_notional": 5.0,
+        }});
+        assert!((grid.validate)(&request).is_ok());
+        let rules = parsed_rules(&request, data_path).unwrap();
+        assert_eq!(rules["btcusdt"].min_notional, 5.0);
+        assert_eq!(rules["ethusdt"], SymbolRules::default());
+    }
+
+    #[test]
+    fn test_symbols_request() {
+        let mut request = serde_json::json!({
+            "symbol": "BTCUSDT",
+            "symbols": ["ETHUSDT", "BNBUSDT"],
+            "exchange": "binance",
+            "market_data_type": "1m",
+            "chart_market_data_type": "1h",
+            "date_start": "2020-09-13",
+            "date_end": "2020-09-14",
+            "deposit": "1000",
+            "commission": 0.1,
+            "allocation": "FirstCome",
+        });
+        let hodl = find_strategy("hodl").unwrap();
+        assert!((hodl.validate)(&request).is_ok());
+        let parsed: HodlSettingsRequest = parse(&request).unwrap();
+        let settings = parsed.backtest.backtest_settings();
+        assert_eq!(settings.symbols, vec!["btcusdt", "ethusdt", "bnbusdt"]);
+        assert_eq!(settings.allocation, Allocation::FirstCome);
+
+        request["allocation"] = serde_json::json!({"Weighted": [1, 2, 1]});
+        assert!((hodl.validate)(&request).is_ok());
+        // Every strategy should get a part of the deposit
+        for weights in [
+            serde_json::json!([1, 2]),
+            serde_json::json!([1, -2, 1]),
+            serde_json::json!([0, 0, 0]),
+        ] {
+            request["allocation"] = serde_json::json!({ "Weighted": weights });
+            assert!((hodl.validate)(&request).is_err());
+        }
+        request["allocation"] = "FixedSplit".into();
+        request["symbols"] = serde_json::json!(["ETHUSDT", "btcusdt"]);
+        assert!((hodl.validate)(&request).is_err());
+    }
+
+    fn parsed_rules(
+        request: &serde_json::Value,
+        data_path: &Path,
+    ) -> Option<BTreeMap<String, SymbolRules>> {
+        let parsed: GridSettingsRequest = parse(request).unwrap();
+        parsed.backtest.symbol_rules(data_path.to_path_buf())
+    }
+
+    #[test]
+    fn test_futures_request() {
+        let mut request = serde_json::json!({
+            "symbol": "BTCUSDT",
+            "exchange": "binance",
+            "market_data_type": "1m",
+            "chart_market_data_type": "1h",
+            "date_start": "2020-09-13",
+            "date_end": "2020-09-14",
+            "deposit": "1000",
+            "commission": 0.1,
+            "price_low": 20.0,
+            "price_high": 80.0,
+            "grids_count": 10,
+            "grid_trigger": 0.0,
+            "grid_sl": null,
+            "grid_tp": null,
+            "mode": "neutral",
+        });
+        let grid = find_strategy("grid").unwrap();
+        // The neutral grid trades the futures
+        assert!((grid.validate)(&request).is_err());
+        request["futures"] = serde_json::json!({"leverage": "10", "margin_mode": "cross"});
+        assert!((grid.validate)(&request).is_ok());
+        request["futures"]["leverage"] = 200.into();
+        assert!((grid.validate)(&request).is_err());
+        request["futures"] = serde_json::json!({"leverage": 10, "maintenance_margin": 10});
+        assert!((grid.validate)(&request).is_err());
+
+        request["futures"] = serde_json::json!({"leverage": 10});
+        assert!((find_strategy("hodl").unwrap().validate)(&request).is_err());
+
+        // The grid orders are on the exchange in advance
+        request["execution"] = serde_json::json!({"slippage": {"model": "volume", "bps": "20"}});
+        assert!((grid.validate)(&request).is_ok());
+        request["execution"]["latency"] = serde_json::json!({"ms": 200});
+        assert!((grid.validate)(&request).is_err());
+        request.as_object_mut().unwrap().remove("execution");
+
+        // The margin is in the quote
+        request["fees"] = serde_json::json!({"vip_tier": 1, "asset": "other"});
+        assert!((grid.validate)(&request).is_ok());
+        request["fees"]["asset"] = "base".into();
+        assert!((grid.validate)(&request).is_err());
+        request.as_object_mut().unwrap().remove("fees");
+
+        // The saved rules are of the spot symbols
+        assert_eq!(parsed_rules(&request, Path::new("/nonexistent")), None);
+        request["rules"] = serde_json::json!({"btcusdt": {
+            "tick_size": 0.01,
+            "step_size": 0.001,
+            "min_qty": 0.001,
+            "max_qty": 100.0,
+            "min_notional": 5.0,
+        }});
+        assert!((grid.validate)(&request).is_err());
+    }
+
+    #[test]
+    fn test_hodl_request() {
+        let mut request = serde_json::json!({
+            "symbol": "BTCUSDT",
+            "exchange": "binance",
+            "market_data_type": "1m",
+            "chart_market_data_type": "1h",
+            "date_start": "2020-09-13",
+            "date_end": "2020-09-14",
+            "deposit": "1000",
+            "commission": 0.1,
+        });
+        let hodl = find_strategy("hodl").unwrap();
+        assert!((hodl.validate)(&request).is_ok());
+        // The whole deposit is bought at once by default
+        let parsed: HodlSettingsRequest = parse(&request).unwrap();
+        let settings = parsed.hodl_settings(1000.0);
+        assert_eq!(settings.purchase_period, 0);
+        assert_eq!(settings.purchase_size, 1000.0);
+
+        request["schedule"] = serde_json::json!({
+            "period": {"weekly": "monday"},
+            "time": "09:00",
+            "timezone": "Europe/Berlin",
+        });
+        assert!((hodl.validate)(&request).is_ok());
+        request["schedule"]["timezone"] = "Europe/Nowhere".into();
+        assert!((hodl.validate)(&request).is_err());
+
+        request["schedule"] = serde_json::Value::Null;
+        request["purchase_size"] = (-1.0).into();
+        assert!((hodl.validate)(&request).is_err());
+
+        request["purchase_size"] = serde_json::Value::Null;
+        request["execution"] = serde_json::json!({"latency": {"bars": 1}});
+        assert!((hodl.validate)(&request).is_ok());
+        request["execution"]["slippage"] = serde_json::json!({"model": "fixed", "bps": -5});
+        assert!((hodl.validate)(&request).is_err());
+        request.as_object_mut().unwrap().remove("execution");
+
+        // The strategies read the higher timeframes only
+        request["timeframes"] = serde_json::json!(["1h", "1d"]);
+        assert!((hodl.validate)(&request).is_ok());
+        let parsed: HodlSettingsRequest = parse(&request).unwrap();
+        assert_eq!(
+            parsed.backtest.backtest_settings().timeframes,
+            vec![MarketDataType::KLine1h, MarketDataType::KLine1d]
+        );
+        request["market_data_type"] = "1h".into();
+        assert!((hodl.validate)(&request).is_err());
+    }
+}
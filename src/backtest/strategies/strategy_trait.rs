@@ -1,16 +1,27 @@
+use std::collections::VecDeque;
+
 use crate::{
-    backtest::settings::StrategySettings,
+    backtest::{
+        fees::FeeAsset,
+        settings::{MarginMode, StrategySettings},
+        strategies::{strategy_utils::cross_liquidation_price, timeframes::Timeframes},
+    },
+    data_handlers::kline_source::KLineSource,
     data_models::market_data::{
-        enums::{OrderType, Side},
+        enums::{OrderType, PricePath, Side},
+        funding_rate::FundingRate,
         kline::KLine,
         order::Order,
-        position::{Position, PositionStatus},
+        position::{Position, PositionSide, PositionStatus},
     },
 };
 
 pub trait Strategy {
     fn strategy_settings(&self) -> StrategySettings;
-    fn klines(&self) -> &Vec<KLine>;
+    fn klines(&self) -> &KLineSource;
+    fn klines_mut(&mut self) -> &mut KLineSource;
+    fn timeframes(&self) -> &Timeframes;
+    fn timeframes_mut(&mut self) -> &mut Timeframes;
     fn positions_opened(&self) -> &Vec<Position>;
     fn positions_opened_mut(&mut self) -> &mut Vec<Position>;
     fn positions_closed(&self) -> &Vec<Position>;
@@ -18,54 +29,233 @@ pub trait Strategy {
     fn current_budget(&self) -> f64;
     fn current_qty(&self) -> f64;
     fn current_kline_position(&self) -> usize;
+    fn funding_rates_mut(&mut self) -> &mut VecDeque<FundingRate>;
 
-    fn set_klines(&mut self, klines: Vec<KLine>);
+    fn set_klines(&mut self, klines: KLineSource);
     fn set_positions_opened(&mut self, positions_opened: Vec<Position>);
     fn set_positions_closed(&mut self, positions_closed: Vec<Position>);
     fn set_current_budget(&mut self, current_budget: f64);
     fn set_current_qty(&mut self, current_qty: f64);
     fn set_current_kline_position(&mut self, current_kline_position: usize);
+    fn set_funding_rates(&mut self, funding_rates: VecDeque<FundingRate>);
 
+    /// Runs all the klines with the `timestamp`.
+    /// There is one kline per timestamp, but there can be several trades in the same millisecond.
+    /// The kline gets into the higher timeframes after the run, so the strategy sees them
+    /// only from the next kline.
     fn run_kline(&mut self, timestamp: i64) {
-        if self.klines().len() <= self.current_kline_position() {
-            return;
+        if let Some(kline) = self.klines().peek().filter(|kline| kline.date == timestamp) {
+            // The funding is paid by the price before the kline
+            let price = self
+                .klines()
+                .last_kline()
+                .map_or(kline.open, |last_kline| last_kline.close);
+            self.apply_funding(timestamp, price);
         }
-        if self.klines()[self.current_kline_position()].date == timestamp {
-            let kline = self.klines()[self.current_kline_position()];
+        while let Some(kline) = self.klines_mut().next_with_date(timestamp) {
             self.run(&kline);
+            self.timeframes_mut().update(&kline);
             self.set_current_kline_position(self.current_kline_position() + 1);
         }
     }
     fn run(&mut self, kline: &KLine);
 
+    /// Pays the funding of the opened positions for the funding times up to the `timestamp`.
+    /// The payment is the funding rate of the position value by the `price`.
+    fn apply_funding(&mut self, timestamp: i64, price: f64) {
+        while let Some(funding_rate) = self
+            .funding_rates_mut()
+            .front()
+            .filter(|funding_rate| funding_rate.date <= timestamp)
+            .copied()
+        {
+            self.funding_rates_mut().pop_front();
+            let mut total = 0.0;
+            for position in self.positions_opened_mut().iter_mut() {
+                let funding = -position.volume_all() * price * funding_rate.rate;
+                position.funding += funding;
+                total += funding;
+            }
+            self.update_strategy_data(total, 0.0);
+        }
+    }
+
     fn update_strategy_data(&mut self, budget: f64, qty: f64) {
         self.set_current_budget(self.current_budget() + budget);
         self.set_current_qty(self.current_qty() + qty);
     }
 
-    fn close_all_positions(&mut self, timestamp: i64, price: f64) {
-        let strategy_comission = self.strategy_settings().commission;
-        for mut position in self.positions_opened_mut().clone() {
-            position.cancel_new_orders(timestamp);
-            position.orders.push(
-                Order::new(timestamp, price, Side::Sell, OrderType::Market)
-                    .updated(timestamp)
-                    .with_price_executed(price)
-                    .with_qty(position.volume_buy())
-                    .with_commission(price, position.volume_buy(), strategy_comission)
-                    .filled(),
-            );
-            position.status = PositionStatus::Closed;
-            position.calculate_pnl();
-            self.update_strategy_data(
-                position.volume_sell() * position.weighted_avg_price_sell(),
-                -position.volume_sell(),
-            );
-            self.positions_closed_mut().push(position.clone());
+    /// Charges the `fee` of an order of the `side` filled at the `price` in the fee asset.
+    /// The base fee is taken from the asset the order receives, so the sells pay it in the quote.
+    fn pay_fee(&mut self, fee: f64, price: f64, side: &Side) {
+        match (self.strategy_settings().fees().asset, side) {
+            (FeeAsset::Base, Side::Buy) => self.update_strategy_data(0.0, -fee / price),
+            (FeeAsset::Base, Side::Sell) | (FeeAsset::Quote, _) => {
+                self.update_strategy_data(-fee, 0.0)
+            }
+            (FeeAsset::Other, _) => (),
+        }
+    }
+
+    /// Returns the quantity the `position` holds, the buy fees taken from the base aren't held
+    fn held_qty(&self, position: &Position) -> f64 {
+        match self.strategy_settings().fees().asset {
+            FeeAsset::Base => position.volume_all() - position.commission_qty_buy(),
+            _ => position.volume_all(),
+        }
+    }
+
+    /// Closes the `position` by the `order_type` at the `price` of the `kline`. The market orders
+    /// get the slippage, the `fee` in percent of the position value is paid from the cash
+    /// on top of the trading fee.
+    fn close_position(
+        &mut self,
+        mut position: Position,
+        kline: &KLine,
+        price: f64,
+        order_type: OrderType,
+        fee: f64,
+    ) {
+        let settings = self.strategy_settings();
+        let qty = self.held_qty(&position);
+        let side = if qty > 0.0 { Side::Sell } else { Side::Buy };
+        let (price_executed, slippage) = match order_type {
+            OrderType::Market => settings
+                .execution
+                .market_price(price, &side, qty.abs(), kline),
+            _ => (price, 0.0),
+        };
+        let rate = settings.fees().rate(&order_type);
+        position.cancel_new_orders(kline.date);
+        let mut order = Order::new(kline.date, price, side.clone(), order_type)
+            .updated(kline.date)
+            .with_price_executed(price_executed)
+            .with_qty(qty.abs())
+            .with_commission(price_executed, qty.abs(), rate + fee)
+            .filled();
+        if slippage > 0.0 {
+            order = order.with_slippage(slippage);
+        }
+        position.orders.push(order);
+        position.status = PositionStatus::Closed;
+        position.calculate_pnl();
+        self.update_strategy_data(
+            qty * price_executed - qty.abs() * price_executed * fee / 100.0,
+            -qty,
+        );
+        self.pay_fee(
+            qty.abs() * price_executed * rate / 100.0,
+            price_executed,
+            &side,
+        );
+        self.positions_closed_mut().push(position);
+    }
+
+    /// Closes the opened positions by the market at the close of the `kline`
+    fn close_all_positions(&mut self, kline: &KLine) {
+        for position in self.positions_opened_mut().clone() {
+            self.close_position(position, kline, kline.close, OrderType::Market, 0.0);
         }
         self.positions_opened_mut().clear();
         if !self.positions_closed().is_empty() {
             dbg!(self.positions_closed().last().unwrap().clone());
         }
     }
+
+    /// Returns the margin of the opened futures positions, it's 0 for the spot
+    fn used_margin(&self) -> f64 {
+        match self.strategy_settings().futures {
+            Some(futures) => {
+                self.positions_opened()
+                    .iter()
+                    .map(|position| position.entry_value())
+                    .sum::<f64>()
+                    / futures.leverage
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Returns true if a position of the `notional` value can be opened at the `price`.
+    /// The spot needs the cash and the futures need the free margin.
+    fn can_open(&self, notional: f64, price: f64) -> bool {
+        match self.strategy_settings().futures {
+            Some(futures) => {
+                self.current_budget() + self.current_qty() * price - self.used_margin()
+                    >= notional / futures.leverage
+            }
+            None => self.current_budget() >= notional,
+        }
+    }
+
+    /// Liquidates the futures positions whose margin falls to the maintenance margin
+    /// inside the price segment of the `kline`. The isolated positions are liquidated one by one,
+    /// the cross ones are liquidated all together.
+    fn check_liquidation(&mut self, kline: &KLine) {
+        let settings = self.strategy_settings();
+        let Some(futures) = settings.futures else {
+            return;
+        };
+        // Only the close is checked, so the price can't gap through the liquidation price
+        let (open, low, high) = match settings.price_path {
+            PricePath::Close => (None, kline.close, kline.close),
+            _ => (Some(kline.open), kline.low, kline.high),
+        };
+        let liquidated: Vec<(usize, f64)> = match futures.margin_mode {
+            MarginMode::Isolated => self
+                .positions_opened()
+                .iter()
+                .enumerate()
+                .filter_map(|(i, position)| {
+                    let price =
+                        position.liquidation_price(futures.leverage, futures.maintenance_margin);
+                    // The position is liquidated at the gap price if the segment opens beyond it
+                    match position.side {
+                        PositionSide::Long if low <= price => {
+                            Some((i, open.map_or(price, |open| price.min(open))))
+                        }
+                        PositionSide::Short if high >= price => {
+                            Some((i, open.map_or(price, |open| price.max(open))))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect(),
+            MarginMode::Cross => {
+                let gross_qty: f64 = self
+                    .positions_opened()
+                    .iter()
+                    .map(|position| position.volume_all().abs())
+                    .sum();
+                match cross_liquidation_price(
+                    self.current_budget(),
+                    self.current_qty(),
+                    gross_qty,
+                    futures.maintenance_margin,
+                    open,
+                    (low, high),
+                ) {
+                    Some(price) => (0..self.positions_opened().len())
+                        .map(|i| (i, price))
+                        .collect(),
+                    None => Vec::new(),
+                }
+            }
+        };
+        let mut positions: Vec<(Position, f64)> = liquidated
+            .into_iter()
+            .rev()
+            .map(|(i, price)| (self.positions_opened_mut().remove(i), price))
+            .collect();
+        positions.reverse();
+        for (position, price) in positions {
+            self.close_position(
+                position,
+                kline,
+                price,
+                OrderType::Liquidation,
+                futures.liquidation_fee,
+            );
+        }
+    }
 }
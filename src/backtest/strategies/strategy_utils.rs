@@ -1,13 +1,19 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, fs, path::PathBuf};
 
-use log::info;
+use log::{info, warn};
 
 use crate::{
-    data_handlers::bin_files::{bin_file_name, get_values_from_file},
+    backtest::{execution::ExecutionSettings, fees::Fees},
+    data_handlers::{
+        bin_files::{bin_file_name, rules_file_name, ValuesSource},
+        kline_source::KLineSource,
+    },
     data_models::market_data::{
-        enums::{MarketDataType, OrderStatus, OrderType, Side},
+        enums::{MarketDataType, OrderStatus, OrderType, PricePath, Side},
+        funding_rate::FundingRate,
         kline::KLine,
         position::{Position, PositionStatus},
+        symbol_rules::SymbolRules,
     },
 };
 
@@ -19,39 +25,140 @@ pub fn get_klines(
     date_start: i64,
     date_end: i64,
 ) -> Vec<KLine> {
+    get_kline_source(
+        data_path,
+        exchange,
+        symbol,
+        market_data_type,
+        date_start,
+        date_end,
+    )
+    .collect()
+}
+
+/// Opens the klines of the period for the lazy reading
+pub fn get_kline_source(
+    data_path: PathBuf,
+    exchange: String,
+    symbol: String,
+    market_data_type: MarketDataType,
+    date_start: i64,
+    date_end: i64,
+) -> KLineSource {
     let file_path = PathBuf::from(data_path.clone()).join(bin_file_name(
         exchange.clone(),
         symbol.clone(),
         market_data_type.clone(),
     ));
     if date_end < date_start {
-        return Vec::new();
+        return KLineSource::default();
     }
     info!("Loading data from file: {:?}", file_path);
-    get_values_from_file::<KLine>(file_path, date_start, date_end, market_data_type).unwrap()
+    match market_data_type {
+        MarketDataType::Trade => {
+            KLineSource::from_trades(ValuesSource::new(file_path, date_start, date_end).unwrap())
+        }
+        _ => KLineSource::from_klines(ValuesSource::new(file_path, date_start, date_end).unwrap()),
+    }
 }
 
-pub fn check_tp_sl(kline: &KLine, positions_opened: &mut Vec<Position>, commission: f64) {
+/// Returns the funding rates of the perpetual futures of the period.
+/// There is no funding if the rates aren't downloaded.
+pub fn get_funding_rates(
+    data_path: PathBuf,
+    exchange: String,
+    symbol: String,
+    date_start: i64,
+    date_end: i64,
+) -> Vec<FundingRate> {
+    let file_path = data_path.join(bin_file_name(exchange, symbol, MarketDataType::FundingRate));
+    if date_end < date_start {
+        return Vec::new();
+    }
+    match ValuesSource::new(file_path.clone(), date_start, date_end) {
+        Ok(source) => source.collect(),
+        Err(e) => {
+            warn!("No funding rates in the file {:?}: {}", file_path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Returns the trading rules of the symbol saved with the symbols of the exchange.
+/// The orders aren't rounded if the symbols aren't fetched.
+pub fn get_symbol_rules(
+    data_path: PathBuf,
+    exchange: String,
+    symbol: String,
+) -> Option<SymbolRules> {
+    let file_path = data_path.join(rules_file_name(exchange));
+    let rules: HashMap<String, SymbolRules> = match fs::read_to_string(&file_path) {
+        Ok(rules) => serde_json::from_str(&rules).ok()?,
+        Err(e) => {
+            warn!("No trading rules in the file {:?}: {}", file_path, e);
+            return None;
+        }
+    };
+    rules.get(&symbol.to_lowercase()).cloned()
+}
+
+/// Fills the take profits and the stops reached by the `kline` segment of the price path.
+/// The stops are executed by the market, so they get the slippage of the `execution`
+/// by the whole `bar` of the segment.
+pub fn check_tp_sl(
+    kline: &KLine,
+    bar: &KLine,
+    positions_opened: &mut Vec<Position>,
+    fees: &Fees,
+    price_path: &PricePath,
+    execution: &ExecutionSettings,
+) {
     for pos in positions_opened.iter_mut() {
         for order in pos.orders.iter_mut() {
-            if order.status == OrderStatus::New {
-                if (order.side == Side::Sell
-                    && order.order_type == OrderType::TakeProfit
-                    && kline.close >= order.price)
-                    || (order.side == Side::Sell
-                        && order.order_type == OrderType::Stop
-                        && kline.close <= order.price)
-                {
-                    let qty = order.qty.unwrap();
-                    let price = order.price;
-                    order
-                        .update(kline.date)
-                        .set_executed_price(price)
-                        .set_qty(qty)
-                        .set_commission(kline.close, qty, commission)
-                        .fill();
+            if order.status != OrderStatus::New {
+                continue;
+            }
+            // The sell orders close the long positions and the buy orders close the short ones
+            let price = match (&order.order_type, &order.side) {
+                (OrderType::TakeProfit, Side::Sell) if kline.close >= order.price => {
+                    // The price could reach the take profit before the entry inside the same kline
+                    if *price_path == PricePath::WorstCase && order.date == kline.date {
+                        continue;
+                    }
+                    order.price
                 }
+                (OrderType::TakeProfit, Side::Buy) if kline.close <= order.price => {
+                    if *price_path == PricePath::WorstCase && order.date == kline.date {
+                        continue;
+                    }
+                    order.price
+                }
+                (OrderType::Stop, Side::Sell) if kline.close <= order.price => match price_path {
+                    PricePath::Close => order.price,
+                    // The stop is executed by the market, so after a gap it's filled at the gap price
+                    _ => order.price.min(kline.open),
+                },
+                (OrderType::Stop, Side::Buy) if kline.close >= order.price => match price_path {
+                    PricePath::Close => order.price,
+                    _ => order.price.max(kline.open),
+                },
+                _ => continue,
+            };
+            let qty = order.qty.unwrap();
+            let (price, slippage) = match order.order_type {
+                OrderType::Stop => execution.market_price(price, &order.side, qty, bar),
+                _ => (price, 0.0),
+            };
+            if slippage > 0.0 {
+                order.set_slippage(slippage);
             }
+            let rate = fees.rate(&order.order_type);
+            order
+                .update(kline.date)
+                .set_executed_price(price)
+                .set_qty(qty)
+                .set_commission(price, qty, rate)
+                .fill();
         }
     }
 }
@@ -62,7 +169,12 @@ pub fn remove_closed_positions(positions_opened: &mut Vec<Position>) -> Vec<Posi
         // It's a replacement for drain_filter, which is still a nightly-only experimental API
         let mut i = 0;
         while i < positions_opened.len() {
-            if positions_opened[i].volume_all() == 0.0 {
+            // The take profit sells less than bought if the buy fee was taken from the base
+            let closing_filled = positions_opened[i].orders.iter().any(|order| {
+                order.status == OrderStatus::Filled
+                    && matches!(order.order_type, OrderType::TakeProfit | OrderType::Stop)
+            });
+            if positions_opened[i].volume_all() == 0.0 || closing_filled {
                 result.push(positions_opened.remove(i));
                 result.last_mut().unwrap().status = PositionStatus::Closed;
             } else {
@@ -73,9 +185,41 @@ pub fn remove_closed_positions(positions_opened: &mut Vec<Position>) -> Vec<Posi
     result
 }
 
+/// Returns the price the cross margin account is liquidated at inside the price segment.
+/// The account is liquidated when its equity `budget + qty * price` falls to the maintenance
+/// margin of the `gross_qty`, the `maintenance_margin` is in percent of the position value.
+/// The `open` of the segment is unknown if only the close is checked.
+pub fn cross_liquidation_price(
+    budget: f64,
+    qty: f64,
+    gross_qty: f64,
+    maintenance_margin: f64,
+    open: Option<f64>,
+    (low, high): (f64, f64),
+) -> Option<f64> {
+    if gross_qty == 0.0 {
+        return None;
+    }
+    // The equity minus the maintenance margin is linear by the price
+    let slope = qty - maintenance_margin / 100.0 * gross_qty;
+    let worst = if slope > 0.0 { low } else { high };
+    if budget + slope * worst > 0.0 {
+        return None;
+    }
+    match open {
+        // The segment opened below the maintenance margin, so it's filled at the gap price
+        Some(open) if budget + slope * open <= 0.0 => Some(open),
+        _ if slope == 0.0 => Some(worst),
+        _ => Some(-budget / slope),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::data_models::market_data::order::Order;
+    use crate::{
+        backtest::execution::Slippage,
+        data_models::market_data::{order::Order, position::PositionSide},
+    };
 
     use super::*;
 
@@ -124,10 +268,14 @@ mod tests {
     #[test]
     fn test_check_tp_sl() {
         let mut positions_opened = get_positions_opened();
+        let kline = KLine::blank().with_close(300.0);
         check_tp_sl(
-            &KLine::blank().with_close(300.0),
+            &kline,
+            &kline,
             &mut positions_opened,
-            0.0,
+            &Fees::flat(0.0),
+            &PricePath::Close,
+            &ExecutionSettings::default(),
         );
         dbg!(positions_opened[0].orders.clone());
         assert_eq!(positions_opened.len(), 3);
@@ -139,13 +287,103 @@ mod tests {
         assert_eq!(positions_opened[1].volume_all(), 1.0);
         assert_eq!(positions_opened[1].orders[0].status, OrderStatus::Filled);
         assert_eq!(positions_opened[1].orders[1].status, OrderStatus::New);
-        check_tp_sl(&KLine::blank().with_close(10.0), &mut positions_opened, 0.0);
+        let kline = KLine::blank().with_close(10.0);
+        check_tp_sl(
+            &kline,
+            &kline,
+            &mut positions_opened,
+            &Fees::flat(0.0),
+            &PricePath::Close,
+            &ExecutionSettings::default(),
+        );
         assert_eq!(positions_opened[1].orders.len(), 2);
         assert_eq!(positions_opened[1].volume_all(), 0.0);
         assert_eq!(positions_opened[1].orders[0].status, OrderStatus::Filled);
         assert_eq!(positions_opened[1].orders[1].status, OrderStatus::Filled);
     }
 
+    #[test]
+    fn test_check_tp_sl_price_path() {
+        let mut positions_opened = get_positions_opened();
+        // The segment of the kline from 60 down to 40 gapped through the stop at 50
+        let segment = KLine::blank()
+            .with_date(10)
+            .with_open(40.0)
+            .with_high(40.0)
+            .with_low(30.0)
+            .with_close(30.0);
+        check_tp_sl(
+            &segment,
+            &segment,
+            &mut positions_opened,
+            &Fees::flat(1.0),
+            &PricePath::OpenHighLowClose,
+            &ExecutionSettings::default(),
+        );
+        assert_eq!(positions_opened[1].orders[1].status, OrderStatus::Filled);
+        assert_eq!(positions_opened[1].orders[1].price_executed, Some(40.0));
+        assert_eq!(positions_opened[1].orders[1].commission, Some(0.4));
+        assert_eq!(positions_opened[1].orders[1].date_update, Some(10));
+    }
+
+    #[test]
+    fn test_check_tp_sl_slippage() {
+        let mut positions_opened = get_positions_opened();
+        let segment = KLine::blank()
+            .with_date(10)
+            .with_open(40.0)
+            .with_high(40.0)
+            .with_low(30.0)
+            .with_close(30.0);
+        let execution = ExecutionSettings {
+            slippage: Some(Slippage::Fixed { bps: 250.0 }),
+            latency: None,
+        };
+        check_tp_sl(
+            &segment,
+            &segment,
+            &mut positions_opened,
+            &Fees::flat(0.0),
+            &PricePath::OpenHighLowClose,
+            &execution,
+        );
+        // The stop is filled below the gap price
+        assert_eq!(positions_opened[1].orders[1].price_executed, Some(39.0));
+        assert_eq!(positions_opened[1].orders[1].slippage, Some(1.0));
+        assert_eq!(positions_opened[1].slippage(), 1.0);
+    }
+
+    #[test]
+    fn test_check_tp_sl_worst_case() {
+        let mut positions_opened = get_positions_opened();
+        let segment = KLine::blank()
+            .with_date(2)
+            .with_open(190.0)
+            .with_high(210.0)
+            .with_low(190.0)
+            .with_close(210.0);
+        // The take profit was placed in the same kline
+        check_tp_sl(
+            &segment,
+            &segment,
+            &mut positions_opened,
+            &Fees::flat(0.0),
+            &PricePath::WorstCase,
+            &ExecutionSettings::default(),
+        );
+        assert_eq!(positions_opened[0].orders[1].status, OrderStatus::New);
+        check_tp_sl(
+            &segment.with_date(3),
+            &segment.with_date(3),
+            &mut positions_opened,
+            &Fees::flat(0.0),
+            &PricePath::WorstCase,
+            &ExecutionSettings::default(),
+        );
+        assert_eq!(positions_opened[0].orders[1].status, OrderStatus::Filled);
+        assert_eq!(positions_opened[0].orders[1].price_executed, Some(200.0));
+    }
+
     #[test]
     fn test_remove_closed_positions() {
         let mut positions_opened = get_positions_opened();
@@ -157,4 +395,81 @@ mod tests {
         assert_eq!(positions_closed[0].orders[0].status, OrderStatus::Filled);
         assert_eq!(positions_closed[0].orders[1].status, OrderStatus::Filled);
     }
+
+    #[test]
+    fn test_check_tp_sl_short() {
+        let mut positions_opened = vec![Position::new("BTCUSDT".to_string())
+            .with_side(PositionSide::Short)
+            .with_order(
+                Order::new(1, 100.0, Side::Sell, OrderType::Limit)
+                    .updated(1)
+                    .with_price_executed(100.0)
+                    .with_qty(1.0)
+                    .with_commission(100.0, 1.0, 0.0)
+                    .filled(),
+            )
+            .with_order(Order::new(1, 90.0, Side::Buy, OrderType::TakeProfit).with_qty(1.0))
+            .with_order(Order::new(1, 120.0, Side::Buy, OrderType::Stop).with_qty(1.0))];
+        let kline = KLine::blank().with_close(95.0);
+        check_tp_sl(
+            &kline,
+            &kline,
+            &mut positions_opened,
+            &Fees::flat(0.0),
+            &PricePath::Close,
+            &ExecutionSettings::default(),
+        );
+        assert_eq!(positions_opened[0].volume_all(), -1.0);
+        // The segment gapped up through the stop
+        let segment = KLine::blank()
+            .with_date(2)
+            .with_open(130.0)
+            .with_high(140.0)
+            .with_low(130.0)
+            .with_close(140.0);
+        check_tp_sl(
+            &segment,
+            &segment,
+            &mut positions_opened,
+            &Fees::flat(0.0),
+            &PricePath::OpenLowHighClose,
+            &ExecutionSettings::default(),
+        );
+        assert_eq!(positions_opened[0].volume_all(), 0.0);
+        assert_eq!(positions_opened[0].orders[1].status, OrderStatus::New);
+        assert_eq!(positions_opened[0].orders[2].price_executed, Some(130.0));
+        positions_opened[0].calculate_pnl();
+        assert_eq!(positions_opened[0].pnl, Some(-30.0));
+    }
+
+    #[test]
+    fn test_cross_liquidation_price() {
+        // 1 coin bought at 100 with the margin of 10
+        assert_eq!(
+            cross_liquidation_price(-90.0, 1.0, 1.0, 0.5, Some(100.0), (80.0, 100.0)),
+            Some(90.0 / 0.995)
+        );
+        assert_eq!(
+            cross_liquidation_price(-90.0, 1.0, 1.0, 0.5, Some(100.0), (95.0, 100.0)),
+            None
+        );
+        // The segment opened below the liquidation price
+        assert_eq!(
+            cross_liquidation_price(-90.0, 1.0, 1.0, 0.5, Some(85.0), (80.0, 85.0)),
+            Some(85.0)
+        );
+        assert_eq!(
+            cross_liquidation_price(-90.0, 1.0, 1.0, 0.5, None, (85.0, 85.0)),
+            Some(90.0 / 0.995)
+        );
+        // 1 coin sold at 100 with the margin of 10
+        assert_eq!(
+            cross_liquidation_price(110.0, -1.0, 1.0, 0.5, None, (120.0, 120.0)),
+            Some(110.0 / 1.005)
+        );
+        assert_eq!(
+            cross_liquidation_price(10.0, 0.0, 0.0, 0.5, None, (0.0, 1000.0)),
+            None
+        );
+    }
 }
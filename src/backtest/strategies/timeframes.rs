@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::data_models::market_data::{enums::MarketDataType, kline::KLine};
+
+/// The closed bars kept for every timeframe. It's the longest lookback of the strategies,
+/// so the memory and the checkpoints don't grow with the backtest period.
+const LOOKBACK: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Timeframe {
+    market_data_type: MarketDataType,
+    interval: i64,
+    forming: Option<KLine>,
+    closed: VecDeque<KLine>,
+}
+
+impl Timeframe {
+    fn close(&mut self, bar: KLine) {
+        if self.closed.len() == LOOKBACK {
+            self.closed.pop_front();
+        }
+        self.closed.push_back(bar);
+    }
+
+    fn update(&mut self, kline: &KLine, base_interval: i64) {
+        let date = kline.date - kline.date.rem_euclid(self.interval);
+        // A kline of the next bar closes the current one if it wasn't closed by the time
+        if let Some(forming) = self.forming {
+            if forming.date != date {
+                self.close(forming);
+                self.forming = None;
+            }
+        }
+        let bar = match self.forming {
+            Some(bar) => KLine {
+                date,
+                open: bar.open,
+                high: bar.high.max(kline.high),
+                low: bar.low.min(kline.low),
+                close: kline.close,
+                volume: bar.volume + kline.volume,
+            },
+            None => kline.with_date(date),
+        };
+        if kline.date + base_interval >= date + self.interval {
+            self.close(bar);
+            self.forming = None;
+        } else {
+            self.forming = Some(bar);
+        }
+    }
+}
+
+/// The bars of the higher timeframes aggregated on the fly from the klines the strategy runs on.
+/// A bar is visible only after the last kline of it has been run, so there is no look-ahead.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Timeframes {
+    base_interval: i64,
+    timeframes: Vec<Timeframe>,
+}
+
+impl Timeframes {
+    pub fn new(base: &MarketDataType, market_data_types: &Vec<MarketDataType>) -> Self {
+        let base_interval = base.value().1;
+        let timeframes = market_data_types
+            .iter()
+            .filter(|mdt| {
+                let valid = is_built_from(mdt, base);
+                if !valid {
+                    warn!("Timeframe {:?} can't be built from {:?}", mdt, base);
+                }
+                valid
+            })
+            .map(|mdt| Timeframe {
+                market_data_type: mdt.clone(),
+                interval: mdt.value().1,
+                forming: None,
+                closed: VecDeque::new(),
+            })
+            .collect();
+        Self {
+            base_interval,
+            timeframes,
+        }
+    }
+
+    /// Checks all the timeframes can be built from the `base` klines
+    pub fn validate(
+        base: &MarketDataType,
+        market_data_types: &[MarketDataType],
+    ) -> Result<(), String> {
+        match market_data_types
+            .iter()
+            .find(|mdt| !is_built_from(mdt, base))
+        {
+            Some(mdt) => Err(format!(
+                "The timeframe {} should be a multiple of {} higher than it",
+                mdt.value().0,
+                base.value().0
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the kline to the bars of all the timeframes
+    pub fn update(&mut self, kline: &KLine) {
+        for timeframe in self.timeframes.iter_mut() {
+            timeframe.update(kline, self.base_interval);
+        }
+    }
+
+    /// Returns the last closed bars of the timeframe up to the lookback, the oldest first
+    pub fn bars(&self, market_data_type: &MarketDataType) -> &VecDeque<KLine> {
+        static NO_BARS: VecDeque<KLine> = VecDeque::new();
+        self.timeframes
+            .iter()
+            .find(|timeframe| timeframe.market_data_type == *market_data_type)
+            .map_or(&NO_BARS, |timeframe| &timeframe.closed)
+    }
+
+    /// Returns the last closed bar of the timeframe
+    pub fn last(&self, market_data_type: &MarketDataType) -> Option<&KLine> {
+        self.bars(market_data_type).back()
+    }
+}
+
+/// Returns true if the bars of the `market_data_type` are whole numbers of the `base` klines
+fn is_built_from(market_data_type: &MarketDataType, base: &MarketDataType) -> bool {
+    let (interval, base_interval) = (market_data_type.value().1, base.value().1);
+    interval > base_interval && (base_interval == 0 || interval % base_interval == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60 * 1000;
+
+    fn kline(minute: i64, close: f64) -> KLine {
+        KLine::blank()
+            .with_date(minute * MINUTE)
+            .with_open(close - 1.0)
+            .with_high(close + 2.0)
+            .with_low(close - 2.0)
+            .with_close(close)
+            .with_volume(1.0)
+    }
+
+    #[test]
+    fn test_validate() {
+        let base = MarketDataType::KLine5m;
+        assert!(Timeframes::validate(&base, &[MarketDataType::KLine1h]).is_ok());
+        for mdt in [
+            MarketDataType::KLine5m,
+            MarketDataType::KLine1m,
+            MarketDataType::FundingRate,
+        ] {
+            assert!(Timeframes::validate(&base, &[MarketDataType::KLine1h, mdt]).is_err());
+        }
+        assert!(Timeframes::validate(&MarketDataType::Trade, &[MarketDataType::KLine1s]).is_ok());
+    }
+
+    #[test]
+    fn test_bar_is_visible_after_close() {
+        let mut timeframes = Timeframes::new(
+            &MarketDataType::KLine1m,
+            &vec![MarketDataType::KLine5m, MarketDataType::KLine15m],
+        );
+        for minute in 0..4 {
+            timeframes.update(&kline(minute, 10.0 + minute as f64));
+        }
+        assert!(timeframes.bars(&MarketDataType::KLine5m).is_empty());
+
+        timeframes.update(&kline(4, 20.0));
+        assert_eq!(
+            timeframes.last(&MarketDataType::KLine5m),
+            Some(&KLine {
+                date: 0,
+                open: 9.0,
+                high: 22.0,
+                low: 8.0,
+                close: 20.0,
+                volume: 5.0,
+            })
+        );
+        assert!(timeframes.bars(&MarketDataType::KLine15m).is_empty());
+
+        for minute in 5..15 {
+            timeframes.update(&kline(minute, 10.0));
+        }
+        assert_eq!(timeframes.bars(&MarketDataType::KLine5m).len(), 3);
+        assert_eq!(timeframes.bars(&MarketDataType::KLine15m).len(), 1);
+        assert_eq!(
+            timeframes.last(&MarketDataType::KLine15m).unwrap().high,
+            22.0
+        );
+        assert!(timeframes.bars(&MarketDataType::KLine1h).is_empty());
+    }
+
+    #[test]
+    fn test_bar_is_closed_by_the_next_one() {
+        // The trades don't have an interval, so the bar is closed by the first trade of the next one
+        let mut timeframes =
+            Timeframes::new(&MarketDataType::Trade, &vec![MarketDataType::KLine1m]);
+        timeframes.update(&KLine::blank().with_date(1000).with_close(1.0));
+        timeframes.update(&KLine::blank().with_date(59_999).with_close(2.0));
+        assert!(timeframes.bars(&MarketDataType::KLine1m).is_empty());
+        timeframes.update(&KLine::blank().with_date(60_000).with_close(3.0));
+        assert_eq!(timeframes.bars(&MarketDataType::KLine1m).len(), 1);
+        assert_eq!(
+            timeframes.last(&MarketDataType::KLine1m).unwrap().close,
+            2.0
+        );
+    }
+
+    #[test]
+    fn test_bars_are_bounded() {
+        let mut timeframes =
+            Timeframes::new(&MarketDataType::KLine1m, &vec![MarketDataType::KLine5m]);
+        let bars = LOOKBACK as i64 + 2;
+        for minute in 0..bars * 5 {
+            timeframes.update(&kline(minute, minute as f64));
+        }
+        // The oldest bars are dropped
+        let closed = timeframes.bars(&MarketDataType::KLine5m);
+        assert_eq!(closed.len(), LOOKBACK);
+        assert_eq!(closed.front().unwrap().date, 2 * 5 * MINUTE);
+        assert_eq!(closed.back().unwrap().date, (bars - 1) * 5 * MINUTE);
+    }
+
+    #[test]
+    fn test_invalid_timeframes() {
+        let timeframes = Timeframes::new(
+            &MarketDataType::KLine1h,
+            &vec![MarketDataType::KLine1m, MarketDataType::Trade],
+        );
+        assert!(timeframes.timeframes.is_empty());
+    }
+}
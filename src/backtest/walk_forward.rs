@@ -0,0 +1,315 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_handlers::utils::i64_to_datetime_str,
+    data_models::market_data::{equity::EquityPoint, metrics::Metrics, position::Position},
+};
+
+use super::{
+    backtest::{generate_time_range, get_metrics},
+    optimizer::{optimize, GridOptimizationRequest, GridParameters, MAX_COMBINATIONS},
+    strategies::grid::runner::run_grid,
+};
+
+const DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// The walk-forward optimization of the grid settings. The parameters are optimized on every
+/// in-sample window and tested on the out-of-sample window right after it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardRequest {
+    #[serde(flatten)]
+    pub optimization: GridOptimizationRequest,
+    pub in_sample_days: i64,
+    pub out_of_sample_days: i64,
+}
+
+impl WalkForwardRequest {
+    /// Checks the optimization can be run and all the windows together
+    /// don't run more backtests than one optimization can
+    pub fn validate(&self) -> Result<(), String> {
+        self.optimization.validate()?;
+        let total = self.windows()?.len() * self.optimization.combinations()?.len();
+        if total > MAX_COMBINATIONS {
+            return Err(format!(
+                "{} backtests requested in all the windows, the limit is {}",
+                total, MAX_COMBINATIONS
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the (in-sample, out-of-sample) windows. The out-of-sample windows follow
+    /// each other, so together they cover the period after the first in-sample window.
+    pub fn windows(&self) -> Result<Vec<((i64, i64), (i64, i64))>, String> {
+        if self.in_sample_days < 1 || self.out_of_sample_days < 1 {
+            return Err("The windows should be at least one day long".into());
+        }
+        let backtest_settings = self.optimization.settings.backtest.backtest_settings();
+        let in_sample = self.in_sample_days * DAY;
+        let windows: Vec<((i64, i64), (i64, i64))> = generate_time_range(
+            backtest_settings.date_start + in_sample,
+            backtest_settings.date_end,
+            self.out_of_sample_days * DAY,
+        )
+        .into_iter()
+        .map(|out_of_sample| {
+            (
+                (out_of_sample.0 - in_sample, out_of_sample.0),
+                out_of_sample,
+            )
+        })
+        .collect();
+        if windows.is_empty() {
+            return Err("The period is shorter than the in-sample window".into());
+        }
+        Ok(windows)
+    }
+
+    /// Returns the optimization request of the period
+    fn optimization_for(&self, period: (i64, i64)) -> GridOptimizationRequest {
+        let mut request = self.optimization.clone();
+        request.settings.backtest.date_start = i64_to_datetime_str(period.0);
+        request.settings.backtest.date_end = i64_to_datetime_str(period.1);
+        request
+    }
+}
+
+/// The best in-sample combination of one window and its out-of-sample result
+#[derive(Debug, Serialize)]
+pub struct WalkForwardWindow {
+    pub in_sample: (i64, i64),
+    pub out_of_sample: (i64, i64),
+    pub parameters: GridParameters,
+    pub in_sample_metrics: Metrics,
+    pub out_of_sample_metrics: Metrics,
+    pub efficiency: f64,
+}
+
+/// The out-of-sample results of all the windows stitched together
+#[derive(Debug, Serialize)]
+pub struct WalkForwardReport {
+    pub windows: Vec<WalkForwardWindow>,
+    pub positions: Vec<Position>,
+    pub equity: Vec<EquityPoint>,
+    pub metrics: Metrics,
+    /// The out-of-sample profit rate relative to the in-sample one
+    pub efficiency: f64,
+}
+
+/// Runs the walk-forward optimization. Every out-of-sample window starts with the deposit
+/// the previous one finished with, so the stitched equity curve is continuous.
+pub fn walk_forward(
+    request: &WalkForwardRequest,
+    data_path: PathBuf,
+) -> Result<WalkForwardReport, String> {
+    request.validate()?;
+    let windows = request.windows()?;
+    let deposit = request.optimization.settings.backtest.deposit;
+    let mut cash = deposit;
+    let mut report_windows = Vec::new();
+    let mut positions = Vec::new();
+    let mut equity = Vec::new();
+    for (in_sample, out_of_sample) in windows {
+        let mut optimization = request.optimization_for(in_sample);
+        optimization.settings.backtest.deposit = cash;
+        let best = optimize(&optimization, data_path.clone())?
+            .into_iter()
+            .next()
+            .ok_or("No valid combination of the parameters")?;
+
+        let mut settings = request.optimization_for(out_of_sample).settings;
+        settings.backtest.deposit = cash;
+        let result = run_grid(&best.parameters.apply(&settings), data_path.clone());
+        cash = result.portfolio.cash;
+        positions.extend(result.positions);
+        equity.extend(result.portfolio.equity.points);
+
+        report_windows.push(WalkForwardWindow {
+            in_sample,
+            out_of_sample,
+            parameters: best.parameters,
+            efficiency: efficiency(
+                &[(&result.metrics, out_of_sample)],
+                &[(&best.metrics, in_sample)],
+            ),
+            in_sample_metrics: best.metrics,
+            out_of_sample_metrics: result.metrics,
+        });
+    }
+    let metrics = get_metrics(
+        &positions,
+        deposit,
+        cash,
+        &equity,
+        &request.optimization.settings.backtest.annualization,
+    );
+    let efficiency = efficiency(
+        &report_windows
+            .iter()
+            .map(|w| (&w.out_of_sample_metrics, w.out_of_sample))
+            .collect::<Vec<_>>(),
+        &report_windows
+            .iter()
+            .map(|w| (&w.in_sample_metrics, w.in_sample))
+            .collect::<Vec<_>>(),
+    );
+    Ok(WalkForwardReport {
+        windows: report_windows,
+        positions,
+        equity,
+        metrics,
+        efficiency,
+    })
+}
+
+/// Returns the ratio of the daily profit percent of the out-of-sample windows
+/// to the one of the in-sample windows, 0 if there was no in-sample profit
+fn efficiency(
+    out_of_sample: &[(&Metrics, (i64, i64))],
+    in_sample: &[(&Metrics, (i64, i64))],
+) -> f64 {
+    let daily_profit = |windows: &[(&Metrics, (i64, i64))]| {
+        let days = windows
+            .iter()
+            .map(|(_, period)| (period.1 - period.0) as f64 / DAY as f64)
+            .sum::<f64>();
+        let profit = windows
+            .iter()
+            .map(|(metrics, _)| metrics.total_profit_percent)
+            .sum::<f64>();
+        profit / days
+    };
+    let in_sample = daily_profit(in_sample);
+    if in_sample <= 0.0 {
+        return 0.0;
+    }
+    daily_profit(out_of_sample) / in_sample
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all};
+
+    use crate::{
+        backtest::{optimizer::ParameterRange, strategies::grid::settings::GridMode},
+        data_handlers::{
+            bin_files::{bin_file_name, create_and_write_to_file},
+            utils::datetime_str_to_i64,
+        },
+        data_models::market_data::{enums::MarketDataType, kline::KLine},
+    };
+
+    use super::*;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+
+    fn walk_forward_request(date_end: &str) -> WalkForwardRequest {
+        serde_json::from_value(serde_json::json!({
+            "settings": {
+                "symbol": "BTCUSDT",
+                "exchange": "binance",
+                "market_data_type": "1h",
+                "chart_market_data_type": "1h",
+                "date_start": "2020-09-01",
+                "date_end": date_end,
+                "deposit": 1000.0,
+                "commission": 0.0,
+                "price_low": 20.0,
+                "price_high": 80.0,
+                "grids_count": 10,
+                "grid_trigger": 0.0,
+                "grid_sl": null,
+                "grid_tp": null,
+                "sell_all": true,
+            },
+            "grids_count": [2, 10],
+            "price_low": [10, 30],
+            "rank_by": "total_profit",
+            "in_sample_days": 3,
+            "out_of_sample_days": 2,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_windows() {
+        let request = walk_forward_request("2020-09-09");
+        let windows = request.windows().unwrap();
+        let date_start = datetime_str_to_i64("2020-09-01".into());
+        assert_eq!(windows.len(), 3);
+        assert_eq!(
+            windows[0],
+            (
+                (date_start, date_start + 3 * DAY),
+                (date_start + 3 * DAY, date_start + 5 * DAY)
+            )
+        );
+        // The last out-of-sample window is cut by the end of the period
+        assert_eq!(windows[2].1, (date_start + 7 * DAY, date_start + 8 * DAY));
+
+        assert!(walk_forward_request("2020-09-03").windows().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut request = walk_forward_request("2020-09-09");
+        assert!(request.validate().is_ok());
+        // The limit is of all the windows, not of one optimization
+        request.optimization.grids_count = Some(ParameterRange::Range {
+            start: 1.0,
+            stop: 100.0,
+            step: 1.0,
+        });
+        assert!(request.optimization.validate().is_ok());
+        assert!(request.validate().is_err());
+
+        let mut request = walk_forward_request("2020-09-09");
+        request.optimization.settings.mode = GridMode::Neutral;
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_walk_forward() {
+        let data_path = PathBuf::from("test_walk_forward");
+        create_dir_all(&data_path).unwrap();
+        let date_start = datetime_str_to_i64("2020-09-01".into());
+        let klines: Vec<KLine> = (0..10 * 24)
+            .map(|i| {
+                let close = 50.0 + 30.0 * ((i as f64) / 6.0).sin() - i as f64 / 10.0;
+                KLine {
+                    date: date_start + i * HOUR,
+                    open: close,
+                    high: close + 1.0,
+                    low: close - 1.0,
+                    close,
+                    volume: 1.0,
+                }
+            })
+            .collect();
+        let file_path = data_path.join(bin_file_name(
+            "binance".into(),
+            "btcusdt".into(),
+            MarketDataType::KLine1h,
+        ));
+        create_and_write_to_file(&klines, file_path).unwrap();
+
+        let request = walk_forward_request("2020-09-10");
+        let report = walk_forward(&request, data_path.clone()).unwrap();
+        assert_eq!(report.windows.len(), 3);
+        // The equity curve covers only the out-of-sample windows
+        assert!(report.equity.first().unwrap().date >= date_start + 3 * DAY);
+        assert!(report.equity.windows(2).all(|w| w[0].date < w[1].date));
+        // The windows compound, so the stitched profit is the sum of the window profits
+        let profit = report
+            .windows
+            .iter()
+            .map(|w| w.out_of_sample_metrics.total_profit)
+            .sum::<f64>();
+        assert!((report.metrics.total_profit - profit).abs() < 1e-6);
+        assert_eq!(report.metrics.start_deposit, 1000.0);
+
+        remove_dir_all(data_path).unwrap();
+    }
+}
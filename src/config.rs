@@ -14,6 +14,14 @@ pub struct AppSettings {
     pub database_url: String,
     pub database_drop: bool,
     pub database_migration_version: Option<String>,
+
+    /// The number of the backtest jobs run at once
+    #[serde(default = "default_jobs_workers")]
+    pub jobs_workers: usize,
+}
+
+fn default_jobs_workers() -> usize {
+    2
 }
 
 impl AppSettings {
@@ -7,7 +7,9 @@ use std::fs::File;
 use std::fs::OpenOptions;
 use std::io;
 use std::io::Write;
+use std::marker::PhantomData;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use crate::data_models::be_bytes::ToFromBytes;
 use crate::data_models::market_data::enums::MarketDataType;
@@ -53,37 +55,90 @@ pub fn get_values_from_file<T: ToFromBytes + KLineTrait>(
     file_path: PathBuf,
     date_start: i64,
     date_end: i64,
-    mdt: MarketDataType,
 ) -> io::Result<Vec<T>> {
-    let first_value = get_first_value_from_file::<T>(file_path.clone())?;
-    let last_value = get_last_value_from_file::<T>(file_path.clone())?;
+    Ok(ValuesSource::new(file_path, date_start, date_end)?.collect())
+}
 
-    // If the requested date range is outside the range of the file, return an empty vector
-    if date_start > last_value.date() || date_end < first_value.date() {
-        return Ok(Vec::new());
-    }
+/// Reads the values of the date range from the file one by one, when they are needed.
+/// The file is memory mapped, so the used memory doesn't depend on the length of the range.
+#[derive(Debug, Clone)]
+pub struct ValuesSource<T> {
+    mmap: Option<Arc<Mmap>>,
+    position: usize,
+    end: usize,
+    value_type: PhantomData<T>,
+}
 
-    let mut offset = 0 as usize;
-    if date_start > first_value.date() {
-        offset = ((date_start - first_value.date()) / mdt.value().1) as usize;
-    };
+impl<T: ToFromBytes + KLineTrait> ValuesSource<T> {
+    pub fn new(file_path: PathBuf, date_start: i64, date_end: i64) -> io::Result<Self> {
+        let first_value = get_first_value_from_file::<T>(file_path.clone())?;
+        let last_value = get_last_value_from_file::<T>(file_path.clone())?;
 
-    let mmap = memmap_for_file(file_path)?;
+        // If the requested date range is outside the range of the file, the source is empty
+        if date_start > last_value.date() || date_end < first_value.date() {
+            return Ok(Self::empty());
+        }
 
-    let mut len = mmap.len() / T::size() - offset;
-    if date_end < last_value.date() {
-        len = len - ((last_value.date() - date_end) / mdt.value().1) as usize;
+        // The values are sorted by the date, but the trades don't have a fixed interval
+        // and the klines can have gaps, so the positions in the file are searched
+        let mmap = memmap_for_file(file_path)?;
+        let position = partition_point::<T>(&mmap, |date| date >= date_start);
+        let end = partition_point::<T>(&mmap, |date| date > date_end);
+        Ok(Self {
+            mmap: Some(Arc::new(mmap)),
+            position,
+            end,
+            value_type: PhantomData,
+        })
     }
 
-    let mut result = Vec::new();
+    pub fn empty() -> Self {
+        Self {
+            mmap: None,
+            position: 0,
+            end: 0,
+            value_type: PhantomData,
+        }
+    }
 
-    for i in 0..len {
-        let v = T::from_be_bytes(&mmap[(offset + i) * T::size()..(offset + i + 1) * T::size()]);
-        if v.date() >= date_start && v.date() <= date_end {
-            result.push(v);
+    /// Returns the next value without moving to it
+    pub fn peek(&self) -> Option<T> {
+        match &self.mmap {
+            Some(mmap) if self.position < self.end => Some(T::from_be_bytes(
+                &mmap[self.position * T::size()..(self.position + 1) * T::size()],
+            )),
+            _ => None,
         }
     }
-    Ok(result)
+}
+
+impl<T: ToFromBytes + KLineTrait> Iterator for ValuesSource<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let value = self.peek()?;
+        self.position += 1;
+        Some(value)
+    }
+}
+
+/// Returns the index of the first value in the file which date satisfies `is_after`.
+/// The values are sorted by the date, so the binary search is used.
+fn partition_point<T: ToFromBytes + KLineTrait>(
+    mmap: &Mmap,
+    is_after: impl Fn(i64) -> bool,
+) -> usize {
+    let (mut low, mut high) = (0, mmap.len() / T::size());
+    while low < high {
+        let middle = (low + high) / 2;
+        let value = T::from_be_bytes(&mmap[middle * T::size()..(middle + 1) * T::size()]);
+        if is_after(value.date()) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    low
 }
 
 pub fn get_first_value_from_file<T: ToFromBytes>(file_path: PathBuf) -> io::Result<T> {
@@ -139,13 +194,21 @@ pub fn bin_file_name(exchange: String, symbol: String, market_data_type: MarketD
     .to_lowercase()
 }
 
+/// The trading rules of the symbols of the exchange are saved with the market data
+pub fn rules_file_name(exchange: String) -> String {
+    format!("{}-rules.json", exchange).to_lowercase()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::fs::remove_file;
 
-    use crate::{data_models::market_data::kline::KLine, tests::common::get_default_candles};
+    use crate::{
+        data_models::market_data::{kline::KLine, market_trade::MarketTrade},
+        tests::common::get_default_candles,
+    };
 
     #[test]
     fn test_create_and_write_to_file() {
@@ -169,7 +232,6 @@ mod tests {
             file_path.clone(),
             candles.first().unwrap().date(),
             candles.last().unwrap().date(),
-            MarketDataType::KLine1m,
         )
         .unwrap();
 
@@ -186,8 +248,7 @@ mod tests {
 
         assert!(file_path.exists());
 
-        let result: Vec<KLine> =
-            get_values_from_file(file_path.clone(), 0, i64::MAX, MarketDataType::KLine1m).unwrap();
+        let result: Vec<KLine> = get_values_from_file(file_path.clone(), 0, i64::MAX).unwrap();
 
         assert_eq!(&result, &candles);
         remove_file(file_path).unwrap();
@@ -208,7 +269,6 @@ mod tests {
             file_path.clone(),
             candles.first().unwrap().date() - MarketDataType::KLine1m.value().1,
             candles.last().unwrap().date() - MarketDataType::KLine1m.value().1,
-            MarketDataType::KLine1m,
         )
         .unwrap();
 
@@ -231,7 +291,6 @@ mod tests {
             file_path.clone(),
             candles.first().unwrap().date() + MarketDataType::KLine1m.value().1,
             candles.last().unwrap().date() + MarketDataType::KLine1m.value().1,
-            MarketDataType::KLine1m,
         )
         .unwrap();
 
@@ -254,7 +313,6 @@ mod tests {
             file_path.clone(),
             candles.first().unwrap().date() + MarketDataType::KLine1m.value().1,
             candles.last().unwrap().date() + MarketDataType::KLine1m.value().1,
-            MarketDataType::KLine1m,
         )
         .unwrap();
 
@@ -277,7 +335,6 @@ mod tests {
             file_path.clone(),
             candles.first().unwrap().date() + MarketDataType::KLine1m.value().1,
             candles.last().unwrap().date() - MarketDataType::KLine1m.value().1,
-            MarketDataType::KLine1m,
         )
         .unwrap();
 
@@ -285,6 +342,51 @@ mod tests {
         remove_file(file_path).unwrap();
     }
 
+    #[test]
+    fn test_get_trades_from_file() {
+        let trades: Vec<MarketTrade> = [1000, 1000, 1500, 4000, 4000, 4000, 9000]
+            .iter()
+            .enumerate()
+            .map(|(i, &timestamp)| MarketTrade {
+                id: i as i64,
+                price: 10.0 + i as f64,
+                qty: 1.0,
+                base_qty: 10.0 + i as f64,
+                timestamp,
+            })
+            .collect();
+        let file_path = PathBuf::from("test_11.bin");
+        create_and_write_to_file(&trades, file_path.clone()).unwrap();
+
+        let result: Vec<MarketTrade> =
+            get_values_from_file(file_path.clone(), 0, i64::MAX).unwrap();
+        assert_eq!(&result, &trades);
+
+        let result: Vec<MarketTrade> = get_values_from_file(file_path.clone(), 1200, 4000).unwrap();
+        assert_eq!(&result, &trades[2..6]);
+
+        let result: Vec<MarketTrade> = get_values_from_file(file_path.clone(), 4001, 8999).unwrap();
+        assert!(result.is_empty());
+
+        remove_file(file_path).unwrap();
+    }
+
+    #[test]
+    fn test_values_source() {
+        let candles = get_default_candles();
+        let file_path = PathBuf::from("test_12.bin");
+        create_and_write_to_file(&candles, file_path.clone()).unwrap();
+
+        let mut source: ValuesSource<KLine> =
+            ValuesSource::new(file_path.clone(), candles[1].date(), candles[2].date()).unwrap();
+        assert_eq!(source.peek(), Some(candles[1]));
+        assert_eq!(source.next(), Some(candles[1]));
+        assert_eq!(source.next(), Some(candles[2]));
+        assert_eq!(source.peek(), None);
+        assert_eq!(source.next(), None);
+        remove_file(file_path).unwrap();
+    }
+
     #[test]
     fn test_get_first_value_from_file() {
         let candles = get_default_candles();
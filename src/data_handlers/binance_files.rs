@@ -52,21 +52,42 @@ pub fn generate_archives_names(
         }
     }
 
-    add_days(
-        &mut result,
-        symbol.clone(),
-        market_data_type.clone(),
-        date_end.year(),
-        date_end.month(),
-        1,
-        date_end.day(),
-    );
+    // The funding rates are archived only by the months
+    if market_data_type == MarketDataType::FundingRate {
+        add_months(
+            &mut result,
+            symbol.clone(),
+            market_data_type.clone(),
+            date_end.year(),
+            date_end.month(),
+            date_end.month(),
+        );
+    } else {
+        add_days(
+            &mut result,
+            symbol.clone(),
+            market_data_type.clone(),
+            date_end.year(),
+            date_end.month(),
+            1,
+            date_end.day(),
+        );
+    }
 
     debug!("Generated archives: {:?}", result);
 
     result
 }
 
+/// Binance names the trades and the funding archives differently from the market data type
+fn archive_data_type(market_data_type: &MarketDataType) -> String {
+    match market_data_type {
+        MarketDataType::Trade => "trades".into(),
+        MarketDataType::FundingRate => "fundingRate".into(),
+        other => other.value().0,
+    }
+}
+
 fn add_months(
     result: &mut Vec<String>,
     symbol: String,
@@ -79,7 +100,7 @@ fn add_months(
         result.push(format!(
             "{}-{}-{}-{:02}.zip",
             symbol.to_uppercase(),
-            market_data_type.value().0,
+            archive_data_type(&market_data_type),
             year,
             month
         ));
@@ -99,7 +120,7 @@ fn add_days(
         result.push(format!(
             "{}-{}-{}-{:02}-{:02}.zip",
             symbol.to_uppercase(),
-            market_data_type.value().0,
+            archive_data_type(&market_data_type),
             year,
             month,
             day
@@ -139,6 +160,33 @@ mod tests {
         assert_eq!(result[12], "BTCUSDT-1m-2021-01-01.zip");
     }
 
+    #[test]
+    fn test_generate_archives_names_trades() {
+        let mut result = generate_archives_names(
+            "BTCUSDT".to_string(),
+            MarketDataType::Trade,
+            1682946000000,
+            1695399134000,
+        );
+        result.sort();
+        assert_eq!(result[0], "BTCUSDT-trades-2023-05.zip");
+        assert_eq!(result[25], "BTCUSDT-trades-2023-09-22.zip");
+    }
+
+    #[test]
+    fn test_generate_archives_names_funding() {
+        let mut result = generate_archives_names(
+            "BTCUSDT".to_string(),
+            MarketDataType::FundingRate,
+            1682946000000,
+            1695399134000,
+        );
+        result.sort();
+        assert_eq!(result.len(), 5);
+        assert_eq!(result[0], "BTCUSDT-fundingRate-2023-05.zip");
+        assert_eq!(result[4], "BTCUSDT-fundingRate-2023-09.zip");
+    }
+
     #[test]
     fn test_add_month() {
         let mut result = Vec::new();
@@ -0,0 +1,137 @@
+use std::vec::IntoIter;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_models::market_data::{kline::KLine, market_trade::MarketTrade};
+
+use super::bin_files::ValuesSource;
+
+#[derive(Debug, Clone)]
+enum Values {
+    Memory(IntoIter<KLine>),
+    KLines(ValuesSource<KLine>),
+    Trades(ValuesSource<MarketTrade>),
+}
+
+/// The klines a strategy runs on. The klines of the files are read lazily,
+/// so a strategy never holds more than one kline of the backtest period in the memory.
+/// The trades are returned as klines with a single price.
+/// Only the last kline is serialized, the rest is opened from the files again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "Option<KLine>", from = "Option<KLine>")]
+pub struct KLineSource {
+    values: Values,
+    last_kline: Option<KLine>,
+}
+
+impl KLineSource {
+    pub fn from_klines(source: ValuesSource<KLine>) -> Self {
+        Self::new(Values::KLines(source))
+    }
+
+    pub fn from_trades(source: ValuesSource<MarketTrade>) -> Self {
+        Self::new(Values::Trades(source))
+    }
+
+    fn new(values: Values) -> Self {
+        Self {
+            values,
+            last_kline: None,
+        }
+    }
+
+    /// Keeps the last kline of the previous source, so it's known if this one is empty
+    pub fn with_last_kline(mut self, last_kline: Option<KLine>) -> Self {
+        self.last_kline = last_kline;
+        self
+    }
+
+    /// Returns the next kline without moving to it
+    pub fn peek(&self) -> Option<KLine> {
+        match &self.values {
+            Values::Memory(klines) => klines.as_slice().first().copied(),
+            Values::KLines(source) => source.peek(),
+            Values::Trades(source) => source.peek().as_ref().map(KLine::from),
+        }
+    }
+
+    /// Returns the next kline only if it has the `date`
+    pub fn next_with_date(&mut self, date: i64) -> Option<KLine> {
+        match self.peek() {
+            Some(kline) if kline.date == date => self.next(),
+            _ => None,
+        }
+    }
+
+    /// Returns the last returned kline
+    pub fn last_kline(&self) -> Option<KLine> {
+        self.last_kline
+    }
+}
+
+impl Iterator for KLineSource {
+    type Item = KLine;
+
+    fn next(&mut self) -> Option<KLine> {
+        let kline = match &mut self.values {
+            Values::Memory(klines) => klines.next(),
+            Values::KLines(source) => source.next(),
+            Values::Trades(source) => source.next().as_ref().map(KLine::from),
+        }?;
+        self.last_kline = Some(kline);
+        Some(kline)
+    }
+}
+
+impl From<Vec<KLine>> for KLineSource {
+    fn from(klines: Vec<KLine>) -> Self {
+        Self::new(Values::Memory(klines.into_iter()))
+    }
+}
+
+impl From<KLineSource> for Option<KLine> {
+    fn from(source: KLineSource) -> Self {
+        source.last_kline
+    }
+}
+
+impl From<Option<KLine>> for KLineSource {
+    fn from(last_kline: Option<KLine>) -> Self {
+        KLineSource::default().with_last_kline(last_kline)
+    }
+}
+
+impl Default for KLineSource {
+    fn default() -> Self {
+        Vec::new().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_models::market_data::kline_trait::KLineTrait;
+
+    use super::*;
+
+    #[test]
+    fn test_kline_source() {
+        let klines = vec![
+            KLine::zero_kline(1, 10.0),
+            KLine::zero_kline(3, 20.0),
+            KLine::zero_kline(3, 30.0),
+        ];
+        let mut source = KLineSource::from(klines.clone());
+        assert_eq!(source.peek(), Some(klines[0]));
+        assert_eq!(source.last_kline(), None);
+        assert_eq!(source.next_with_date(2), None);
+        assert_eq!(source.next_with_date(1), Some(klines[0]));
+        assert_eq!(source.next_with_date(3), Some(klines[1]));
+        assert_eq!(source.next_with_date(3), Some(klines[2]));
+        assert_eq!(source.next_with_date(3), None);
+        assert_eq!(source.last_kline(), Some(klines[2]));
+
+        let source = KLineSource::default().with_last_kline(Some(klines[2]));
+        assert_eq!(source.peek(), None);
+        assert_eq!(source.last_kline(), Some(klines[2]));
+    }
+}
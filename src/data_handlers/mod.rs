@@ -1,6 +1,7 @@
 pub mod bin_files;
 pub mod binance_files;
 pub mod csv_files;
+pub mod kline_source;
 pub mod kv_store;
 pub mod pipeline;
 pub mod utils;
@@ -134,7 +134,8 @@ where
     let file_name = extract_archive(data_path.clone(), archive_path.clone()).unwrap();
     let csv_path = data_path.clone().join(file_name.clone());
     let mut trades = load_data_from_csv::<T>(csv_path.clone());
-    if market_data_type != MarketDataType::Trade {
+    // The trades and the funding rates don't have a fixed interval
+    if market_data_type.value().1 > 0 {
         trades = fill_trades_by_zeros::<T>(trades, market_data_type.clone(), last_trade_date);
     }
     let binary_path = data_path.clone().join(bin_file_name(
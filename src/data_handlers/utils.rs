@@ -38,6 +38,14 @@ pub fn get_archive_url(
     archive_name: String,
 ) -> String {
     match mdt {
+        // The funding rates are only of the USD-M perpetual futures
+        MarketDataType::FundingRate => format!(
+            "{}/data/futures/um/{}/fundingRate/{}/{}",
+            data_url,
+            get_period(archive_name.clone()),
+            symbol.to_uppercase(),
+            archive_name
+        ),
         MarketDataType::Trade => format!(
             "{}/data/spot/{}/trades/{}/{}",
             data_url,
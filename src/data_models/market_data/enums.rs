@@ -13,6 +13,8 @@ pub enum OrderType {
     TakeProfit,
     TakeProfitMarket,
     TrailingStopMarket,
+    /// The position is closed by the exchange when its margin is below the maintenance margin
+    Liquidation,
 }
 
 impl Default for OrderType {
@@ -42,6 +44,42 @@ impl Default for OrderStatus {
     }
 }
 
+/// The order in which the price is assumed to go through the kline prices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PricePath {
+    /// Only the close price is checked
+    Close,
+    /// Open -> High -> Low -> Close
+    OpenHighLowClose,
+    /// Open -> Low -> High -> Close
+    OpenLowHighClose,
+    /// Against the positions: Open -> Low -> High -> Close for the longs and
+    /// Open -> High -> Low -> Close for the shorts, and a take profit can't be filled
+    /// in the kline it was placed
+    WorstCase,
+}
+
+impl Default for PricePath {
+    fn default() -> Self {
+        Self::Close
+    }
+}
+
+/// How the returns of the equity are annualized in the metrics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Annualization {
+    /// The daily returns, the value is the number of days in a year
+    Daily(u32),
+    /// The returns between the equity samples, the year is 365 days of the sampling interval
+    Interval,
+}
+
+impl Default for Annualization {
+    fn default() -> Self {
+        Self::Daily(365)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, EnumIter)]
 pub enum MarketDataType {
     Trade,
@@ -57,6 +95,8 @@ pub enum MarketDataType {
     KLine6h,
     KLine8h,
     KLine1d,
+    /// The funding rates of the perpetual futures, they are not run by the strategies
+    FundingRate,
 }
 
 impl MarketDataType {
@@ -76,6 +116,7 @@ impl MarketDataType {
             MarketDataType::KLine6h => ("6h".into(), 6 * 60 * 60 * 1000),
             MarketDataType::KLine8h => ("8h".into(), 8 * 60 * 60 * 1000),
             MarketDataType::KLine1d => ("1d".into(), 24 * 60 * 60 * 1000),
+            MarketDataType::FundingRate => ("funding".into(), 0),
         }
     }
 
@@ -106,6 +147,7 @@ impl FromStr for MarketDataType {
             "6h" => Ok(MarketDataType::KLine6h),
             "8h" => Ok(MarketDataType::KLine8h),
             "1d" => Ok(MarketDataType::KLine1d),
+            "funding" => Ok(MarketDataType::FundingRate),
             _ => Err(()),
         }
     }
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// The value of the account at the moment of the backtest
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub date: i64,
+    /// The cash plus the open quantity marked to the close price
+    pub equity: f64,
+    pub cash: f64,
+    /// The value of the open quantity by the close price
+    pub exposure: f64,
+    /// The margin used by the open positions
+    pub margin: f64,
+}
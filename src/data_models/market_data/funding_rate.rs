@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_models::be_bytes::ToFromBytes;
+
+use super::kline_trait::KLineTrait;
+
+pub const FUNDING_RATE_SIZE: usize = 3 * 8;
+
+/// The funding rate of a perpetual futures contract, the row of the Binance
+/// `fundingRate` archives. The longs pay the shorts the `rate` of the position value
+/// at the `date` if it's positive and the shorts pay the longs if it's negative.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct FundingRate {
+    pub date: i64,
+    pub interval_hours: i64,
+    pub rate: f64,
+}
+
+impl ToFromBytes for FundingRate {
+    fn size() -> usize {
+        FUNDING_RATE_SIZE
+    }
+
+    fn to_be_bytes(&self) -> Vec<u8> {
+        [
+            self.date.to_be_bytes(),
+            self.interval_hours.to_be_bytes(),
+            self.rate.to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    fn from_be_bytes(b: &[u8]) -> FundingRate {
+        FundingRate {
+            date: i64::from_be_bytes(b[..8].try_into().unwrap()),
+            interval_hours: i64::from_be_bytes(b[8..16].try_into().unwrap()),
+            rate: f64::from_be_bytes(b[16..24].try_into().unwrap()),
+        }
+    }
+}
+
+/// The rate is the only value, so it's the price of the kline
+impl KLineTrait for FundingRate {
+    fn date(&self) -> i64 {
+        self.date
+    }
+    fn open(&self) -> f64 {
+        self.rate
+    }
+    fn high(&self) -> f64 {
+        self.rate
+    }
+    fn low(&self) -> f64 {
+        self.rate
+    }
+    fn close(&self) -> f64 {
+        self.rate
+    }
+    fn qty(&self) -> f64 {
+        0.0
+    }
+    fn zero_kline(date: i64, price: f64) -> Self {
+        FundingRate {
+            date,
+            interval_hours: 0,
+            rate: price,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::ReaderBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_funding_rate() {
+        let csv = "calc_time,funding_interval_hours,last_funding_rate\n\
+                   1682899200000,8,0.00010000\n\
+                   1682928000000,8,-0.00002500\n";
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(csv.as_bytes());
+        // The header of the newer archives isn't a value
+        let rates: Vec<FundingRate> = reader.deserialize().filter_map(Result::ok).collect();
+        assert_eq!(
+            rates,
+            vec![
+                FundingRate {
+                    date: 1682899200000,
+                    interval_hours: 8,
+                    rate: 0.0001,
+                },
+                FundingRate {
+                    date: 1682928000000,
+                    interval_hours: 8,
+                    rate: -0.000025,
+                },
+            ]
+        );
+        let bytes = rates[1].to_be_bytes();
+        assert_eq!(bytes.len(), FundingRate::size());
+        assert_eq!(FundingRate::from_be_bytes(&bytes), rates[1]);
+    }
+}
@@ -2,7 +2,9 @@ use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::data_models::be_bytes::ToFromBytes;
 
-use super::kline_trait::KLineTrait;
+use super::{
+    enums::PricePath, kline_trait::KLineTrait, market_trade::MarketTrade, position::PositionSide,
+};
 
 pub const KLINE_SIZE: usize = 6 * 8;
 
@@ -35,10 +37,58 @@ impl KLine {
         self
     }
 
+    pub fn with_open(mut self, value: f64) -> Self {
+        self.open = value;
+        self
+    }
+
+    pub fn with_high(mut self, value: f64) -> Self {
+        self.high = value;
+        self
+    }
+
+    pub fn with_low(mut self, value: f64) -> Self {
+        self.low = value;
+        self
+    }
+
     pub fn with_close(mut self, value: f64) -> Self {
         self.close = value;
         self
     }
+
+    pub fn with_volume(mut self, value: f64) -> Self {
+        self.volume = value;
+        self
+    }
+
+    /// Splits the kline into the segments the price went through according to the `price_path`.
+    /// Every segment is a kline from one price point to the next one, so it moves in one direction.
+    /// The first segment is the open price itself, it's the gap from the previous kline.
+    /// The worst case goes against the positions of the `side` first.
+    pub fn price_path(&self, price_path: &PricePath, side: &PositionSide) -> Vec<KLine> {
+        let points = match (price_path, side) {
+            (PricePath::Close, _) => return vec![*self],
+            (PricePath::OpenHighLowClose, _) | (PricePath::WorstCase, PositionSide::Short) => {
+                [self.open, self.high, self.low, self.close]
+            }
+            (PricePath::OpenLowHighClose, _) | (PricePath::WorstCase, PositionSide::Long) => {
+                [self.open, self.low, self.high, self.close]
+            }
+        };
+        let mut result = vec![KLine::zero_kline(self.date, self.open)];
+        for pair in points.windows(2) {
+            result.push(KLine {
+                date: self.date,
+                open: pair[0],
+                high: pair[0].max(pair[1]),
+                low: pair[0].min(pair[1]),
+                close: pair[1],
+                volume: 0.0,
+            });
+        }
+        result
+    }
 }
 
 fn f64_to_i64<'de, D>(deserializer: D) -> Result<i64, D::Error>
@@ -107,3 +157,56 @@ impl KLineTrait for KLine {
         }
     }
 }
+
+/// A trade is a kline with a single price, so the strategies can run on the trades
+impl From<&MarketTrade> for KLine {
+    fn from(trade: &MarketTrade) -> Self {
+        KLine::zero_kline(trade.timestamp, trade.price).with_volume(trade.qty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_kline() -> KLine {
+        KLine::blank()
+            .with_date(1)
+            .with_open(100.0)
+            .with_high(120.0)
+            .with_low(80.0)
+            .with_close(110.0)
+    }
+
+    fn closes(klines: &Vec<KLine>) -> Vec<f64> {
+        klines.iter().map(|k| k.close).collect()
+    }
+
+    #[test]
+    fn test_price_path() {
+        let kline = get_kline();
+        let long = PositionSide::Long;
+        assert_eq!(kline.price_path(&PricePath::Close, &long), vec![kline]);
+
+        let path = kline.price_path(&PricePath::OpenHighLowClose, &long);
+        assert_eq!(closes(&path), vec![100.0, 120.0, 80.0, 110.0]);
+        assert_eq!(
+            (path[2].open, path[2].high, path[2].low),
+            (120.0, 120.0, 80.0)
+        );
+        assert!(path.iter().all(|k| k.date == 1));
+
+        let path = kline.price_path(&PricePath::OpenLowHighClose, &long);
+        assert_eq!(closes(&path), vec![100.0, 80.0, 120.0, 110.0]);
+        assert_eq!(
+            (path[1].open, path[1].high, path[1].low),
+            (100.0, 100.0, 80.0)
+        );
+
+        // The worst case goes down first for the longs and up first for the shorts
+        let path = kline.price_path(&PricePath::WorstCase, &long);
+        assert_eq!(closes(&path), vec![100.0, 80.0, 120.0, 110.0]);
+        let path = kline.price_path(&PricePath::WorstCase, &PositionSide::Short);
+        assert_eq!(closes(&path), vec![100.0, 120.0, 80.0, 110.0]);
+    }
+}
@@ -1,9 +1,12 @@
 use std::collections::HashSet;
 
 use serde::Serialize;
-use statistical::standard_deviation;
+use statistical::{mean, standard_deviation};
 
-use super::position::Position;
+use super::{enums::Annualization, equity::EquityPoint, position::Position};
+
+const DAY: i64 = 24 * 60 * 60 * 1000;
+const YEAR: i64 = 365 * DAY;
 
 #[derive(Default, Debug, Serialize)]
 pub struct Metrics {
@@ -20,6 +23,15 @@ pub struct Metrics {
     pub profit_factor: f64,
     pub expected_payoff: f64,
     pub sortino: f64,
+    pub sharpe: f64,
+    pub calmar: f64,
+    /// The compound annual growth rate in percent
+    pub cagr: f64,
+    /// The annualized standard deviation of the returns in percent
+    pub volatility: f64,
+    /// The annualized deviation of the negative returns in percent
+    pub downside_deviation: f64,
+    pub ulcer_index: f64,
     pub average_position_size: f64,
     pub start_deposit: f64,
     pub finish_deposit: f64,
@@ -27,12 +39,41 @@ pub struct Metrics {
     pub total_profit_percent: f64,
     pub max_deposit: f64,
     pub max_drawdown: f64,
+    pub max_drawdown_percent: f64,
+    /// The longest time in milliseconds the equity was below its previous peak
+    pub max_drawdown_duration: i64,
     pub drawdown: f64,
+    pub drawdown_percent: f64,
     pub max_use_of_funds: f64,
+    /// The funding received by the futures positions, it's negative if it's paid
+    pub funding: f64,
+    /// The cost of the market order slippage, it's already in the profit
+    pub slippage: f64,
+    /// The maker and taker fees of the positions
+    pub fees: f64,
+    /// The total return of the benchmark in percent
+    pub benchmark_return: f64,
+    /// The total return above the benchmark in percent
+    pub excess_return: f64,
+    /// The annualized return not explained by the benchmark in percent
+    pub alpha: f64,
+    pub beta: f64,
+    pub correlation: f64,
+    pub information_ratio: f64,
+    /// The return in the periods the benchmark rose, in percent of the benchmark return
+    pub up_capture: f64,
+    /// The return in the periods the benchmark fell, in percent of the benchmark return
+    pub down_capture: f64,
 }
 
 impl Metrics {
-    pub fn new(positions: &Vec<Position>, start_deposit: f64, finish_deposit: f64) -> Self {
+    pub fn new(
+        positions: &Vec<Position>,
+        start_deposit: f64,
+        finish_deposit: f64,
+        equity: &Vec<EquityPoint>,
+        annualization: &Annualization,
+    ) -> Self {
         let positions_number = positions.len() as u64;
         if positions_number == 0 {
             return Metrics::default();
@@ -71,15 +112,29 @@ impl Metrics {
             average_loss_position,
         );
 
-        let sortino = Self::get_sortino(positions);
+        let (returns, periods_per_year) = Self::get_returns(equity, annualization);
+        let volatility = Self::get_volatility(&returns, periods_per_year);
+        let downside_deviation = Self::get_downside_deviation(&returns, periods_per_year);
+        let sharpe = Self::get_sharpe(&returns, periods_per_year);
+        let sortino = Self::get_sortino(&returns, periods_per_year);
+        let cagr = Self::get_cagr(equity);
 
         let average_position_size = Self::get_average_position_size(positions);
         let total_profit = Self::get_total_profit(finish_deposit, start_deposit);
         let total_profit_percent = Self::get_total_profit_percent(finish_deposit, start_deposit);
-        let max_deposit = 0.0;
-        let max_drawdown = 0.0;
-        let drawdown = 0.0;
+        let max_deposit = Self::get_max_deposit(equity, start_deposit);
+        let (max_drawdown, max_drawdown_percent) = Self::get_max_drawdown(equity);
+        let max_drawdown_duration = Self::get_max_drawdown_duration(equity);
+        let (drawdown, drawdown_percent) = Self::get_drawdown(equity);
+        let calmar = Self::get_calmar(cagr, max_drawdown_percent);
+        let ulcer_index = Self::get_ulcer_index(equity);
         let max_use_of_funds = Self::get_max_use_of_funds(positions);
+        let funding = positions.iter().map(|position| position.funding).sum();
+        let slippage = positions.iter().map(|position| position.slippage()).sum();
+        let fees = positions
+            .iter()
+            .map(|position| position.commission_buy() + position.commission_sell())
+            .sum();
 
         Metrics {
             id: 0,
@@ -95,6 +150,12 @@ impl Metrics {
             profit_factor,
             expected_payoff,
             sortino,
+            sharpe,
+            calmar,
+            cagr,
+            volatility,
+            downside_deviation,
+            ulcer_index,
             average_position_size,
             start_deposit,
             finish_deposit,
@@ -102,9 +163,46 @@ impl Metrics {
             total_profit_percent,
             max_deposit,
             max_drawdown,
+            max_drawdown_percent,
+            max_drawdown_duration,
             drawdown,
+            drawdown_percent,
             max_use_of_funds,
+            funding,
+            slippage,
+            fees,
+            ..Default::default()
+        }
+    }
+
+    /// Adds the comparison of the `equity` with the `benchmark` equity sampled at the same dates
+    pub fn with_benchmark(
+        mut self,
+        equity: &Vec<EquityPoint>,
+        benchmark: &Vec<EquityPoint>,
+        annualization: &Annualization,
+    ) -> Self {
+        let total_return = Self::get_equity_return(equity);
+        self.benchmark_return = Self::get_equity_return(benchmark);
+        self.excess_return = total_return - self.benchmark_return;
+
+        let (returns, periods_per_year) =
+            Self::get_paired_returns(equity, benchmark, annualization);
+        let (strategy, benchmark): (Vec<f64>, Vec<f64>) = returns.iter().copied().unzip();
+        let (beta, correlation) = Self::get_beta_and_correlation(&strategy, &benchmark);
+        self.beta = beta;
+        self.correlation = correlation;
+        if !returns.is_empty() {
+            self.alpha = (mean(&strategy) - beta * mean(&benchmark)) * periods_per_year * 100.0;
+        }
+        let excess: Vec<f64> = returns.iter().map(|(s, b)| s - b).collect();
+        let tracking_error = Self::get_volatility(&excess, periods_per_year);
+        if tracking_error != 0.0 {
+            self.information_ratio = mean(&excess) * periods_per_year * 100.0 / tracking_error;
         }
+        self.up_capture = Self::get_capture(&returns, |b| b > 0.0);
+        self.down_capture = Self::get_capture(&returns, |b| b < 0.0);
+        self
     }
 
     fn get_profit_positions(positions: &Vec<Position>) -> Vec<&Position> {
@@ -159,10 +257,7 @@ impl Metrics {
         average_profit_position: f64,
     ) -> f64 {
         if profit_positions_number != 0 && average_profit_position != 0.0 {
-            positions
-                .iter()
-                .map(|p| p.volume_buy() * p.weighted_avg_price_buy())
-                .sum::<f64>()
+            positions.iter().map(|p| p.entry_value()).sum::<f64>()
                 / profit_positions_number as f64
                 / average_profit_position
         } else {
@@ -189,31 +284,173 @@ impl Metrics {
             + loss_positions_percent * average_loss_position
     }
 
-    fn get_sortino(positions: &Vec<Position>) -> f64 {
-        let mut stddev = 0.0;
-        let s: Vec<f64> = positions
-            .iter()
-            .filter(|t| t.pnl.unwrap() < 0.0)
-            .map(|t| t.pnl.unwrap())
+    /// Returns the equity values the returns are calculated from and the number of the periods in a year
+    fn get_period_values(
+        equity: &Vec<EquityPoint>,
+        annualization: &Annualization,
+    ) -> (Vec<f64>, f64) {
+        match annualization {
+            Annualization::Daily(days) => {
+                // The equity at the start and at the end of every day
+                let mut values: Vec<f64> = equity.first().map(|p| p.equity).into_iter().collect();
+                for (i, p) in equity.iter().enumerate() {
+                    let next = equity.get(i + 1);
+                    if next.map_or(true, |n| n.date.div_euclid(DAY) != p.date.div_euclid(DAY)) {
+                        values.push(p.equity);
+                    }
+                }
+                (values, *days as f64)
+            }
+            Annualization::Interval => {
+                let mut intervals: Vec<i64> =
+                    equity.windows(2).map(|w| w[1].date - w[0].date).collect();
+                intervals.sort_unstable();
+                let periods_per_year = match intervals.get(intervals.len() / 2) {
+                    Some(&interval) if interval > 0 => YEAR as f64 / interval as f64,
+                    _ => 0.0,
+                };
+                (equity.iter().map(|p| p.equity).collect(), periods_per_year)
+            }
+        }
+    }
+
+    /// Returns the periodic returns of the equity and the number of the periods in a year
+    fn get_returns(equity: &Vec<EquityPoint>, annualization: &Annualization) -> (Vec<f64>, f64) {
+        let (values, periods_per_year) = Self::get_period_values(equity, annualization);
+        let returns = values
+            .windows(2)
+            .filter(|w| w[0] > 0.0)
+            .map(|w| w[1] / w[0] - 1.0)
             .collect();
-        if s.len() >= 2 {
-            stddev = standard_deviation(&s, None);
+        (returns, periods_per_year)
+    }
+
+    /// Returns the pairs of the returns of the equity and the benchmark of the same periods
+    fn get_paired_returns(
+        equity: &Vec<EquityPoint>,
+        benchmark: &Vec<EquityPoint>,
+        annualization: &Annualization,
+    ) -> (Vec<(f64, f64)>, f64) {
+        let (values, periods_per_year) = Self::get_period_values(equity, annualization);
+        let (benchmark_values, _) = Self::get_period_values(benchmark, annualization);
+        if values.len() != benchmark_values.len() {
+            return (Vec::new(), periods_per_year);
         }
-        if stddev != 0.0 {
-            (positions.iter().map(|t| t.pnl.unwrap()).sum::<f64>() / positions.len() as f64)
-                / stddev
-                * 252_f64.sqrt()
-        } else {
-            0.0
+        let returns = values
+            .windows(2)
+            .zip(benchmark_values.windows(2))
+            .filter(|(s, b)| s[0] > 0.0 && b[0] > 0.0)
+            .map(|(s, b)| (s[1] / s[0] - 1.0, b[1] / b[0] - 1.0))
+            .collect();
+        (returns, periods_per_year)
+    }
+
+    /// Returns the total return of the equity curve in percent
+    fn get_equity_return(equity: &Vec<EquityPoint>) -> f64 {
+        match (equity.first(), equity.last()) {
+            (Some(first), Some(last)) if first.equity > 0.0 => {
+                (last.equity / first.equity - 1.0) * 100.0
+            }
+            _ => 0.0,
         }
     }
 
-    fn get_average_position_size(positions: &Vec<Position>) -> f64 {
-        positions
+    fn get_beta_and_correlation(strategy: &Vec<f64>, benchmark: &Vec<f64>) -> (f64, f64) {
+        if strategy.len() < 2 {
+            return (0.0, 0.0);
+        }
+        let (strategy_mean, benchmark_mean) = (mean(strategy), mean(benchmark));
+        let covariance = strategy
             .iter()
-            .map(|p| p.volume_buy() * p.weighted_avg_price_buy())
+            .zip(benchmark.iter())
+            .map(|(s, b)| (s - strategy_mean) * (b - benchmark_mean))
             .sum::<f64>()
-            / positions.len() as f64
+            / (strategy.len() - 1) as f64;
+        let strategy_deviation = standard_deviation(strategy, None);
+        let benchmark_deviation = standard_deviation(benchmark, None);
+        if benchmark_deviation == 0.0 {
+            return (0.0, 0.0);
+        }
+        let beta = covariance / benchmark_deviation.powi(2);
+        let correlation = if strategy_deviation == 0.0 {
+            0.0
+        } else {
+            covariance / (strategy_deviation * benchmark_deviation)
+        };
+        (beta, correlation)
+    }
+
+    /// Returns the mean return of the periods the benchmark return matches the `filter`
+    /// in percent of the mean benchmark return
+    fn get_capture(returns: &Vec<(f64, f64)>, filter: impl Fn(f64) -> bool) -> f64 {
+        let (strategy, benchmark): (Vec<f64>, Vec<f64>) =
+            returns.iter().copied().filter(|(_, b)| filter(*b)).unzip();
+        if benchmark.is_empty() {
+            return 0.0;
+        }
+        mean(&strategy) / mean(&benchmark) * 100.0
+    }
+
+    fn get_volatility(returns: &Vec<f64>, periods_per_year: f64) -> f64 {
+        if returns.len() < 2 {
+            return 0.0;
+        }
+        standard_deviation(returns, None) * periods_per_year.sqrt() * 100.0
+    }
+
+    fn get_downside_deviation(returns: &Vec<f64>, periods_per_year: f64) -> f64 {
+        if returns.is_empty() {
+            return 0.0;
+        }
+        let downside =
+            returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64;
+        downside.sqrt() * periods_per_year.sqrt() * 100.0
+    }
+
+    fn get_sharpe(returns: &Vec<f64>, periods_per_year: f64) -> f64 {
+        let volatility = Self::get_volatility(returns, periods_per_year);
+        if volatility == 0.0 {
+            return 0.0;
+        }
+        mean(returns) * periods_per_year * 100.0 / volatility
+    }
+
+    fn get_sortino(returns: &Vec<f64>, periods_per_year: f64) -> f64 {
+        let downside_deviation = Self::get_downside_deviation(returns, periods_per_year);
+        if downside_deviation == 0.0 {
+            return 0.0;
+        }
+        mean(returns) * periods_per_year * 100.0 / downside_deviation
+    }
+
+    fn get_cagr(equity: &Vec<EquityPoint>) -> f64 {
+        match (equity.first(), equity.last()) {
+            (Some(first), Some(last)) if first.equity > 0.0 && last.date > first.date => {
+                let years = (last.date - first.date) as f64 / YEAR as f64;
+                ((last.equity / first.equity).max(0.0).powf(1.0 / years) - 1.0) * 100.0
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn get_calmar(cagr: f64, max_drawdown_percent: f64) -> f64 {
+        if max_drawdown_percent == 0.0 {
+            return 0.0;
+        }
+        cagr / max_drawdown_percent
+    }
+
+    /// Returns the root mean square of the drawdowns in percent
+    fn get_ulcer_index(equity: &Vec<EquityPoint>) -> f64 {
+        let drawdowns = Self::get_drawdowns(equity);
+        if drawdowns.is_empty() {
+            return 0.0;
+        }
+        (drawdowns.iter().map(|d| d.1.powi(2)).sum::<f64>() / drawdowns.len() as f64).sqrt()
+    }
+
+    fn get_average_position_size(positions: &Vec<Position>) -> f64 {
+        positions.iter().map(|p| p.entry_value()).sum::<f64>() / positions.len() as f64
     }
 
     fn get_total_profit(finish_deposit: f64, start_deposit: f64) -> f64 {
@@ -224,10 +461,72 @@ impl Metrics {
         (finish_deposit - start_deposit) / start_deposit as f64 * 100.0
     }
 
+    /// Returns the peak equity
+    fn get_max_deposit(equity: &Vec<EquityPoint>, start_deposit: f64) -> f64 {
+        equity
+            .iter()
+            .map(|p| p.equity)
+            .fold(start_deposit, f64::max)
+    }
+
+    /// Returns the drawdown of every equity point from the peak before it
+    fn get_drawdowns(equity: &Vec<EquityPoint>) -> Vec<(f64, f64)> {
+        let mut peak = f64::MIN;
+        equity
+            .iter()
+            .map(|p| {
+                peak = peak.max(p.equity);
+                let drawdown = peak - p.equity;
+                let percent = if peak > 0.0 {
+                    drawdown / peak * 100.0
+                } else {
+                    0.0
+                };
+                (drawdown, percent)
+            })
+            .collect()
+    }
+
+    /// Returns the max drawdown and the max drawdown in percent, they can be from different peaks
+    fn get_max_drawdown(equity: &Vec<EquityPoint>) -> (f64, f64) {
+        Self::get_drawdowns(equity)
+            .iter()
+            .fold((0.0, 0.0), |max, d| (max.0.max(d.0), max.1.max(d.1)))
+    }
+
+    /// Returns the drawdown at the end of the backtest
+    fn get_drawdown(equity: &Vec<EquityPoint>) -> (f64, f64) {
+        Self::get_drawdowns(equity)
+            .last()
+            .copied()
+            .unwrap_or((0.0, 0.0))
+    }
+
+    /// Returns the longest time from a peak to the recovery or to the end of the backtest
+    fn get_max_drawdown_duration(equity: &Vec<EquityPoint>) -> i64 {
+        let mut peak: Option<&EquityPoint> = None;
+        let mut underwater = false;
+        let mut max_duration = 0;
+        for p in equity.iter() {
+            if let Some(peak_point) = peak {
+                if p.equity < peak_point.equity || underwater {
+                    max_duration = max_duration.max(p.date - peak_point.date);
+                }
+                if p.equity < peak_point.equity {
+                    underwater = true;
+                    continue;
+                }
+            }
+            peak = Some(p);
+            underwater = false;
+        }
+        max_duration
+    }
+
     fn get_max_use_of_funds(positions: &Vec<Position>) -> f64 {
         positions
             .iter()
-            .map(|p| p.volume_buy() * p.weighted_avg_price_buy())
+            .map(|p| p.entry_value())
             .reduce(f64::max)
             .unwrap()
     }
@@ -239,7 +538,7 @@ mod test {
     use crate::data_models::market_data::{
         enums::{OrderType, Side},
         order::Order,
-        position::PositionStatus,
+        position::{PositionSide, PositionStatus},
     };
 
     use super::*;
@@ -256,6 +555,8 @@ mod test {
                     Order::new(1502942400, 100.0, Side::Buy, OrderType::Market).with_price_executed(100.0).with_qty(1.0).filled(),
                     Order::new(1502942400 + 3600, 120.0, Side::Sell, OrderType::Market).with_price_executed(120.0).with_qty(1.0).filled(),
                 ],
+                side: PositionSide::Long,
+                funding: 0.0,
             },
             Position {
                 id: "2".into(),
@@ -266,6 +567,8 @@ mod test {
                     Order::new(1502942400, 100.0, Side::Buy, OrderType::Market).with_price_executed(100.0).with_qty(1.0).filled(),
                     Order::new(1502942400 + 3600, 120.0, Side::Sell, OrderType::Market).with_price_executed(120.0).with_qty(1.0).filled(),
                 ],
+                side: PositionSide::Long,
+                funding: 0.0,
             },
             Position {
                 id: "3".into(),
@@ -276,6 +579,8 @@ mod test {
                     Order::new(1502942400, 100.0, Side::Buy, OrderType::Market).with_price_executed(100.0).with_qty(1.0).filled(),
                     Order::new(1502942400 + 3600, 80.0, Side::Sell, OrderType::Market).with_price_executed(80.0).with_qty(1.0).filled(),
                 ],
+                side: PositionSide::Long,
+                funding: 0.0,
             },
             Position {
                 id: "4".into(),
@@ -285,7 +590,9 @@ mod test {
                 orders: vec![
                     Order::new(1502942400, 100.0, Side::Buy, OrderType::Market).with_price_executed(100.0).with_qty(1.0).filled(),
                     Order::new(1502942400 + 3600, 60.0, Side::Sell, OrderType::Market).with_price_executed(60.0).with_qty(1.0).filled(),
-                ],            
+                ],
+                side: PositionSide::Long,
+                funding: 0.0,
             },
         ]
     }
@@ -374,12 +681,6 @@ mod test {
         assert_eq!(Metrics::get_expected_payoff(0.0, 0.0, 0.0, 0.0), 0.0);
     }
 
-    #[test]
-    fn test_get_sortino() {
-        let positions = get_positions_info();
-        assert_eq!(Metrics::get_sortino(&positions), -5.612486080160912);
-    }
-
     #[test]
     fn test_get_average_position_size() {
         let positions = get_positions_info();
@@ -401,4 +702,145 @@ mod test {
         let positions = get_positions_info();
         assert_eq!(Metrics::get_max_use_of_funds(&positions), 100.0);
     }
+
+    fn get_equity(values: Vec<(i64, f64)>) -> Vec<EquityPoint> {
+        values
+            .iter()
+            .map(|&(date, equity)| EquityPoint {
+                date,
+                equity,
+                cash: equity,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_max_deposit() {
+        let equity = get_equity(vec![(1, 100.0), (2, 120.0), (3, 90.0)]);
+        assert_eq!(Metrics::get_max_deposit(&equity, 100.0), 120.0);
+        assert_eq!(Metrics::get_max_deposit(&Vec::new(), 100.0), 100.0);
+    }
+
+    #[test]
+    fn test_get_drawdown() {
+        let equity = get_equity(vec![
+            (1, 100.0),
+            (2, 50.0),
+            (3, 200.0),
+            (4, 140.0),
+            (5, 210.0),
+            (6, 189.0),
+        ]);
+        assert_eq!(Metrics::get_max_drawdown(&equity), (60.0, 50.0));
+        assert_eq!(Metrics::get_drawdown(&equity), (21.0, 10.0));
+        assert_eq!(Metrics::get_max_drawdown(&Vec::new()), (0.0, 0.0));
+        assert_eq!(Metrics::get_drawdown(&Vec::new()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_get_max_drawdown_duration() {
+        let equity = get_equity(vec![
+            (0, 100.0),
+            (10, 90.0),
+            (20, 100.0),
+            (30, 80.0),
+            (40, 90.0),
+            (70, 95.0),
+            (80, 120.0),
+            (90, 110.0),
+        ]);
+        // The drawdown from 20 is recovered at 80
+        assert_eq!(Metrics::get_max_drawdown_duration(&equity), 60);
+        let equity = get_equity(vec![(0, 100.0), (10, 110.0), (20, 100.0), (50, 90.0)]);
+        assert_eq!(Metrics::get_max_drawdown_duration(&equity), 40);
+        let equity = get_equity(vec![(0, 100.0), (10, 110.0), (20, 120.0)]);
+        assert_eq!(Metrics::get_max_drawdown_duration(&equity), 0);
+    }
+
+    #[test]
+    fn test_get_returns() {
+        let equity = get_equity(vec![
+            (0, 100.0),
+            (DAY / 2, 90.0),
+            (DAY, 110.0),
+            (2 * DAY, 99.0),
+            (2 * DAY + 1, 121.0),
+        ]);
+        let (returns, periods_per_year) = Metrics::get_returns(&equity, &Annualization::Daily(365));
+        assert_eq!(periods_per_year, 365.0);
+        assert_eq!(returns.len(), 3);
+        assert!((returns[0] + 0.1).abs() < 1e-12);
+        assert!((returns[2] - 0.1).abs() < 1e-12);
+
+        let (returns, periods_per_year) = Metrics::get_returns(&equity, &Annualization::Interval);
+        assert_eq!(periods_per_year, 730.0);
+        assert_eq!(returns.len(), 4);
+    }
+
+    #[test]
+    fn test_return_metrics() {
+        let returns = vec![0.1, -0.1, 0.2, -0.2];
+        assert_eq!(Metrics::get_sharpe(&returns, 4.0), 0.0);
+        assert!((Metrics::get_downside_deviation(&returns, 4.0) - 22.360679774997898).abs() < 1e-9);
+
+        let returns = vec![0.02, -0.01, 0.02, -0.01];
+        let volatility = Metrics::get_volatility(&returns, 365.0);
+        assert!((volatility - 0.017320508075688773 * 365_f64.sqrt() * 100.0).abs() < 1e-9);
+        assert!(
+            (Metrics::get_sharpe(&returns, 365.0) - 0.005 * 365.0 * 100.0 / volatility).abs()
+                < 1e-9
+        );
+        assert!(Metrics::get_sortino(&returns, 365.0) > Metrics::get_sharpe(&returns, 365.0));
+        assert_eq!(Metrics::get_sortino(&vec![0.01, 0.02], 365.0), 0.0);
+    }
+
+    #[test]
+    fn test_get_cagr() {
+        let equity = get_equity(vec![(0, 100.0), (YEAR, 90.0), (2 * YEAR, 121.0)]);
+        assert!((Metrics::get_cagr(&equity) - 10.0).abs() < 1e-9);
+        assert_eq!(Metrics::get_cagr(&get_equity(vec![(0, 100.0)])), 0.0);
+        assert_eq!(Metrics::get_calmar(10.0, 20.0), 0.5);
+        assert_eq!(Metrics::get_calmar(10.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_get_ulcer_index() {
+        let equity = get_equity(vec![(0, 100.0), (1, 90.0), (2, 80.0), (3, 100.0)]);
+        assert!((Metrics::get_ulcer_index(&equity) - 125_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_with_benchmark() {
+        let equity = get_equity(vec![
+            (0, 100.0),
+            (DAY, 110.0),
+            (2 * DAY, 99.0),
+            (3 * DAY, 118.8),
+        ]);
+        let benchmark = get_equity(vec![
+            (0, 100.0),
+            (DAY, 120.0),
+            (2 * DAY, 96.0),
+            (3 * DAY, 134.4),
+        ]);
+        let metrics =
+            Metrics::default().with_benchmark(&equity, &benchmark, &Annualization::Interval);
+        assert!((metrics.benchmark_return - 34.4).abs() < 1e-9);
+        assert!((metrics.excess_return - (18.8 - 34.4)).abs() < 1e-9);
+        // Every return is a half of the benchmark one
+        assert!((metrics.beta - 0.5).abs() < 1e-9);
+        assert!((metrics.correlation - 1.0).abs() < 1e-9);
+        assert!(metrics.alpha.abs() < 1e-9);
+        assert!(metrics.information_ratio < 0.0);
+        assert!((metrics.up_capture - 50.0).abs() < 1e-9);
+        assert!((metrics.down_capture - 50.0).abs() < 1e-9);
+
+        // The same curve
+        let metrics =
+            Metrics::default().with_benchmark(&benchmark, &benchmark, &Annualization::Interval);
+        assert!((metrics.beta - 1.0).abs() < 1e-9);
+        assert_eq!(metrics.excess_return, 0.0);
+        assert_eq!(metrics.information_ratio, 0.0);
+    }
 }
@@ -1,4 +1,6 @@
 pub mod enums;
+pub mod equity;
+pub mod funding_rate;
 pub mod kline;
 pub mod kline_trait;
 pub mod market_data;
@@ -6,3 +8,4 @@ pub mod market_trade;
 pub mod metrics;
 pub mod order;
 pub mod position;
+pub mod symbol_rules;
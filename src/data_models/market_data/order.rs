@@ -10,6 +10,9 @@ pub struct Order {
     pub price_executed: Option<f64>,
     pub qty: Option<f64>,
     pub commission: Option<f64>,
+    /// The cost of the market order slippage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slippage: Option<f64>,
     pub order_type: OrderType,
     pub side: Side,
     pub status: OrderStatus,
@@ -24,6 +27,7 @@ impl Order {
             price_executed: None,
             qty: None,
             commission: None,
+            slippage: None,
             side,
             status: OrderStatus::default(),
             order_type,
@@ -51,6 +55,11 @@ impl Order {
         self
     }
 
+    pub fn with_slippage(mut self, slippage: f64) -> Self {
+        self.slippage = Some(slippage);
+        self
+    }
+
     pub fn filled(mut self) -> Self {
         self.status = OrderStatus::Filled;
         self
@@ -77,6 +86,11 @@ impl Order {
         self
     }
 
+    pub fn set_slippage(&mut self, slippage: f64) -> &mut Self {
+        self.slippage = Some(slippage);
+        self
+    }
+
     pub fn fill(&mut self) -> &mut Self {
         self.status = OrderStatus::Filled;
         self
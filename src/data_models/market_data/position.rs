@@ -1,5 +1,6 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use uuid::{Builder, Uuid};
 
 use super::{
     enums::{OrderStatus, Side},
@@ -13,20 +14,41 @@ pub struct Position {
     pub status: PositionStatus,
     pub orders: Vec<Order>,
     pub pnl: Option<f64>,
+    /// The positions are long unless they are the futures shorts
+    #[serde(default, skip_serializing_if = "PositionSide::is_long")]
+    pub side: PositionSide,
+    /// The funding received by the futures position, it's negative if it's paid
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub funding: f64,
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
 }
 
 #[allow(dead_code)]
 impl Position {
     pub fn new(symbol: String) -> Self {
+        Self::new_with_id(Uuid::new_v4().to_string(), symbol)
+    }
+
+    pub fn new_with_id(id: String, symbol: String) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id,
             symbol,
             status: PositionStatus::Opened,
             orders: Vec::new(),
             pnl: None,
+            side: PositionSide::Long,
+            funding: 0.0,
         }
     }
 
+    pub fn with_side(mut self, side: PositionSide) -> Self {
+        self.side = side;
+        self
+    }
+
     pub fn with_order(mut self, order: Order) -> Self {
         self.orders.push(order);
         self
@@ -97,6 +119,25 @@ impl Position {
             .sum()
     }
 
+    /// Returns the base quantity of the commission of the filled buy orders,
+    /// it's the part of the purchase that isn't received if the fee is taken from the base
+    pub fn commission_qty_buy(&self) -> f64 {
+        self.orders
+            .iter()
+            .filter(|order| order.status == OrderStatus::Filled && order.side == Side::Buy)
+            .map(|order| order.commission.unwrap() / order.price_executed.unwrap())
+            .sum()
+    }
+
+    /// Returns the cost of the slippage of the filled orders, it's already in the prices
+    pub fn slippage(&self) -> f64 {
+        self.orders
+            .iter()
+            .filter(|order| order.status == OrderStatus::Filled)
+            .filter_map(|order| order.slippage)
+            .sum()
+    }
+
     pub fn weighted_avg_price_buy(&self) -> f64 {
         self.orders
             .iter()
@@ -151,13 +192,40 @@ impl Position {
     }
 
     pub fn calculate_pnl(&mut self) {
+        let volume = match self.side {
+            PositionSide::Long => self.volume_buy(),
+            PositionSide::Short => self.volume_sell(),
+        };
         self.pnl = Some(
-            (self.weighted_avg_price_sell() - self.weighted_avg_price_buy()) * self.volume_buy()
+            (self.weighted_avg_price_sell() - self.weighted_avg_price_buy()) * volume
                 - self.commission_buy()
-                - self.commission_sell(),
+                - self.commission_sell()
+                + self.funding,
         )
     }
 
+    /// Returns the value of the position by the entry price
+    pub fn entry_value(&self) -> f64 {
+        match self.side {
+            PositionSide::Long => self.volume_buy() * self.weighted_avg_price_buy(),
+            PositionSide::Short => self.volume_sell() * self.weighted_avg_price_sell(),
+        }
+    }
+
+    /// Returns the price the isolated position is liquidated at. The margin is the entry value
+    /// divided by the `leverage`, the `maintenance_margin` is in percent of the position value.
+    pub fn liquidation_price(&self, leverage: f64, maintenance_margin: f64) -> f64 {
+        let maintenance_margin = maintenance_margin / 100.0;
+        match self.side {
+            PositionSide::Long => {
+                self.weighted_avg_price_buy() * (1.0 - 1.0 / leverage) / (1.0 - maintenance_margin)
+            }
+            PositionSide::Short => {
+                self.weighted_avg_price_sell() * (1.0 + 1.0 / leverage) / (1.0 + maintenance_margin)
+            }
+        }
+    }
+
     pub fn cancel_new_orders(&mut self, date: i64) {
         for order in self.orders.iter_mut() {
             if order.status == OrderStatus::New {
@@ -168,12 +236,85 @@ impl Position {
     }
 }
 
+/// Makes the ids of the positions. The ids are random unless there is a seed,
+/// then the same seed gives the same sequence of ids.
+/// It's serialized as the seed and the number of the ids made, the generator is restored from them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "PositionIdsState", from = "PositionIdsState")]
+pub struct PositionIds {
+    rng: Option<StdRng>,
+    seed: Option<u64>,
+    count: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PositionIdsState {
+    seed: Option<u64>,
+    count: u64,
+}
+
+impl PositionIds {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            rng: seed.map(StdRng::seed_from_u64),
+            seed,
+            count: 0,
+        }
+    }
+
+    pub fn next_id(&mut self) -> String {
+        self.count += 1;
+        match &mut self.rng {
+            Some(rng) => Builder::from_random_bytes(rng.gen()).into_uuid(),
+            None => Uuid::new_v4(),
+        }
+        .to_string()
+    }
+}
+
+impl From<PositionIds> for PositionIdsState {
+    fn from(ids: PositionIds) -> Self {
+        Self {
+            seed: ids.seed,
+            count: ids.count,
+        }
+    }
+}
+
+impl From<PositionIdsState> for PositionIds {
+    fn from(state: PositionIdsState) -> Self {
+        let mut ids = PositionIds::new(state.seed);
+        // The random ids don't depend on the ones made before
+        if let Some(rng) = &mut ids.rng {
+            for _ in 0..state.count {
+                rng.gen::<[u8; 16]>();
+            }
+        }
+        ids.count = state.count;
+        ids
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum PositionStatus {
     Opened,
     Closed,
 }
 
+/// The direction of the position, a short is opened by a sell and closed by a buy
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum PositionSide {
+    #[default]
+    Long,
+    Short,
+}
+
+impl PositionSide {
+    pub fn is_long(&self) -> bool {
+        *self == PositionSide::Long
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::data_models::market_data::enums::OrderType;
@@ -191,6 +332,7 @@ mod test {
                 price_executed: Some(100.00),
                 qty: Some(8.0),
                 commission: Some(80.0),
+                slippage: None,
                 order_type: OrderType::default(),
                 side: Side::Buy,
                 status: OrderStatus::Filled,
@@ -202,6 +344,7 @@ mod test {
                 price_executed: Some(200.00),
                 qty: Some(16.0),
                 commission: Some(320.0),
+                slippage: None,
                 order_type: OrderType::default(),
                 side: Side::Buy,
                 status: OrderStatus::Filled,
@@ -213,6 +356,7 @@ mod test {
                 price_executed: Some(300.00),
                 qty: Some(24.0),
                 commission: Some(600.0),
+                slippage: None,
                 order_type: OrderType::default(),
                 side: Side::Sell,
                 status: OrderStatus::Filled,
@@ -346,4 +490,80 @@ mod test {
         assert_eq!(p.orders[4].status, OrderStatus::Cancelled);
         assert_eq!(p.orders[4].date_update, Some(300000));
     }
+
+    #[test]
+    fn test_position_ids() {
+        let mut a = PositionIds::new(Some(1));
+        let mut b = PositionIds::new(Some(1));
+        let ids: Vec<String> = (0..3).map(|_| a.next_id()).collect();
+        assert_eq!(ids, (0..3).map(|_| b.next_id()).collect::<Vec<String>>());
+        assert_ne!(ids[0], ids[1]);
+        assert_ne!(ids[0], PositionIds::new(Some(2)).next_id());
+        assert_ne!(
+            PositionIds::new(None).next_id(),
+            PositionIds::new(None).next_id()
+        );
+
+        // The restored generator continues the sequence
+        let mut c = PositionIds::new(Some(1));
+        c.next_id();
+        c.next_id();
+        let mut restored: PositionIds =
+            serde_json::from_str(&serde_json::to_string(&c).unwrap()).unwrap();
+        assert_eq!(restored.next_id(), ids[2]);
+
+        // The random ids aren't replayed, only their number is kept
+        let mut d = PositionIds::new(None);
+        d.next_id();
+        let restored: PositionIds =
+            serde_json::from_str(&serde_json::to_string(&d).unwrap()).unwrap();
+        assert!(restored.rng.is_none());
+        assert_eq!(restored.count, 1);
+    }
+
+    #[test]
+    fn test_short() {
+        let mut p = Position::new("BTCUSDT".to_string())
+            .with_side(PositionSide::Short)
+            .with_order(
+                Order::new(1, 100.0, Side::Sell, OrderType::Limit)
+                    .with_price_executed(100.0)
+                    .with_qty(2.0)
+                    .with_commission(100.0, 2.0, 1.0)
+                    .filled(),
+            );
+        assert_eq!(p.volume_all(), -2.0);
+        assert_eq!(p.entry_value(), 200.0);
+        assert_eq!(p.liquidation_price(10.0, 0.5), 100.0 * 1.1 / 1.005);
+        // The long side isn't serialized, so the spot positions are the same as before
+        assert!(serde_json::to_string(&p)
+            .unwrap()
+            .contains("\"side\":\"Short\""));
+        assert!(!serde_json::to_string(&get_position())
+            .unwrap()
+            .contains("side\":\"Long"));
+
+        p.orders.push(
+            Order::new(2, 80.0, Side::Buy, OrderType::TakeProfit)
+                .with_price_executed(80.0)
+                .with_qty(2.0)
+                .with_commission(80.0, 2.0, 1.0)
+                .filled(),
+        );
+        p.calculate_pnl();
+        assert_eq!(p.pnl, Some(40.0 - 2.0 - 1.6));
+    }
+
+    #[test]
+    fn test_liquidation_price() {
+        let p = Position::new("BTCUSDT".to_string()).with_order(
+            Order::new(1, 100.0, Side::Buy, OrderType::Limit)
+                .with_price_executed(100.0)
+                .with_qty(1.0)
+                .filled(),
+        );
+        assert_eq!(p.liquidation_price(10.0, 0.5), 100.0 * 0.9 / 0.995);
+        // The position without the leverage is never liquidated
+        assert_eq!(p.liquidation_price(1.0, 0.5), 0.0);
+    }
 }
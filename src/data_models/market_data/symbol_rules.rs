@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+use super::order::Order;
+
+/// A filter of the Binance exchangeInfo symbol, the numbers are strings there
+#[derive(Debug, Deserialize)]
+#[serde(tag = "filterType", rename_all = "SCREAMING_SNAKE_CASE")]
+enum SymbolFilter {
+    #[serde(rename_all = "camelCase")]
+    PriceFilter {
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        tick_size: f64,
+    },
+    #[serde(rename_all = "camelCase")]
+    LotSize {
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        step_size: f64,
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        min_qty: f64,
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        max_qty: f64,
+    },
+    #[serde(rename_all = "camelCase")]
+    MinNotional {
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        min_notional: f64,
+    },
+    /// The newer symbols have it instead of the MIN_NOTIONAL
+    #[serde(rename_all = "camelCase")]
+    Notional {
+        #[serde(deserialize_with = "deserialize_number_from_string")]
+        min_notional: f64,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExchangeInfoSymbol {
+    symbol: String,
+    filters: Vec<SymbolFilter>,
+}
+
+/// The trading rules of a symbol from the exchange filters, a zero value means there is no rule
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SymbolRules {
+    /// The prices are multiples of the tick size
+    pub tick_size: f64,
+    /// The quantities are multiples of the step size
+    pub step_size: f64,
+    pub min_qty: f64,
+    pub max_qty: f64,
+    /// The minimal value of an order in the quote
+    pub min_notional: f64,
+}
+
+impl SymbolRules {
+    /// Returns the rules of all the symbols of the exchangeInfo response by the lowercase symbol
+    pub fn from_exchange_info(
+        exchange_info: &serde_json::Value,
+    ) -> serde_json::Result<HashMap<String, SymbolRules>> {
+        let symbols: Vec<ExchangeInfoSymbol> =
+            serde_json::from_value(exchange_info["symbols"].clone())?;
+        Ok(symbols
+            .into_iter()
+            .map(|symbol| {
+                let mut rules = SymbolRules::default();
+                for filter in symbol.filters {
+                    match filter {
+                        SymbolFilter::PriceFilter { tick_size } => rules.tick_size = tick_size,
+                        SymbolFilter::LotSize {
+                            step_size,
+                            min_qty,
+                            max_qty,
+                        } => {
+                            rules.step_size = step_size;
+                            rules.min_qty = min_qty;
+                            rules.max_qty = max_qty;
+                        }
+                        SymbolFilter::MinNotional { min_notional }
+                        | SymbolFilter::Notional { min_notional } => {
+                            rules.min_notional = min_notional
+                        }
+                        SymbolFilter::Other => (),
+                    }
+                }
+                (symbol.symbol.to_lowercase(), rules)
+            })
+            .collect())
+    }
+
+    /// Returns the price rounded to the nearest tick
+    pub fn round_price(&self, price: f64) -> f64 {
+        if self.tick_size <= 0.0 {
+            return price;
+        }
+        to_step_decimals(
+            (price / self.tick_size).round() * self.tick_size,
+            self.tick_size,
+        )
+    }
+
+    /// Returns the quantity rounded down to the step and cut to the maximal quantity,
+    /// so the order never costs more than it was sized for
+    pub fn round_qty(&self, qty: f64) -> f64 {
+        let qty = match self.max_qty > 0.0 {
+            true => qty.min(self.max_qty),
+            false => qty,
+        };
+        if self.step_size <= 0.0 {
+            return qty;
+        }
+        // The quantity that is a multiple of the step by the float error isn't rounded down
+        let steps = (qty / self.step_size + 1e-9).floor();
+        to_step_decimals(steps * self.step_size, self.step_size)
+    }
+
+    /// Returns true if the exchange accepts the order of the `qty` at the `price`
+    pub fn accepts(&self, price: f64, qty: f64) -> bool {
+        qty > 0.0 && qty >= self.min_qty && price * qty >= self.min_notional
+    }
+
+    /// Rounds the prices and the quantities of the orders of a new position.
+    /// Returns false if the exchange rejects any of them.
+    pub fn apply(&self, orders: &mut [Order]) -> bool {
+        for order in orders.iter_mut() {
+            order.price = self.round_price(order.price);
+            order.price_executed = order.price_executed.map(|price| self.round_price(price));
+            order.qty = order.qty.map(|qty| self.round_qty(qty));
+        }
+        orders
+            .iter()
+            .all(|order| self.accepts(order.price, order.qty.unwrap_or(0.0)))
+    }
+}
+
+/// Removes the float error of the multiple of the `step` by rounding it to the step decimals
+fn to_step_decimals(value: f64, step: f64) -> f64 {
+    let decimals = step
+        .to_string()
+        .split('.')
+        .nth(1)
+        .map_or(0, |fraction| fraction.len());
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_models::market_data::enums::{OrderType, Side};
+
+    use super::*;
+
+    fn get_rules() -> SymbolRules {
+        SymbolRules {
+            tick_size: 0.01,
+            step_size: 0.001,
+            min_qty: 0.001,
+            max_qty: 100.0,
+            min_notional: 5.0,
+        }
+    }
+
+    #[test]
+    fn test_from_exchange_info() {
+        let exchange_info = serde_json::json!({
+            "symbols": [
+                {
+                    "symbol": "BTCUSDT",
+                    "filters": [
+                        {"filterType": "PRICE_FILTER", "minPrice": "0.01000000",
+                         "maxPrice": "1000000.00000000", "tickSize": "0.01000000"},
+                        {"filterType": "LOT_SIZE", "minQty": "0.00001000",
+                         "maxQty": "9000.00000000", "stepSize": "0.00001000"},
+                        {"filterType": "ICEBERG_PARTS", "limit": 10},
+                        {"filterType": "NOTIONAL", "minNotional": "5.00000000",
+                         "applyMinToMarket": true, "maxNotional": "9000000.00000000"},
+                    ]
+                },
+                {
+                    "symbol": "ETHBTC",
+                    "filters": [
+                        {"filterType": "MIN_NOTIONAL", "minNotional": "0.00010000"},
+                    ]
+                }
+            ]
+        });
+        let rules = SymbolRules::from_exchange_info(&exchange_info).unwrap();
+        assert_eq!(
+            rules["btcusdt"],
+            SymbolRules {
+                tick_size: 0.01,
+                step_size: 0.00001,
+                min_qty: 0.00001,
+                max_qty: 9000.0,
+                min_notional: 5.0,
+            }
+        );
+        assert_eq!(rules["ethbtc"].min_notional, 0.0001);
+        assert_eq!(rules["ethbtc"].step_size, 0.0);
+    }
+
+    #[test]
+    fn test_round() {
+        let rules = get_rules();
+        assert_eq!(rules.round_price(33.333333), 33.33);
+        assert_eq!(rules.round_price(0.1 + 0.2), 0.3);
+        assert_eq!(rules.round_qty(10.0 / 33.33), 0.3);
+        assert_eq!(rules.round_qty(0.3), 0.3);
+        assert_eq!(rules.round_qty(1000.0), 100.0);
+        assert!(rules.accepts(50.0, 0.1));
+        // Below the minimal value
+        assert!(!rules.accepts(40.0, 0.1));
+        assert!(!rules.accepts(50_000.0, 0.0));
+        // The rules without the filters leave the values
+        assert_eq!(SymbolRules::default().round_qty(1.0 / 3.0), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_apply() {
+        let rules = get_rules();
+        let mut orders = vec![
+            Order::new(0, 33.333333, Side::Buy, OrderType::Limit)
+                .with_price_executed(33.333333)
+                .with_qty(10.0 / 33.333333),
+            Order::new(0, 44.444444, Side::Sell, OrderType::TakeProfit).with_qty(10.0 / 33.333333),
+        ];
+        assert!(rules.apply(&mut orders));
+        assert_eq!(orders[0].price_executed, Some(33.33));
+        assert_eq!(orders[0].qty, Some(0.3));
+        assert_eq!(orders[1].price, 44.44);
+
+        let mut orders = vec![Order::new(0, 33.33, Side::Buy, OrderType::Limit).with_qty(0.1)];
+        assert!(!rules.apply(&mut orders));
+    }
+}
@@ -11,6 +11,7 @@ pub struct BacktestResultId {
 pub struct Data {
     pub id: i64,
     pub metrics_id: i64,
+    pub strategy: String,
     pub symbol: String,
     pub exchange: String,
     pub market_data_type: MarketDataType,
@@ -19,14 +20,17 @@ pub struct Data {
     pub date_end: i64,
     pub deposit: f64,
     pub commission: f64,
-    pub price_low: f64,
-    pub price_high: f64,
-    pub grid_count: i64,
-    pub grid_trigger: f64,
+    /// The grid columns are only set for the grid results stored before the requests were saved
+    pub price_low: Option<f64>,
+    pub price_high: Option<f64>,
+    pub grid_count: Option<i64>,
+    pub grid_trigger: Option<f64>,
     pub grid_sl: Option<f64>,
     pub grid_tp: Option<f64>,
     pub sell_all: Option<bool>,
     pub positions: Vec<Position>,
+    /// The request the result was run with
+    pub request: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,3 +42,11 @@ pub struct ResultOption {
     pub date_start: String,
     pub date_end: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproducibilityCheck {
+    pub id: i64,
+    pub reproducible: bool,
+    /// The byte offset of the first difference of the positions JSON
+    pub first_difference: Option<usize>,
+}
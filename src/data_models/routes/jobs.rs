@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn value(&self) -> String {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+        .to_string()
+    }
+
+    /// Returns true if the job won't change anymore
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+impl TryFrom<String> for JobStatus {
+    type Error = String;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        match input.as_str() {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "done" => Ok(JobStatus::Done),
+            "failed" => Ok(JobStatus::Failed),
+            "cancelled" => Ok(JobStatus::Cancelled),
+            _ => Err(format!("Unknown job status: {}", input)),
+        }
+    }
+}
+
+/// The backtest request of the `strategy`, its fields are next to the strategy name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRequest {
+    #[serde(default = "default_strategy")]
+    pub strategy: String,
+    #[serde(flatten)]
+    pub request: serde_json::Value,
+}
+
+fn default_strategy() -> String {
+    "grid".into()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JobId {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    pub user_id: i64,
+    pub strategy: String,
+    pub status: JobStatus,
+    /// The part of the backtest period passed in percent
+    pub progress: f64,
+    /// The id of the backtest result when the job is done
+    pub result_id: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
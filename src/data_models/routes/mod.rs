@@ -1 +1,3 @@
 pub mod backtest_results;
+pub mod jobs;
+pub mod optimizations;
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_models::market_data::enums::MarketDataType;
+
+#[derive(Serialize, Deserialize)]
+pub struct OptimizationId {
+    pub id: i64,
+}
+
+/// The saved result table of an optimization, the best combination first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Optimization {
+    pub id: i64,
+    pub symbol: String,
+    pub exchange: String,
+    pub market_data_type: MarketDataType,
+    pub date_start: i64,
+    pub date_end: i64,
+    pub rank_by: String,
+    pub request: serde_json::Value,
+    pub results: serde_json::Value,
+}
@@ -1,10 +1,10 @@
 use sqlx::{Error, Pool, Sqlite};
 
 use crate::{
-    backtest::{settings::BacktestSettings, strategies::grid::settings::GridSettingsRequest},
-    data_handlers::utils::{datetime_str_to_i64, i64_to_datetime_str},
+    backtest::{runner::StrategyBacktest, settings::BacktestRequest},
+    data_handlers::utils::i64_to_datetime_str,
     data_models::{
-        market_data::{metrics::Metrics, position::Position},
+        market_data::{equity::EquityPoint, metrics::Metrics},
         routes::backtest_results::{Data, ResultOption},
     },
 };
@@ -36,9 +36,30 @@ pub async fn insert_metrics(metrics: &Metrics, pool: &Pool<Sqlite>) -> Result<i6
             max_deposit,
             max_drawdown,
             drawdown,
-            max_use_of_funds
+            max_use_of_funds,
+            max_drawdown_percent,
+            max_drawdown_duration,
+            drawdown_percent,
+            sharpe,
+            calmar,
+            cagr,
+            volatility,
+            downside_deviation,
+            ulcer_index,
+            benchmark_return,
+            excess_return,
+            alpha,
+            beta,
+            correlation,
+            information_ratio,
+            up_capture,
+            down_capture,
+            funding,
+            slippage,
+            fees
         ) VALUES (
-            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21,
+            ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38, ?39, ?40, ?41
         )",
         positions_number,
         profit_positions_number,
@@ -60,7 +81,27 @@ pub async fn insert_metrics(metrics: &Metrics, pool: &Pool<Sqlite>) -> Result<i6
         metrics.max_deposit,
         metrics.max_drawdown,
         metrics.drawdown,
-        metrics.max_use_of_funds
+        metrics.max_use_of_funds,
+        metrics.max_drawdown_percent,
+        metrics.max_drawdown_duration,
+        metrics.drawdown_percent,
+        metrics.sharpe,
+        metrics.calmar,
+        metrics.cagr,
+        metrics.volatility,
+        metrics.downside_deviation,
+        metrics.ulcer_index,
+        metrics.benchmark_return,
+        metrics.excess_return,
+        metrics.alpha,
+        metrics.beta,
+        metrics.correlation,
+        metrics.information_ratio,
+        metrics.up_capture,
+        metrics.down_capture,
+        metrics.funding,
+        metrics.slippage,
+        metrics.fees
     )
     .execute(pool)
     .await?;
@@ -68,23 +109,28 @@ pub async fn insert_metrics(metrics: &Metrics, pool: &Pool<Sqlite>) -> Result<i6
     Ok(result.last_insert_rowid())
 }
 
+/// Saves the data of the backtest of the `strategy`. The strategy parameters are only
+/// in the request, the grid columns are left from the time only the grid was stored.
 pub async fn insert_data(
-    backtest_settings: &BacktestSettings,
-    grid_settings: &GridSettingsRequest,
-    positions: &Vec<Position>,
+    strategy: &str,
+    result: &StrategyBacktest,
     metrics_id: i64,
     pool: &Pool<Sqlite>,
 ) -> Result<i64, Error> {
+    let backtest_settings = &result.backtest_settings;
+    let backtest_request: BacktestRequest =
+        serde_json::from_value(result.request.clone()).map_err(|e| Error::Decode(e.into()))?;
     let market_data_type = backtest_settings.market_data_type.value().0;
-    let chart_market_data_type = grid_settings.chart_market_data_type.value().0;
-    let date_start = datetime_str_to_i64(grid_settings.date_start.clone());
-    let date_end = datetime_str_to_i64(grid_settings.date_end.clone());
-    let grids_count = grid_settings.grids_count;
-    let positions = serde_json::to_string(&positions).unwrap();
+    let chart_market_data_type = backtest_request.chart_market_data_type.value().0;
+    let positions = serde_json::to_string(&result.positions).unwrap();
+    let equity = serde_json::to_string(&result.portfolio.equity.points).unwrap();
+    let benchmark_equity = serde_json::to_string(&result.benchmark).unwrap();
+    let request = result.request.to_string();
 
     let result = sqlx::query!(
         "INSERT INTO backtest_data (
             metrics_id,
+            strategy,
             symbol,
             exchange,
             market_data_type,
@@ -93,34 +139,27 @@ pub async fn insert_data(
             date_end,
             deposit,
             commission,
-            price_low,
-            price_high,
-            grid_count,
-            grid_trigger,
-            grid_sl,
-            grid_tp,
-            sell_all,
-            positions
+            positions,
+            equity,
+            benchmark_equity,
+            request
         ) VALUES (
-            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14
         )",
         metrics_id,
+        strategy,
         backtest_settings.symbols[0],
         backtest_settings.exchange,
         market_data_type,
         chart_market_data_type,
-        date_start,
-        date_end,
+        backtest_settings.date_start,
+        backtest_settings.date_end,
         backtest_settings.deposit,
         backtest_settings.commission,
-        grid_settings.price_low,
-        grid_settings.price_high,
-        grids_count,
-        grid_settings.grid_trigger,
-        grid_settings.grid_sl,
-        grid_settings.grid_tp,
-        grid_settings.sell_all,
-        positions
+        positions,
+        equity,
+        benchmark_equity,
+        request
     )
     .execute(pool)
     .await?;
@@ -128,6 +167,16 @@ pub async fn insert_data(
     Ok(result.last_insert_rowid())
 }
 
+/// Saves the metrics and the data of the backtest, returns the id of the data
+pub async fn insert_result(
+    strategy: &str,
+    result: &StrategyBacktest,
+    pool: &Pool<Sqlite>,
+) -> Result<i64, Error> {
+    let metrics_id = insert_metrics(&result.metrics, pool).await?;
+    insert_data(strategy, result, metrics_id, pool).await
+}
+
 pub async fn get_data(backtest_results_id: i64, pool: &Pool<Sqlite>) -> Result<Data, Error> {
     let row = sqlx::query!(
         "SELECT * FROM backtest_data WHERE id = ?1",
@@ -139,6 +188,7 @@ pub async fn get_data(backtest_results_id: i64, pool: &Pool<Sqlite>) -> Result<D
     let result = Data {
         id: row.id,
         metrics_id: row.metrics_id,
+        strategy: row.strategy,
         symbol: row.symbol,
         exchange: row.exchange,
         market_data_type: row.market_data_type.into(),
@@ -153,13 +203,68 @@ pub async fn get_data(backtest_results_id: i64, pool: &Pool<Sqlite>) -> Result<D
         grid_trigger: row.grid_trigger,
         grid_sl: row.grid_sl,
         grid_tp: row.grid_tp,
-        sell_all: Some(row.sell_all),
-        positions: serde_json::from_str(&row.positions).unwrap(),
+        sell_all: row.sell_all,
+        positions: serde_json::from_str(&row.positions).map_err(|e| Error::Decode(e.into()))?,
+        request: row
+            .request
+            .map(|request| serde_json::from_str(&request))
+            .transpose()
+            .map_err(|e| Error::Decode(e.into()))?,
     };
 
     Ok(result)
 }
 
+pub async fn get_equity(
+    backtest_results_id: i64,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<EquityPoint>, Error> {
+    let row = sqlx::query!(
+        "SELECT equity FROM backtest_data WHERE id = ?1",
+        backtest_results_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    serde_json::from_str(&row.equity).map_err(|e| Error::Decode(e.into()))
+}
+
+/// Returns the buy and hold equity the result is compared with
+pub async fn get_benchmark_equity(
+    backtest_results_id: i64,
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<EquityPoint>, Error> {
+    let row = sqlx::query!(
+        "SELECT benchmark_equity FROM backtest_data WHERE id = ?1",
+        backtest_results_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    serde_json::from_str(&row.benchmark_equity).map_err(|e| Error::Decode(e.into()))
+}
+
+/// Returns the strategy and the request the result was run with and its positions as they
+/// were stored. The results stored before the requests were saved have no request.
+pub async fn get_request_and_positions(
+    backtest_results_id: i64,
+    pool: &Pool<Sqlite>,
+) -> Result<(String, Option<serde_json::Value>, String), Error> {
+    let row = sqlx::query!(
+        "SELECT strategy, request, positions FROM backtest_data WHERE id = ?1",
+        backtest_results_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let request = row
+        .request
+        .map(|request| serde_json::from_str(&request))
+        .transpose()
+        .map_err(|e| Error::Decode(e.into()))?;
+    Ok((row.strategy, request, row.positions))
+}
+
 pub async fn get_data_options(pool: &Pool<Sqlite>) -> Result<Vec<ResultOption>, Error> {
     let results = sqlx::query!(
         "SELECT id, symbol, exchange, market_data_type, date_start, date_end FROM backtest_data ORDER BY id DESC LIMIT 10",
@@ -202,6 +307,12 @@ pub async fn get_metrics(backtest_results_id: i64, pool: &Pool<Sqlite>) -> Resul
         profit_factor: row.profit_factor,
         expected_payoff: row.expected_payoff,
         sortino: row.sortino,
+        sharpe: row.sharpe,
+        calmar: row.calmar,
+        cagr: row.cagr,
+        volatility: row.volatility,
+        downside_deviation: row.downside_deviation,
+        ulcer_index: row.ulcer_index,
         average_position_size: row.average_position_size,
         start_deposit: row.start_deposit,
         finish_deposit: row.finish_deposit,
@@ -209,8 +320,22 @@ pub async fn get_metrics(backtest_results_id: i64, pool: &Pool<Sqlite>) -> Resul
         total_profit_percent: row.total_profit_percent,
         max_deposit: row.max_deposit,
         max_drawdown: row.max_drawdown,
+        max_drawdown_percent: row.max_drawdown_percent,
+        max_drawdown_duration: row.max_drawdown_duration,
         drawdown: row.drawdown,
+        drawdown_percent: row.drawdown_percent,
         max_use_of_funds: row.max_use_of_funds,
+        benchmark_return: row.benchmark_return,
+        excess_return: row.excess_return,
+        alpha: row.alpha,
+        beta: row.beta,
+        correlation: row.correlation,
+        information_ratio: row.information_ratio,
+        up_capture: row.up_capture,
+        down_capture: row.down_capture,
+        funding: row.funding,
+        slippage: row.slippage,
+        fees: row.fees,
     };
 
     Ok(result)
@@ -0,0 +1,174 @@
+use chrono::Utc;
+use sqlx::{Error, Pool, Sqlite};
+
+use crate::data_models::routes::jobs::{Job, JobStatus};
+
+pub async fn insert_job(
+    user_id: i64,
+    strategy: &str,
+    request: &serde_json::Value,
+    pool: &Pool<Sqlite>,
+) -> Result<i64, Error> {
+    let status = JobStatus::Queued.value();
+    let request = request.to_string();
+    let now = Utc::now().timestamp_millis();
+
+    let result = sqlx::query!(
+        "INSERT INTO backtest_jobs (
+            user_id,
+            strategy,
+            status,
+            request,
+            created_at,
+            updated_at
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?5
+        )",
+        user_id,
+        strategy,
+        status,
+        request,
+        now
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn get_job(job_id: i64, pool: &Pool<Sqlite>) -> Result<Job, Error> {
+    let row = sqlx::query!(
+        "SELECT id, user_id, strategy, status, progress, result_id, error, created_at, updated_at
+        FROM backtest_jobs WHERE id = ?1",
+        job_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Job {
+        id: row.id,
+        user_id: row.user_id,
+        strategy: row.strategy,
+        status: row
+            .status
+            .try_into()
+            .map_err(|e: String| Error::Decode(e.into()))?,
+        progress: row.progress,
+        result_id: row.result_id,
+        error: row.error,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    })
+}
+
+/// Marks the oldest queued job as running and returns its id, strategy and request JSON.
+/// The job is taken in one statement, so two workers never get the same one.
+pub async fn claim_next_job(pool: &Pool<Sqlite>) -> Result<Option<(i64, String, String)>, Error> {
+    let queued = JobStatus::Queued.value();
+    let running = JobStatus::Running.value();
+    let now = Utc::now().timestamp_millis();
+
+    let row = sqlx::query!(
+        r#"UPDATE backtest_jobs SET status = ?1, updated_at = ?2
+        WHERE id = (SELECT id FROM backtest_jobs WHERE status = ?3 ORDER BY id LIMIT 1)
+        RETURNING id AS "id!", strategy, request"#,
+        running,
+        now,
+        queued
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| (row.id, row.strategy, row.request)))
+}
+
+pub async fn update_progress(job_id: i64, progress: f64, pool: &Pool<Sqlite>) -> Result<(), Error> {
+    let running = JobStatus::Running.value();
+    let now = Utc::now().timestamp_millis();
+
+    sqlx::query!(
+        "UPDATE backtest_jobs SET progress = ?1, updated_at = ?2 WHERE id = ?3 AND status = ?4",
+        progress,
+        now,
+        job_id,
+        running
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Sets the final status of a running job. A job cancelled meanwhile stays cancelled.
+pub async fn finish_job(
+    job_id: i64,
+    status: JobStatus,
+    result_id: Option<i64>,
+    error: Option<String>,
+    pool: &Pool<Sqlite>,
+) -> Result<(), Error> {
+    let status = status.value();
+    let running = JobStatus::Running.value();
+    let progress = if result_id.is_some() { 100.0 } else { 0.0 };
+    let now = Utc::now().timestamp_millis();
+
+    sqlx::query!(
+        "UPDATE backtest_jobs
+        SET status = ?1, result_id = ?2, error = ?3, updated_at = ?4,
+            progress = MAX(progress, ?5)
+        WHERE id = ?6 AND status = ?7",
+        status,
+        result_id,
+        error,
+        now,
+        progress,
+        job_id,
+        running
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Cancels the queued or running job of the user.
+/// Returns false if there is no such job or it's already finished.
+pub async fn cancel_job(job_id: i64, user_id: i64, pool: &Pool<Sqlite>) -> Result<bool, Error> {
+    let cancelled = JobStatus::Cancelled.value();
+    let queued = JobStatus::Queued.value();
+    let running = JobStatus::Running.value();
+    let now = Utc::now().timestamp_millis();
+
+    let result = sqlx::query!(
+        "UPDATE backtest_jobs SET status = ?1, updated_at = ?2
+        WHERE id = ?3 AND user_id = ?4 AND status IN (?5, ?6)",
+        cancelled,
+        now,
+        job_id,
+        user_id,
+        queued,
+        running
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Puts the jobs interrupted by a restart back to the queue
+pub async fn requeue_running_jobs(pool: &Pool<Sqlite>) -> Result<u64, Error> {
+    let queued = JobStatus::Queued.value();
+    let running = JobStatus::Running.value();
+    let now = Utc::now().timestamp_millis();
+
+    let result = sqlx::query!(
+        "UPDATE backtest_jobs SET status = ?1, updated_at = ?2 WHERE status = ?3",
+        queued,
+        now,
+        running
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -1,3 +1,5 @@
 pub mod backtest_results;
+pub mod jobs;
 pub mod market_data;
+pub mod optimizations;
 pub mod user;
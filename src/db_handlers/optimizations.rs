@@ -0,0 +1,91 @@
+use sqlx::{Error, Pool, Sqlite};
+
+use crate::{
+    backtest::optimizer::{GridOptimizationRequest, GridOptimizationRow},
+    data_handlers::utils::i64_to_datetime_str,
+    data_models::routes::{backtest_results::ResultOption, optimizations::Optimization},
+};
+
+pub async fn insert_optimization(
+    request: &GridOptimizationRequest,
+    results: &Vec<GridOptimizationRow>,
+    pool: &Pool<Sqlite>,
+) -> Result<i64, Error> {
+    let backtest_settings = request.settings.backtest.backtest_settings();
+    let market_data_type = backtest_settings.market_data_type.value().0;
+    let request_json = serde_json::to_string(&request).unwrap();
+    let results = serde_json::to_string(&results).unwrap();
+
+    let result = sqlx::query!(
+        "INSERT INTO backtest_optimizations (
+            symbol,
+            exchange,
+            market_data_type,
+            date_start,
+            date_end,
+            rank_by,
+            request,
+            results
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
+        )",
+        backtest_settings.symbols[0],
+        backtest_settings.exchange,
+        market_data_type,
+        backtest_settings.date_start,
+        backtest_settings.date_end,
+        request.rank_by,
+        request_json,
+        results
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn get_optimization(
+    optimization_id: i64,
+    pool: &Pool<Sqlite>,
+) -> Result<Optimization, Error> {
+    let row = sqlx::query!(
+        "SELECT * FROM backtest_optimizations WHERE id = ?1",
+        optimization_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let result = Optimization {
+        id: row.id,
+        symbol: row.symbol,
+        exchange: row.exchange,
+        market_data_type: row.market_data_type.into(),
+        date_start: row.date_start,
+        date_end: row.date_end,
+        rank_by: row.rank_by,
+        request: serde_json::from_str(&row.request).map_err(|e| Error::Decode(e.into()))?,
+        results: serde_json::from_str(&row.results).map_err(|e| Error::Decode(e.into()))?,
+    };
+
+    Ok(result)
+}
+
+pub async fn get_optimization_options(pool: &Pool<Sqlite>) -> Result<Vec<ResultOption>, Error> {
+    let results = sqlx::query!(
+        "SELECT id, symbol, exchange, market_data_type, date_start, date_end FROM backtest_optimizations ORDER BY id DESC LIMIT 10",
+    )
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|row| ResultOption {
+        id: row.id,
+        symbol: row.symbol.clone(),
+        exchange: row.exchange.clone(),
+        market_data_type: row.market_data_type.clone().into(),
+        date_start: i64_to_datetime_str(row.date_start),
+        date_end: i64_to_datetime_str(row.date_end),
+    })
+    .collect();
+
+    Ok(results)
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use log::{error, info};
+use sqlx::SqlitePool;
+
+use crate::{
+    backtest::{progress::Progress, strategies::registry::find_strategy},
+    config::AppSettings,
+    data_models::routes::jobs::JobStatus,
+    db_handlers::{backtest_results::insert_result, jobs},
+};
+
+/// How long an idle worker waits before it looks for a queued job again
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How often the progress of a running job is written to the database
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The progress of the jobs running in this process by the job id
+#[derive(Debug, Clone, Default)]
+pub struct RunningJobs(Arc<Mutex<HashMap<i64, Progress>>>);
+
+impl RunningJobs {
+    pub fn get(&self, job_id: i64) -> Option<Progress> {
+        self.0.lock().unwrap().get(&job_id).cloned()
+    }
+
+    fn insert(&self, job_id: i64, progress: Progress) {
+        self.0.lock().unwrap().insert(job_id, progress);
+    }
+
+    fn remove(&self, job_id: i64) {
+        self.0.lock().unwrap().remove(&job_id);
+    }
+}
+
+/// Puts the jobs interrupted by the last shutdown back to the queue and starts
+/// the workers. Every worker runs one backtest at a time.
+pub async fn start_workers(
+    app_settings: Arc<AppSettings>,
+    pool: Arc<SqlitePool>,
+    running: RunningJobs,
+) {
+    match jobs::requeue_running_jobs(&pool).await {
+        Ok(0) => (),
+        Ok(count) => info!("{} interrupted backtest jobs are queued again", count),
+        Err(e) => error!("Error requeueing backtest jobs: {}", e),
+    }
+    for _ in 0..app_settings.jobs_workers.max(1) {
+        tokio::spawn(worker(app_settings.clone(), pool.clone(), running.clone()));
+    }
+}
+
+async fn worker(app_settings: Arc<AppSettings>, pool: Arc<SqlitePool>, running: RunningJobs) {
+    loop {
+        match jobs::claim_next_job(&pool).await {
+            Ok(Some((job_id, strategy, request))) => {
+                run_job(job_id, &strategy, request, &app_settings, &pool, &running).await
+            }
+            Ok(None) => tokio::time::sleep(POLL_INTERVAL).await,
+            Err(e) => {
+                error!("Error claiming a backtest job: {}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Fails the job that can't be run, it's already marked as running
+async fn fail_job(job_id: i64, error: String, pool: &SqlitePool) {
+    if let Err(e) = jobs::finish_job(job_id, JobStatus::Failed, None, Some(error), pool).await {
+        error!("Error finishing the job {}: {}", job_id, e);
+    }
+}
+
+async fn run_job(
+    job_id: i64,
+    strategy: &str,
+    request: String,
+    app_settings: &AppSettings,
+    pool: &SqlitePool,
+    running: &RunningJobs,
+) {
+    let Some(definition) = find_strategy(strategy) else {
+        fail_job(job_id, format!("Unknown strategy: {}", strategy), pool).await;
+        return;
+    };
+    // The job could be queued with a request of an older version
+    let request: serde_json::Value = match serde_json::from_str(&request) {
+        Ok(request) => request,
+        Err(e) => {
+            fail_job(job_id, format!("Invalid request: {}", e), pool).await;
+            return;
+        }
+    };
+    let progress = Progress::default();
+    running.insert(job_id, progress.clone());
+
+    let data_path = PathBuf::from(app_settings.data_path.clone());
+    let mut handle = tokio::task::spawn_blocking({
+        let progress = progress.clone();
+        move || (definition.run)(&request, data_path, progress)
+    });
+    let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            result = &mut handle => break result,
+            _ = ticker.tick() => {
+                if let Err(e) = jobs::update_progress(job_id, progress.percent(), pool).await {
+                    error!("Error updating the progress of the job {}: {}", job_id, e);
+                }
+            }
+        }
+    };
+
+    // The status of a cancelled job is already set by the one who cancelled it
+    if progress.is_cancelled() {
+        running.remove(job_id);
+        return;
+    }
+    let finished = match result {
+        Ok(Ok(result)) => match insert_result(strategy, &result, pool).await {
+            Ok(result_id) => {
+                jobs::finish_job(job_id, JobStatus::Done, Some(result_id), None, pool).await
+            }
+            Err(e) => {
+                error!("Error inserting the results of the job {}: {}", job_id, e);
+                jobs::finish_job(job_id, JobStatus::Failed, None, Some(e.to_string()), pool).await
+            }
+        },
+        Ok(Err(e)) => jobs::finish_job(job_id, JobStatus::Failed, None, Some(e), pool).await,
+        Err(e) => {
+            error!("The backtest job {} failed: {}", job_id, e);
+            jobs::finish_job(job_id, JobStatus::Failed, None, Some(e.to_string()), pool).await
+        }
+    };
+    if let Err(e) = finished {
+        error!("Error finishing the job {}: {}", job_id, e);
+    }
+    // The progress is removed after the final status is saved,
+    // so the events of the job don't miss the last progress
+    running.remove(job_id);
+}
@@ -5,6 +5,7 @@ mod data_handlers;
 mod data_models;
 mod database;
 mod db_handlers;
+mod jobs;
 mod routes;
 mod server;
 mod tests;
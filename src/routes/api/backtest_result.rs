@@ -1,9 +1,20 @@
 use std::{fs, path::PathBuf};
 
-use actix_web::{error::ErrorInternalServerError, web, Error, HttpResponse};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    web, Error, HttpResponse,
+};
 
 use crate::{
-    app_state::AppState, data_models::routes::backtest_results::BacktestResultId,
+    app_state::AppState,
+    backtest::{
+        monte_carlo::{simulate, MonteCarloRequest},
+        progress::Progress,
+        runner::first_difference,
+        settings::BacktestRequest,
+        strategies::registry::find_strategy,
+    },
+    data_models::routes::backtest_results::{BacktestResultId, ReproducibilityCheck},
     db_handlers::backtest_results,
 };
 
@@ -40,3 +51,70 @@ pub async fn metrics(
         .map_err(ErrorInternalServerError)?;
     Ok(HttpResponse::Ok().json(result))
 }
+
+pub async fn equity(
+    data: web::Data<AppState>,
+    r: web::Query<BacktestResultId>,
+) -> Result<HttpResponse, Error> {
+    let result = backtest_results::get_equity(r.id, &data.pool)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+pub async fn benchmark(
+    data: web::Data<AppState>,
+    r: web::Query<BacktestResultId>,
+) -> Result<HttpResponse, Error> {
+    let result = backtest_results::get_benchmark_equity(r.id, &data.pool)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+pub async fn monte_carlo(
+    data: web::Data<AppState>,
+    r: web::Query<MonteCarloRequest>,
+) -> Result<HttpResponse, Error> {
+    let request = r.into_inner();
+    request.validate().map_err(ErrorBadRequest)?;
+    let result = backtest_results::get_data(request.id, &data.pool)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    let report = web::block(move || simulate(&result.positions, result.deposit, &request)).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Re-runs the stored result and compares the positions with the stored ones
+pub async fn reproduce(
+    data: web::Data<AppState>,
+    r: web::Query<BacktestResultId>,
+) -> Result<HttpResponse, Error> {
+    let (strategy, request, stored_positions) =
+        backtest_results::get_request_and_positions(r.id, &data.pool)
+            .await
+            .map_err(ErrorInternalServerError)?;
+    let definition = find_strategy(&strategy).ok_or(ErrorBadRequest("Unknown strategy"))?;
+    let seeded = request
+        .as_ref()
+        .and_then(|request| serde_json::from_value::<BacktestRequest>(request.clone()).ok())
+        .is_some_and(|request| request.seed.is_some());
+    let request = match request {
+        Some(request) if seeded => request,
+        _ => {
+            return Err(ErrorBadRequest(
+                "The result wasn't run with a seed, so it can't be reproduced",
+            ))
+        }
+    };
+    let data_path = PathBuf::from(data.app_settings.data_path.clone());
+    let result = web::block(move || (definition.run)(&request, data_path, Progress::default()))
+        .await?
+        .map_err(ErrorBadRequest)?;
+    let first_difference = first_difference(&result, &stored_positions);
+    Ok(HttpResponse::Ok().json(ReproducibilityCheck {
+        id: r.id,
+        reproducible: first_difference.is_none(),
+        first_difference,
+    }))
+}
@@ -1,14 +1,16 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{collections::HashSet, fs, path::PathBuf};
 
 use crate::{
-    app_state::AppState, data_handlers::bin_files::get_filenames,
-    data_models::market_data::enums::MarketDataType,
+    app_state::AppState,
+    data_handlers::bin_files::{get_filenames, rules_file_name},
+    data_models::market_data::{enums::MarketDataType, symbol_rules::SymbolRules},
 };
 use actix_web::{
     web::{self},
     HttpRequest, HttpResponse,
 };
 use cached::proc_macro::cached;
+use log::warn;
 use strum::IntoEnumIterator;
 
 pub async fn exchanges(_data: web::Data<AppState>) -> Result<HttpResponse, actix_web::Error> {
@@ -49,7 +51,7 @@ pub async fn internal_symbols(
 }
 
 pub async fn external_symbols(
-    _data: web::Data<AppState>,
+    data: web::Data<AppState>,
     req: HttpRequest,
 ) -> Result<HttpResponse, actix_web::Error> {
     let exchange_name: String = req.match_info().get("exchange").unwrap().parse().unwrap();
@@ -69,6 +71,21 @@ pub async fn external_symbols(
         .collect();
     symbols.sort();
 
+    // The trading rules of the symbols are kept for the backtests
+    let data_path = PathBuf::from(data.app_settings.data_path.clone());
+    let rules_path = data_path.join(rules_file_name(exchange_name));
+    match SymbolRules::from_exchange_info(&json_body) {
+        Ok(rules) => {
+            if let Err(e) = fs::write(&rules_path, serde_json::to_string(&rules)?) {
+                warn!(
+                    "Failed to save the trading rules to {:?}: {}",
+                    rules_path, e
+                );
+            }
+        }
+        Err(e) => warn!("Failed to parse the trading rules: {}", e),
+    }
+
     Ok(HttpResponse::Ok().json(symbols))
 }
 
@@ -100,7 +117,8 @@ pub async fn internal_mdts(
         let filename = fullfilename.split(".").collect::<Vec<&str>>()[0];
         let filename_parts: Vec<&str> = filename.split("-").collect();
         let symbol = filename_parts[1].to_string().to_uppercase();
-        if symbol == symbol_name {
+        // The funding rates are loaded with the klines, they can't be run alone
+        if symbol == symbol_name && filename_parts[2] != MarketDataType::FundingRate.value().0 {
             mdts.push(filename_parts[2].to_string());
         }
     }
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use actix_web::error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorNotFound};
+use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse, Responder, Result};
+use actix_web_lab::sse::{self, Sse};
+use log::error;
+use tokio::sync::mpsc;
+
+use crate::app_state::AppState;
+use crate::backtest::strategies::registry::find_strategy;
+use crate::data_models::routes::jobs::{Job, JobId, JobRequest, JobStatus};
+use crate::data_models::user::User;
+use crate::db_handlers::jobs;
+
+use super::backtest::check_trial_access;
+
+/// How often the state of the job is checked for the events
+const EVENTS_INTERVAL: Duration = Duration::from_millis(500);
+
+pub async fn submit(
+    req: HttpRequest,
+    job_request: web::Json<JobRequest>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let definition =
+        find_strategy(&job_request.strategy).ok_or(ErrorNotFound("Unknown strategy"))?;
+    (definition.validate)(&job_request.request).map_err(ErrorBadRequest)?;
+    let user = req.extensions().get::<User>().unwrap().clone();
+    if !check_trial_access(&data.pool, &user).await {
+        return Err(ErrorForbidden("Trial access limit reached"));
+    }
+    let job_id = match jobs::insert_job(
+        user.user_id,
+        definition.name,
+        &job_request.request,
+        &data.pool,
+    )
+    .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Error inserting backtest job: {}", e);
+            return Err(ErrorInternalServerError(e));
+        }
+    };
+    Ok(HttpResponse::Ok().json(JobId { id: job_id }))
+}
+
+pub async fn status(
+    req: HttpRequest,
+    job_id: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let mut job = user_job(&req, *job_id, &data).await?;
+    // The running job reports its progress more often than it's saved
+    if job.status == JobStatus::Running {
+        if let Some(progress) = data.jobs.get(job.id) {
+            job.progress = progress.percent();
+        }
+    }
+    Ok(HttpResponse::Ok().json(job))
+}
+
+pub async fn cancel(
+    req: HttpRequest,
+    job_id: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let job = user_job(&req, *job_id, &data).await?;
+    let cancelled = jobs::cancel_job(job.id, job.user_id, &data.pool)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    if cancelled {
+        if let Some(progress) = data.jobs.get(job.id) {
+            progress.cancel();
+        }
+    }
+    let job = jobs::get_job(job.id, &data.pool)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(job))
+}
+
+/// Streams the Server-Sent Events of the job: `progress` with the state of the running
+/// backtest and `status` with the job every time its status changes. The stream ends
+/// after the job is finished.
+pub async fn events(
+    req: HttpRequest,
+    job_id: web::Path<i64>,
+    data: web::Data<AppState>,
+) -> Result<impl Responder, Error> {
+    let job = user_job(&req, *job_id, &data).await?;
+    let (sender, receiver) = mpsc::channel(10);
+    actix_web::rt::spawn(send_events(job.id, data, sender));
+    Ok(Sse::from_infallible_receiver(receiver).with_keep_alive(Duration::from_secs(15)))
+}
+
+async fn send_events(job_id: i64, data: web::Data<AppState>, sender: mpsc::Sender<sse::Event>) {
+    let mut interval = tokio::time::interval(EVENTS_INTERVAL);
+    let mut last_status = None;
+    let mut last_progress = None;
+    loop {
+        interval.tick().await;
+        let job = match jobs::get_job(job_id, &data.pool).await {
+            Ok(job) => job,
+            Err(e) => {
+                error!("Error getting the job {}: {}", job_id, e);
+                return;
+            }
+        };
+        if let Some(progress) = data.jobs.get(job_id).map(|progress| progress.event()) {
+            if last_progress.as_ref() != Some(&progress) {
+                let event = sse::Data::new_json(&progress).unwrap().event("progress");
+                if sender.send(event.into()).await.is_err() {
+                    return;
+                }
+                last_progress = Some(progress);
+            }
+        }
+        if last_status != Some(job.status) {
+            last_status = Some(job.status);
+            let event = sse::Data::new_json(&job).unwrap().event("status");
+            if sender.send(event.into()).await.is_err() {
+                return;
+            }
+        }
+        if job.status.is_finished() {
+            return;
+        }
+    }
+}
+
+/// Returns the job if it belongs to the user of the request
+async fn user_job(req: &HttpRequest, job_id: i64, data: &AppState) -> Result<Job, Error> {
+    let user_id = req.extensions().get::<User>().unwrap().user_id;
+    let job = match jobs::get_job(job_id, &data.pool).await {
+        Ok(job) => job,
+        Err(sqlx::Error::RowNotFound) => return Err(ErrorNotFound("Job not found")),
+        Err(e) => return Err(ErrorInternalServerError(e)),
+    };
+    if job.user_id != user_id {
+        return Err(ErrorNotFound("Job not found"));
+    }
+    Ok(job)
+}
@@ -10,10 +10,13 @@ use crate::{
         utils::{datetime_str_to_i64, i64_to_datetime_str},
     },
     data_models::market_data::{
+        enums::MarketDataType,
+        funding_rate::FundingRate,
         kline::KLine,
         market_data::{
             GetMarketDataRequest, MarketDataDatesRequest, MarketDataDatesResponse, MarketDataFront,
         },
+        market_trade::MarketTrade,
     },
     db_handlers::market_data,
 };
@@ -35,16 +38,44 @@ pub async fn download_market_data(
 ) -> Result<HttpResponse, Error> {
     let data_path = PathBuf::from(data.app_settings.data_path.clone());
 
-    pipeline::pipeline::<KLine>(
-        data_path.clone(),
-        data.app_settings.binance_data_url.clone(),
-        r.exchange.to_lowercase(),
-        r.symbol.to_lowercase(),
-        r.market_data_type.clone(),
-        datetime_str_to_i64(r.date_start.clone()),
-        datetime_str_to_i64(r.date_end.clone()),
-    )
-    .await;
+    match r.market_data_type {
+        MarketDataType::Trade => {
+            pipeline::pipeline::<MarketTrade>(
+                data_path.clone(),
+                data.app_settings.binance_data_url.clone(),
+                r.exchange.to_lowercase(),
+                r.symbol.to_lowercase(),
+                r.market_data_type.clone(),
+                datetime_str_to_i64(r.date_start.clone()),
+                datetime_str_to_i64(r.date_end.clone()),
+            )
+            .await
+        }
+        MarketDataType::FundingRate => {
+            pipeline::pipeline::<FundingRate>(
+                data_path.clone(),
+                data.app_settings.binance_data_url.clone(),
+                r.exchange.to_lowercase(),
+                r.symbol.to_lowercase(),
+                r.market_data_type.clone(),
+                datetime_str_to_i64(r.date_start.clone()),
+                datetime_str_to_i64(r.date_end.clone()),
+            )
+            .await
+        }
+        _ => {
+            pipeline::pipeline::<KLine>(
+                data_path.clone(),
+                data.app_settings.binance_data_url.clone(),
+                r.exchange.to_lowercase(),
+                r.symbol.to_lowercase(),
+                r.market_data_type.clone(),
+                datetime_str_to_i64(r.date_start.clone()),
+                datetime_str_to_i64(r.date_end.clone()),
+            )
+            .await
+        }
+    }
 
     let insert_id = market_data::insert_market_data(
         &data.pool,
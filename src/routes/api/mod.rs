@@ -2,5 +2,7 @@ pub mod auth;
 pub mod backtest;
 pub mod backtest_result;
 pub mod exchange;
+pub mod jobs;
 pub mod market_data;
+pub mod optimization;
 pub mod pages;
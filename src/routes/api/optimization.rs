@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
+use actix_web::{web, Error, HttpResponse, Result};
+use log::error;
+
+use crate::app_state::AppState;
+use crate::backtest::optimizer::{optimize, GridOptimizationRequest};
+use crate::backtest::walk_forward::{walk_forward, WalkForwardRequest};
+use crate::data_models::routes::optimizations::OptimizationId;
+use crate::db_handlers::optimizations;
+
+pub async fn optimize_grid(
+    request: web::Json<GridOptimizationRequest>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let request = request.into_inner();
+    request.validate().map_err(ErrorBadRequest)?;
+    let data_path = PathBuf::from(data.app_settings.data_path.clone());
+    // The backtests are heavy, so they are run out of the async workers
+    let (request, results) = web::block(move || {
+        let results = optimize(&request, data_path);
+        (request, results)
+    })
+    .await?;
+    let results = results.map_err(ErrorBadRequest)?;
+    let optimization_id =
+        match optimizations::insert_optimization(&request, &results, &data.pool).await {
+            Ok(id) => id,
+            Err(e) => {
+                error!("Error inserting optimization results: {}", e);
+                return Err(ErrorInternalServerError(e));
+            }
+        };
+    Ok(HttpResponse::Ok().json(OptimizationId {
+        id: optimization_id,
+    }))
+}
+
+pub async fn results(
+    data: web::Data<AppState>,
+    r: web::Query<OptimizationId>,
+) -> Result<HttpResponse, Error> {
+    let result = optimizations::get_optimization(r.id, &data.pool)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+pub async fn options(data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    let result = optimizations::get_optimization_options(&data.pool)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+pub async fn walk_forward_grid(
+    request: web::Json<WalkForwardRequest>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let request = request.into_inner();
+    request.validate().map_err(ErrorBadRequest)?;
+    let data_path = PathBuf::from(data.app_settings.data_path.clone());
+    let report = web::block(move || walk_forward(&request, data_path))
+        .await?
+        .map_err(ErrorBadRequest)?;
+    Ok(HttpResponse::Ok().json(report))
+}
@@ -13,6 +13,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
+    backtest::strategies::registry::strategies,
     db_handlers::user::{check_user_by_id, get_user},
 };
 use jsonwebtoken::{decode, DecodingKey, Validation};
@@ -60,6 +61,8 @@ pub async fn rbac_middleware(
             "/api/backtest/result/options",
             "/api/backtest/result/chart",
             "/api/backtest/result/metrics",
+            "/api/backtest/result/equity",
+            "/api/backtest/result/benchmark",
         ],
     );
 
@@ -67,12 +70,32 @@ pub async fn rbac_middleware(
 
     access_map.insert(
         "GridBacktestViewer",
-        vec!["/pages/grid-backtest", "/api/backtest/result/data"],
+        vec![
+            "/pages/grid-backtest",
+            "/pages/hodl-backtest",
+            "/api/backtest/strategies",
+            "/api/backtest/result/data",
+            "/api/backtest/result/monte-carlo",
+            "/api/backtest/optimization/results",
+            "/api/backtest/optimization/options",
+        ],
     );
 
-    let grid_backtest_runner = vec!["/api/backtest/hodl/run", "/api/backtest/grid/run"];
-
-    access_map.insert("GridBacktestRunner", grid_backtest_runner.clone());
+    // Every registered strategy has its run route
+    let run_routes: Vec<String> = strategies()
+        .iter()
+        .map(|strategy| format!("/api/backtest/{}/run", strategy.name))
+        .collect();
+    let mut grid_backtest_runner: Vec<&str> = run_routes.iter().map(String::as_str).collect();
+    grid_backtest_runner.push("/api/jobs");
+
+    // These run many backtests at once or re-run a stored one, so they're not a part of the trial
+    let mut grid_backtest_optimizer = grid_backtest_runner.clone();
+    grid_backtest_optimizer.push("/api/backtest/grid/optimize");
+    grid_backtest_optimizer.push("/api/backtest/grid/walk-forward");
+    grid_backtest_optimizer.push("/api/backtest/result/reproduce");
+
+    access_map.insert("GridBacktestRunner", grid_backtest_optimizer);
     access_map.insert("GridBacktestTrialRunner", grid_backtest_runner.clone());
 
     let claims = get_claims(&mut req).await;
@@ -9,6 +9,7 @@ use crate::{
     app_state::AppState,
     config::AppSettings,
     database,
+    jobs::{self, RunningJobs},
     routes::{api, middlewares},
     web::template::template,
 };
@@ -36,8 +37,17 @@ pub async fn start_server() -> std::io::Result<()> {
         app_settings: Arc::new(settings),
         tera: Arc::new(template()),
         pool: Arc::new(pool),
+        jobs: RunningJobs::default(),
     });
 
+    // Start the workers of the backtest jobs queue
+    jobs::start_workers(
+        app_data.app_settings.clone(),
+        app_data.pool.clone(),
+        app_data.jobs.clone(),
+    )
+    .await;
+
     HttpServer::new(move || {
     let cors = Cors::default()
         .allow_any_origin()
@@ -67,9 +77,22 @@ pub async fn start_server() -> std::io::Result<()> {
         .route("/api/market-data/download",web::post().to(api::market_data::download_market_data))
         .route("/api/market-data/date-input",web::get().to(api::market_data::market_data_dates))
         .route("/api/market-data/klines", web::get().to(api::market_data::klines))
-        .route("/api/backtest/grid/run", web::post().to(api::backtest::run_grid))
+        .route("/api/backtest/strategies", web::get().to(api::backtest::strategies))
+        .route("/api/backtest/{strategy}/run", web::post().to(api::backtest::run))
         .route("/api/backtest/result/data", web::get().to(api::backtest_result::data))
         .route("/api/backtest/result/metrics", web::get().to(api::backtest_result::metrics))
+        .route("/api/backtest/result/equity", web::get().to(api::backtest_result::equity))
+        .route("/api/backtest/result/benchmark", web::get().to(api::backtest_result::benchmark))
+        .route("/api/backtest/result/monte-carlo", web::get().to(api::backtest_result::monte_carlo))
+        .route("/api/backtest/result/reproduce", web::get().to(api::backtest_result::reproduce))
+        .route("/api/backtest/grid/optimize", web::post().to(api::optimization::optimize_grid))
+        .route("/api/backtest/grid/walk-forward", web::post().to(api::optimization::walk_forward_grid))
+        .route("/api/backtest/optimization/results", web::get().to(api::optimization::results))
+        .route("/api/backtest/optimization/options", web::get().to(api::optimization::options))
+        .route("/api/jobs", web::post().to(api::jobs::submit))
+        .route("/api/jobs/{id}", web::get().to(api::jobs::status))
+        .route("/api/jobs/{id}", web::delete().to(api::jobs::cancel))
+        .route("/api/jobs/{id}/events", web::get().to(api::jobs::events))
         })
         .bind(("0.0.0.0", 8080))?
         .run()